@@ -20,9 +20,101 @@ macro_rules! slice_cnt {
     ($vec: ident, $from: expr, $length: literal) => { $vec[$from..($from+$length)].try_into().unwrap_or([0; $length]) };
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Aseprite {
     pub header: AsepriteHeader,
-    pub frames: Vec<AsepriteFrame>
+    pub frames: Vec<AsepriteFrame>,
+
+    /// Anything off about the file that didn't stop it being read, in the order
+    /// it was come across.
+    pub warnings: Vec<String>
+}
+
+impl Aseprite {
+    /// Builds the sprite's palette from every palette chunk in the file, applied
+    /// in order. Entries no chunk sets are left as transparent black.
+    /// 
+    /// The old palette chunks are only used when the file has no new ones, as
+    /// newer versions of Aseprite still write both.
+    pub fn palette(&self) -> Vec<[u8; 4]> {
+        let mut palette: Vec<[u8; 4]> = vec![];
+        let chunks = || self.frames.iter().flat_map(|x| x.chunks.iter());
+
+        let has_new_palette = chunks().any(|x| matches!(x, Chunk::Palette(_)));
+
+        for chunk in chunks() {
+            match chunk {
+                Chunk::Palette(pchunk) => {
+                    palette.resize((pchunk.palette_size as usize).min(MAX_PALETTE_SIZE), [0; 4]);
+
+                    for (i, entry) in pchunk.entries.iter().enumerate() {
+                        let Some(slot) = palette.get_mut(pchunk.first_index as usize + i) else { break };
+                        *slot = entry.rgba;
+                    }
+                },
+                Chunk::OldPalette(opchunk) if !has_new_palette => {
+                    let mut index = 0;
+
+                    for packet in &opchunk.packets {
+                        index += packet.skip as usize;
+
+                        for [r, g, b] in &packet.colours {
+                            if palette.len() <= index {
+                                palette.resize(index + 1, [0; 4]);
+                            }
+
+                            palette[index] = [*r, *g, *b, 255];
+                            index += 1;
+                        }
+                    }
+                },
+                _ => ()
+            }
+        }
+
+        palette
+    }
+
+    /// Gets the name given to each entry of `palette`, if it was given one. Only
+    /// the newer palette chunks have names.
+    pub fn palette_names(&self) -> Vec<Option<String>> {
+        let mut names: Vec<Option<String>> = vec![];
+
+        for chunk in self.frames.iter().flat_map(|x| x.chunks.iter()) {
+            let Chunk::Palette(pchunk) = chunk else { continue };
+            names.resize((pchunk.palette_size as usize).min(MAX_PALETTE_SIZE), None);
+
+            for (i, entry) in pchunk.entries.iter().enumerate() {
+                let Some(slot) = names.get_mut(pchunk.first_index as usize + i) else { break };
+                *slot = entry.name.as_ref().and_then(|x| x.as_str().ok()).map(|x| x.to_owned());
+            }
+        }
+
+        names
+    }
+
+    /// Gets the colour profile of the sprite, if the file has one.
+    pub fn color_profile(&self) -> Option<&AsepriteColorProfileChunk> {
+        self.frames.iter()
+            .flat_map(|x| x.chunks.iter())
+            .find_map(|x| match x { Chunk::ColorProfile(c) => Some(c), _ => None })
+    }
+
+    /// Gets every slice in the file, in the order they were read.
+    pub fn slices(&self) -> Vec<&AsepriteSliceChunk> {
+        self.frames.iter()
+            .flat_map(|x| x.chunks.iter())
+            .filter_map(|x| match x { Chunk::Slice(s) => Some(s), _ => None })
+            .collect()
+    }
+
+    /// Gets every tileset in the file, in the order their chunks show up.
+    pub fn tilesets(&self) -> Vec<&AsepriteTilesetChunk> {
+        self.frames.iter()
+            .flat_map(|x| x.chunks.iter())
+            .filter_map(|x| match x { Chunk::Tileset(t) => Some(t), _ => None })
+            .collect()
+    }
 }
 
 const ASEPRITE_MAGIC_HEADER: u16 = 0xA5E0;
@@ -34,20 +126,40 @@ pub struct AsepriteString {
 }
 
 impl AsepriteString {
-    pub fn read_from_bytes(from: &[u8]) -> Self {
-        let length = slice_to!(u16, &from[0..2]);
-        let data = from[2..].to_vec();
-        Self {
+    /// Reads a string prefixed by its length, returning it with how many bytes
+    /// of `from` it took up. If `from` is too short for the length it says it
+    /// has, only the bytes that are there are kept; see
+    /// `AsepriteString::is_truncated`.
+    pub fn read_from_bytes(from: &[u8]) -> (Self, usize) {
+        let length = slice_to!(u16, from.get(0..2).unwrap_or_default());
+        let end = (2 + length as usize).min(from.len());
+        let data = from.get(2..end).unwrap_or_default().to_vec();
+        (Self {
             length,
             data
-        }
+        }, end)
     }
 
     pub fn as_str(&self) -> Result<&str, std::str::Utf8Error> {
-        std::str::from_utf8(&self.data[..self.length as usize])
+        std::str::from_utf8(&self.data)
+    }
+
+    /// Checks if the string's length runs past the bytes it was read from.
+    pub fn is_truncated(&self) -> bool {
+        self.length as usize > self.data.len()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for AsepriteString {
+    /// Written as the text itself rather than its bytes, with anything that
+    /// isn't valid UTF-8 replaced.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&String::from_utf8_lossy(&self.data))
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AsepriteHeader {
     pub fsize:           u32,
 	pub magic:           u16,
@@ -56,9 +168,11 @@ pub struct AsepriteHeader {
 	pub height:          u16,
 	pub colour_depth:    u16,
 	pub flags:           u32,
+	/// Deprecated in favour of the duration each frame has.
 	pub speed:           u16,
 	    // zero:            [u8; 8],
-	// pub palette_entry:   u8,
+	/// The palette index that is fully transparent in indexed sprites.
+	pub palette_entry:   u8,
 	    // ignore:          [u8; 3],
 	pub colour_count:    u16,
 	pub pixel_width:     u8,
@@ -73,6 +187,7 @@ pub struct AsepriteHeader {
 
 const READ_HEADER_SIZE: usize = 128; // size_of::<AsepriteHeader>();
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AsepriteFrame {
     pub size: u32,
     pub magic: u16,
@@ -84,11 +199,19 @@ pub struct AsepriteFrame {
     pub chunks: Vec<Chunk>
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Chunk {
     Unknown(RawAsepriteChunk),
     Layer(AsepriteLayerChunk),
     Cel(AsepriteCelChunk),
-    Tag(AsepriteTagChunk)
+    Tag(AsepriteTagChunk),
+    Palette(AsepritePaletteChunk),
+    OldPalette(AsepriteOldPaletteChunk),
+    Tileset(AsepriteTilesetChunk),
+    Slice(AsepriteSliceChunk),
+    UserData(AsepriteUserDataChunk),
+    ColorProfile(AsepriteColorProfileChunk),
+    CelExtra(AsepriteCelExtraChunk)
 }
 
 impl Chunk {
@@ -98,10 +221,18 @@ impl Chunk {
             Self::Layer(_)   => "layer",
             Self::Cel(_)     => "cel",
             Self::Tag(_)     => "tag",
+            Self::Palette(_) => "palette",
+            Self::OldPalette(_) => "old palette",
+            Self::Tileset(_) => "tileset",
+            Self::Slice(_)   => "slice",
+            Self::UserData(_) => "user data",
+            Self::ColorProfile(_) => "color profile",
+            Self::CelExtra(_) => "cel extra",
         }
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RawAsepriteChunk {
     pub size: u32,
     pub chunk_type: u16,
@@ -111,6 +242,7 @@ pub struct RawAsepriteChunk {
 
 #[repr(u16)]
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum AsepriteBlendMode {
     Normal     =  0,
     Multiply   =  1,
@@ -133,9 +265,14 @@ pub enum AsepriteBlendMode {
     Divide     = 18
 }
 
+impl AsepriteBlendMode {
+    /// How many blend modes there are.
+    pub const COUNT: u16 = 19;
+}
+
 impl From<u16> for AsepriteBlendMode {
     fn from(value: u16) -> Self {
-        match value % 19 {
+        match value % Self::COUNT {
              0 => Self::Normal,
              1 => Self::Multiply,
              2 => Self::Screen,
@@ -189,6 +326,7 @@ impl Display for AsepriteBlendMode {
 
 #[repr(u16)]
 #[derive(PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum AsepriteLayerType {
     Normal = 0,
     Group,
@@ -207,6 +345,7 @@ impl From<u16> for AsepriteLayerType {
 }
 
 const ASEPRITE_LAYER_CHUNK_MAGIC: u16 = 0x2004;
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AsepriteLayerChunk {
     pub flags: u16,
     pub layer_type: AsepriteLayerType,
@@ -218,7 +357,10 @@ pub struct AsepriteLayerChunk {
         // future: [u8; 3],
     pub name: AsepriteString,
 
-    // pub tileset_index: Option<u32> // only if layer type == 2
+    /// The id of the tileset the layer's tiles come from, for tilemap layers.
+    pub tileset_index: Option<u32>,
+
+    pub user_data: Option<AsepriteUserDataChunk>
 }
 
 #[repr(u16)]
@@ -242,6 +384,7 @@ impl BitAnd<AsepriteLayerFlags> for u16 {
 
 #[repr(u16)]
 #[derive(PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum AsepriteCelType {
     Raw = 0,
     Linked,
@@ -273,13 +416,14 @@ impl Display for AsepriteCelType {
 }
 
 const ASEPRITE_CEL_CHUNK_MAGIC: u16 = 0x2005;
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AsepriteCelChunk {
     pub layer_index: u16,
     pub x_pos: i16,
     pub y_pos: i16,
     pub opacity: u8,
     pub cel_type: AsepriteCelType,
-    // pub z_index: i16,
+    pub z_index: i16,
         // future: [u8; 5],
 
     // cel type 0
@@ -296,11 +440,40 @@ pub struct AsepriteCelChunk {
     pub compressed_data: Option<Rc<[u8]>>,
 
     // cel type 3
-    // i don't want to support it
+    // reuse width and height, counted in tiles
+    // reuse compressed_data
+    pub tile_masks: Option<AsepriteTileMasks>,
+    /// Every tile in the tilemap row by row, still holding the flip bits.
+    pub tiles:      Option<Vec<u32>>,
+
+    /// Precise bounds, for cels on reference layers.
+    pub extra:     Option<AsepriteCelExtraChunk>,
+    pub user_data: Option<AsepriteUserDataChunk>
+}
+
+/// Says which bits of a tilemap cel's tiles hold what.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepriteTileMasks {
+    pub bits_per_tile: u16,
+    pub tile_id:       u32,
+    pub x_flip:        u32,
+    pub y_flip:        u32,
+    pub diagonal_flip: u32,
+        // reserved: [u8; 10],
+}
+
+impl AsepriteTileMasks {
+    /// Splits a tile into its id and whether it's flipped on x, on y and
+    /// diagonally.
+    pub fn split(&self, tile: u32) -> (u32, bool, bool, bool) {
+        (tile & self.tile_id, tile & self.x_flip > 0, tile & self.y_flip > 0, tile & self.diagonal_flip > 0)
+    }
 }
 
 #[derive(Clone, Copy)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum AsepriteTagDirection {
     Forward = 0,
     Reverse,
@@ -332,6 +505,7 @@ impl Display for AsepriteTagDirection {
 }
 
 const ASEPRITE_TAG_CHUNK_MAGIC: u16 = 0x2018;
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AsepriteTagChunk {
     pub tag_count: u16,
         // future: [u8; 8],
@@ -339,15 +513,267 @@ pub struct AsepriteTagChunk {
     pub tags: Vec<AsepriteTag>
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AsepriteTag {
     pub from: u16,
     pub to: u16,
     pub direction: AsepriteTagDirection,
     pub repeat_count: u16,
         // reserved: [u8; 6],
-        // colour: [u8; 3],
+    /// The colour from before tags kept it in their user data.
+    pub colour: [u8; 3],
         // extra: u8,
-    pub name: AsepriteString
+    pub name: AsepriteString,
+
+    pub user_data: Option<AsepriteUserDataChunk>
+}
+
+impl AsepriteTag {
+    /// Gets the colour the tag is shown with. Newer versions of Aseprite keep it
+    /// in the tag's user data and only fill in `colour` for older readers.
+    pub fn display_colour(&self) -> [u8; 4] {
+        match self.user_data.as_ref().and_then(|x| x.colour) {
+            Some(rgba) => rgba,
+            None => [self.colour[0], self.colour[1], self.colour[2], 255],
+        }
+    }
+}
+
+const ASEPRITE_PALETTE_CHUNK_MAGIC: u16 = 0x2019;
+/// The most palette entries that will be kept, so a corrupt palette size can't
+/// ask for a huge allocation.
+const MAX_PALETTE_SIZE: usize = 1 << 16;
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepritePaletteChunk {
+    pub palette_size: u32,
+    pub first_index:  u32,
+    pub last_index:   u32,
+        // future: [u8; 8],
+
+    pub entries: Vec<AsepritePaletteEntry>
+}
+
+/// Set in a palette entry's flags when the entry has a name.
+const ASEPRITE_PALETTE_ENTRY_HAS_NAME: u16 = 1;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepritePaletteEntry {
+        // flags: u16, only says if there's a name
+    pub rgba:  [u8; 4],
+    pub name:  Option<AsepriteString>
+}
+
+/// Used by old versions of Aseprite, with 8 bit colour channels.
+const ASEPRITE_OLD_PALETTE_CHUNK_MAGIC:   u16 = 0x0004;
+/// Used by even older versions of Aseprite, with 6 bit colour channels.
+const ASEPRITE_OLDER_PALETTE_CHUNK_MAGIC: u16 = 0x0011;
+
+/// Both kinds of old palette chunk, with the colours already scaled to 8 bits.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepriteOldPaletteChunk {
+    pub packet_count: u16,
+    pub packets: Vec<AsepriteOldPalettePacket>
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepriteOldPalettePacket {
+    /// How many palette entries to skip over from where the last packet ended.
+    pub skip:    u8,
+    pub colours: Vec<[u8; 3]>
+}
+
+const ASEPRITE_TILESET_CHUNK_MAGIC: u16 = 0x2023;
+
+#[repr(u32)]
+pub enum AsepriteTilesetFlags {
+    ExternalFile = 1,
+    TilesInFile  = 1 << 1,
+    EmptyTileIs0 = 1 << 2,
+}
+
+impl BitAnd<AsepriteTilesetFlags> for u32 {
+    type Output = u32;
+
+    fn bitand(self, rhs: AsepriteTilesetFlags) -> Self::Output {
+        self & rhs as Self::Output
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepriteTilesetChunk {
+    pub id:          u32,
+    pub flags:       u32,
+    pub tile_count:  u32,
+    pub tile_width:  u16,
+    pub tile_height: u16,
+    /// The number tile 1 is shown as in Aseprite's UI, doesn't affect the data.
+    pub base_index:  i16,
+        // reserved: [u8; 14],
+    pub name:        AsepriteString,
+
+    /// The external file id and the id of the tileset inside it, if the
+    /// tileset comes from another file.
+    pub external:    Option<(u32, u32)>,
+    /// The pixels of every tile, one after the other, if the tiles are kept
+    /// inside this file.
+    pub tiles:       Option<Vec<Rc<[u8]>>>,
+}
+
+const ASEPRITE_SLICE_CHUNK_MAGIC: u16 = 0x2022;
+
+#[repr(u32)]
+pub enum AsepriteSliceFlags {
+    NinePatch = 1,
+    HasPivot  = 1 << 1,
+}
+
+impl BitAnd<AsepriteSliceFlags> for u32 {
+    type Output = u32;
+
+    fn bitand(self, rhs: AsepriteSliceFlags) -> Self::Output {
+        self & rhs as Self::Output
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepriteSliceChunk {
+    pub key_count: u32,
+    pub flags:     u32,
+        // reserved: u32,
+    pub name:      AsepriteString,
+
+    pub keys: Vec<AsepriteSliceKey>,
+
+    pub user_data: Option<AsepriteUserDataChunk>
+}
+
+/// Where a slice is from a frame onwards, until the next key.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepriteSliceKey {
+    pub frame:  u32,
+    pub x:      i32,
+    pub y:      i32,
+    pub width:  u32,
+    pub height: u32,
+
+    /// The middle of a 9-patch slice, relative to the slice.
+    pub center: Option<AsepriteSliceCenter>,
+    /// The pivot, relative to the slice.
+    pub pivot:  Option<(i32, i32)>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepriteSliceCenter {
+    pub x:      i32,
+    pub y:      i32,
+    pub width:  u32,
+    pub height: u32,
+}
+
+const ASEPRITE_USER_DATA_CHUNK_MAGIC: u16 = 0x2020;
+
+#[repr(u32)]
+pub enum AsepriteUserDataFlags {
+    Text       = 1,
+    Colour     = 1 << 1,
+    Properties = 1 << 2,
+}
+
+impl BitAnd<AsepriteUserDataFlags> for u32 {
+    type Output = u32;
+
+    fn bitand(self, rhs: AsepriteUserDataFlags) -> Self::Output {
+        self & rhs as Self::Output
+    }
+}
+
+/// Text and a colour the user gave to a layer, cel, tag, or slice. These are
+/// moved onto the chunk they belong to while reading, and are only left in the
+/// frame's chunks if there was nothing to give them to.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepriteUserDataChunk {
+    pub flags:  u32,
+    pub text:   Option<AsepriteString>,
+    pub colour: Option<[u8; 4]>,
+        // properties: not read yet, with `AsepriteUserDataFlags::Properties`
+}
+
+const ASEPRITE_COLOR_PROFILE_CHUNK_MAGIC: u16 = 0x2007;
+
+#[repr(u16)]
+#[derive(PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum AsepriteColorProfileType {
+    None = 0,
+    Srgb,
+    EmbeddedIcc
+}
+
+impl From<u16> for AsepriteColorProfileType {
+    fn from(value: u16) -> Self {
+        match value % 3 {
+            0 => Self::None,
+            1 => Self::Srgb,
+            2 => Self::EmbeddedIcc,
+            _ => unreachable!("should be impossible value from modulo bound")
+        }
+    }
+}
+
+impl Display for AsepriteColorProfileType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None        => f.write_str("None"),
+            Self::Srgb        => f.write_str("sRGB"),
+            Self::EmbeddedIcc => f.write_str("Embedded ICC"),
+        }
+    }
+}
+
+/// Set in `AsepriteColorProfileChunk::flags` when `gamma` should be used.
+const ASEPRITE_COLOR_PROFILE_FIXED_GAMMA: u16 = 1;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepriteColorProfileChunk {
+    pub profile_type: AsepriteColorProfileType,
+    pub flags:        u16,
+    /// Only meant to be used if the fixed gamma flag is set, 1.0 is linear.
+    pub gamma:        f32,
+        // reserved: [u8; 8],
+
+    /// The ICC profile itself, for embedded profiles.
+    pub icc_data:     Option<Rc<[u8]>>,
+}
+
+impl AsepriteColorProfileChunk {
+    /// Gets the gamma if the file says to use it.
+    pub fn fixed_gamma(&self) -> Option<f32> {
+        (self.flags & ASEPRITE_COLOR_PROFILE_FIXED_GAMMA > 0).then_some(self.gamma)
+    }
+}
+
+const ASEPRITE_CEL_EXTRA_CHUNK_MAGIC: u16 = 0x2006;
+
+/// Set in `AsepriteCelExtraChunk::flags` when the precise bounds are set.
+const ASEPRITE_CEL_EXTRA_PRECISE_BOUNDS: u32 = 1;
+
+/// Where a cel really is, with sub-pixel positions and its scaled size. These
+/// are moved onto the cel they belong to while reading.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AsepriteCelExtraChunk {
+    pub flags:  u32,
+    pub x:      f32,
+    pub y:      f32,
+    pub width:  f32,
+    pub height: f32,
+        // reserved: [u8; 16],
+}
+
+impl AsepriteCelExtraChunk {
+    /// Gets the precise position and size, if they were set.
+    pub fn precise_bounds(&self) -> Option<(f32, f32, f32, f32)> {
+        (self.flags & ASEPRITE_CEL_EXTRA_PRECISE_BOUNDS > 0).then_some((self.x, self.y, self.width, self.height))
+    }
 }
 
 #[derive(Debug)]
@@ -367,6 +793,12 @@ impl Error for AsepriteError {
     }
 }
 
+impl From<io::Error> for AsepriteError {
+    fn from(value: io::Error) -> Self {
+        Self::Other(Box::new(value))
+    }
+}
+
 impl Display for AsepriteError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -379,12 +811,504 @@ impl Display for AsepriteError {
     }
 }
 
+/// Gives user data to the chunk it belongs to, which is the chunk read just
+/// before it. A tag chunk is followed by user data for each of its tags in turn,
+/// so `tags_done` keeps count of how many have been given theirs.
+/// 
+/// Returns the user data back if there's nothing for it to go on.
+fn attach_user_data(owner: Option<&mut Chunk>, tags_done: &mut usize, user_data: AsepriteUserDataChunk) -> Option<AsepriteUserDataChunk> {
+    let slot = match owner {
+        Some(Chunk::Layer(lchunk)) => &mut lchunk.user_data,
+        Some(Chunk::Cel(cchunk))   => &mut cchunk.user_data,
+        Some(Chunk::Slice(schunk)) => &mut schunk.user_data,
+        Some(Chunk::Tag(tchunk))   => match tchunk.tags.get_mut(*tags_done) {
+            Some(tag) => {
+                *tags_done += 1;
+                &mut tag.user_data
+            },
+            None => return Some(user_data),
+        },
+        _ => return Some(user_data),
+    };
+
+    if slot.is_some() {
+        return Some(user_data);
+    }
+
+    *slot = Some(user_data);
+    None
+}
+
+/// Turns a 16.16 fixed point number into a float.
+fn fixed_point(value: i32) -> f32 {
+    value as f32 / 65536.0
+}
+
+/// Makes sure a chunk is big enough for the fixed fields read out of it.
+fn check_chunk_size(data: &[u8], chunk_type: u16, min_size: usize) -> Result<(), AsepriteError> {
+    if data.len() < min_size {
+        return Err(AsepriteError::Other(format!(
+            "chunk {chunk_type:#06x} is {} bytes, needs at least {min_size}", data.len()
+        ).into()));
+    }
+
+    Ok(())
+}
+
+/// Decompresses zlib data, like the pixels of compressed cels.
+fn inflate(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut z = ZlibDecoder::new(data);
+    let mut r = vec![];
+    z.read_to_end(&mut r)?;
+
+    Ok(r)
+}
+
+/// Decompresses the pixels of every compressed image cel, split between as many
+/// threads as there are cores since each cel is independent of the others.
+fn inflate_cels(frames: &mut [AsepriteFrame]) -> Result<(), AsepriteError> {
+    let mut cels: Vec<&mut AsepriteCelChunk> = frames.iter_mut()
+        .flat_map(|x| x.chunks.iter_mut())
+        .filter_map(|x| match x {
+            Chunk::Cel(c) if matches!(c.cel_type, AsepriteCelType::CompressedImage) => Some(c),
+            _ => None,
+        })
+        .collect();
+
+    if cels.is_empty() {
+        return Ok(());
+    }
+
+    let compressed: Vec<&[u8]> = cels.iter().map(|x| x.compressed_data.as_deref().unwrap_or_default()).collect();
+    let threads = std::thread::available_parallelism().map_or(1, |x| x.get());
+    let per_thread = compressed.len().div_ceil(threads);
+
+    let inflated: Vec<io::Result<Vec<u8>>> = std::thread::scope(|s| {
+        let workers: Vec<_> = compressed.chunks(per_thread)
+            .map(|batch| s.spawn(move || batch.iter().map(|x| inflate(x)).collect::<Vec<_>>()))
+            .collect();
+
+        workers.into_iter()
+            .flat_map(|x| x.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    });
+
+    for (cel, pixels) in cels.iter_mut().zip(inflated) {
+        cel.raw_data = Some(pixels?.into());
+    }
+
+    Ok(())
+}
+
+/// Reads a single chunk out of `data`, which holds all of it including its size
+/// and type.
+fn read_chunk(chunk_type: u16, data: &[u8], header: &AsepriteHeader, warnings: &mut Vec<String>) -> Result<Chunk, AsepriteError> {
+    let size = data.len() as u32;
+
+    check_chunk_size(data, chunk_type, match chunk_type {
+        ASEPRITE_LAYER_CHUNK_MAGIC         => 22,
+        ASEPRITE_CEL_CHUNK_MAGIC           => 22,
+        ASEPRITE_TAG_CHUNK_MAGIC           => 16,
+        ASEPRITE_PALETTE_CHUNK_MAGIC       => 26,
+        ASEPRITE_CEL_EXTRA_CHUNK_MAGIC     => 26,
+        ASEPRITE_COLOR_PROFILE_CHUNK_MAGIC => 14,
+        ASEPRITE_USER_DATA_CHUNK_MAGIC     => 10,
+        ASEPRITE_SLICE_CHUNK_MAGIC         => 18,
+        ASEPRITE_TILESET_CHUNK_MAGIC       => 38,
+        ASEPRITE_OLD_PALETTE_CHUNK_MAGIC | ASEPRITE_OLDER_PALETTE_CHUNK_MAGIC => 8,
+        // every chunk has at least its size and type
+        _ => 6
+    })?;
+
+    Ok(match chunk_type {
+        ASEPRITE_LAYER_CHUNK_MAGIC => {
+            let layer_type = AsepriteLayerType::from(slice_to!(u16, &data[ 8..10]));
+            let is_tilemap = layer_type == AsepriteLayerType::Tilemap;
+            Chunk::Layer(AsepriteLayerChunk {
+                flags:          slice_to!(u16, &data[ 6.. 8]),
+                // layer_type                       [08..10]
+                child_level:    slice_to!(u16, &data[10..12]),
+                // default_width:  slice_to!(u16, &data[12..14]),
+                // default_height: slice_to!(u16, &data[14..16]),
+                blend_mode:     AsepriteBlendMode::from(slice_to!(u16, &data[16..18])),
+                opacity:        data[18],
+                // future:         slice_cnt!(data, 19, 3),
+                name:           AsepriteString::read_from_bytes(
+                                    data.get(22..(data.len().saturating_sub(if is_tilemap { 4 } else { 0 }))).unwrap_or_default()
+                                ).0,
+
+                tileset_index:  is_tilemap.then(|| slice_to!(u32, data.get((data.len() - 4)..).unwrap_or_default())),
+
+                layer_type,
+                user_data: None
+            })
+        },
+        ASEPRITE_CEL_CHUNK_MAGIC => {
+            let mut r = Chunk::Cel(AsepriteCelChunk {
+                layer_index: slice_to!(u16, &data[ 6.. 8]),
+                x_pos:       slice_to!(i16, &data[ 8..10]),
+                y_pos:       slice_to!(i16, &data[10..12]),
+                opacity:     data[12],
+                cel_type:    AsepriteCelType::from(slice_to!(u16, &data[13..15])),
+                z_index:     slice_to!(i16, &data[15..17]),
+                // future:      slice_cnt!(data, 17, 5),
+                
+                // cel specific fields set below
+                // cel type 0
+                width: None,
+                height: None,
+                raw_data: None,
+                
+                // cel type 1
+                linked_to: None,
+                
+                // cel type 2
+                // width,
+                // height,
+                compressed_data: None,
+
+                // cel type 3
+                // width,
+                // height,
+                // compressed_data,
+                tile_masks: None,
+                tiles: None,
+
+                extra: None,
+                user_data: None
+            });
+
+            if let Chunk::Cel(ref mut c) = &mut r {
+                check_chunk_size(data, chunk_type, match c.cel_type {
+                    AsepriteCelType::Raw               => 26,
+                    AsepriteCelType::Linked            => 24,
+                    AsepriteCelType::CompressedImage   => 26,
+                    AsepriteCelType::CompressedTilemap => 54,
+                })?;
+
+                match c.cel_type {
+                    AsepriteCelType::Raw => {
+                        c.width =    Some(slice_to!(u16, &data[22..24]));
+                        c.height =   Some(slice_to!(u16, &data[24..26]));
+                        c.raw_data = Some(data[26..].into());
+                    },
+
+                    AsepriteCelType::Linked => {
+                        c.linked_to = Some(slice_to!(u16, &data[22..24]));
+                    },
+
+                    AsepriteCelType::CompressedImage => {
+                        c.width =           Some(slice_to!(u16, &data[22..24]));
+                        c.height =          Some(slice_to!(u16, &data[24..26]));
+                        // decompressed along with every other cel once the whole
+                        // file's been read, see `inflate_cels`
+                        c.compressed_data = Some(data[26..].into());
+                    },
+
+                    AsepriteCelType::CompressedTilemap => {
+                        c.width =           Some(slice_to!(u16, &data[22..24]));
+                        c.height =          Some(slice_to!(u16, &data[24..26]));
+                        let masks = AsepriteTileMasks {
+                            bits_per_tile:  slice_to!(u16, &data[26..28]),
+                            tile_id:        slice_to!(u32, &data[28..32]),
+                            x_flip:         slice_to!(u32, &data[32..36]),
+                            y_flip:         slice_to!(u32, &data[36..40]),
+                            diagonal_flip:  slice_to!(u32, &data[40..44]),
+                            // reserved:       slice_cnt!(data, 44, 10),
+                        };
+                        c.compressed_data = Some(data[54..].into());
+
+                        let tile_data = inflate(&data[54..])?;
+                        c.tiles = Some(match masks.bits_per_tile {
+                             8 => tile_data.iter().map(|x| *x as u32).collect(),
+                            16 => tile_data.chunks_exact(2).map(|x| slice_to!(u16, x) as u32).collect(),
+                            _  => tile_data.chunks_exact(4).map(|x| slice_to!(u32, x)).collect(),
+                        });
+                        c.tile_masks = Some(masks);
+                    },
+                };
+            }
+
+            r
+        },
+        ASEPRITE_TAG_CHUNK_MAGIC => {
+            let mut tag_data = AsepriteTagChunk {
+                tag_count:  slice_to!(u16, &data[6..8]),
+                // future:     slice_cnt!(data, 8, 8),
+                tags:       Vec::<AsepriteTag>::new() 
+            };
+
+            let mut offset = 16;
+            for tag_index in 0..tag_data.tag_count {
+                if data.len() < 19 + offset {
+                    warnings.push(format!("tag chunk ran out after {tag_index} of {} tags", tag_data.tag_count));
+                    break;
+                }
+
+                // a bad length only cuts the name short, the string keeps track of it
+                let (name, name_size) = AsepriteString::read_from_bytes(&data[(17 + offset)..]);
+
+                tag_data.tags.push(AsepriteTag {
+                    from:           slice_to!(u16, &data[(    offset)..(2 + offset)]),
+                    to:             slice_to!(u16, &data[(2 + offset)..(4 + offset)]),
+                    direction:      AsepriteTagDirection::from(data[4 + offset]),
+                    repeat_count:   slice_to!(u16, &data[(5 + offset)..(7 + offset)]),
+                    // reserved:       slice_cnt!(data, {7 + offset}, 6),
+                    colour:         [data[13 + offset], data[14 + offset], data[15 + offset]],
+                    // extra:          data[ 16 + offset],
+                    name,
+                    user_data:      None
+                });
+
+                if tag_data.tags.last().is_some_and(|x| x.name.is_truncated()) {
+                    warnings.push(format!("tag {tag_index} has a name longer than its chunk"));
+                }
+
+                offset += 17 + name_size
+            }
+
+            Chunk::Tag(tag_data)
+        },
+        ASEPRITE_PALETTE_CHUNK_MAGIC => {
+            let mut palette_data = AsepritePaletteChunk {
+                palette_size: slice_to!(u32, &data[ 6..10]),
+                first_index:  slice_to!(u32, &data[10..14]),
+                last_index:   slice_to!(u32, &data[14..18]),
+                // future:       slice_cnt!(data, 18, 8),
+                entries:      Vec::<AsepritePaletteEntry>::new()
+            };
+
+            let mut offset = 26;
+            for entry_index in palette_data.first_index..=palette_data.last_index {
+                if data.len() < 6 + offset {
+                    warnings.push(format!("palette chunk ran out at entry {entry_index}"));
+                    break;
+                }
+
+                let flags = slice_to!(u16, &data[offset..(2 + offset)]);
+                let rgba = [data[2 + offset], data[3 + offset], data[4 + offset], data[5 + offset]];
+                offset += 6;
+
+                // the name has to be stepped over even if it isn't wanted, or
+                // every entry after it is read from the wrong place
+                let name = if flags & ASEPRITE_PALETTE_ENTRY_HAS_NAME > 0 {
+                    let (name, name_size) = AsepriteString::read_from_bytes(data.get(offset..).unwrap_or_default());
+                    offset += name_size;
+                    Some(name)
+                } else { None };
+
+                palette_data.entries.push(AsepritePaletteEntry { rgba, name });
+            }
+
+            Chunk::Palette(palette_data)
+        },
+        ASEPRITE_CEL_EXTRA_CHUNK_MAGIC => {
+            Chunk::CelExtra(AsepriteCelExtraChunk {
+                flags:  slice_to!(u32, &data[ 6..10]),
+                x:      fixed_point(slice_to!(i32, &data[10..14])),
+                y:      fixed_point(slice_to!(i32, &data[14..18])),
+                width:  fixed_point(slice_to!(i32, &data[18..22])),
+                height: fixed_point(slice_to!(i32, &data[22..26])),
+                // reserved: slice_cnt!(data, 26, 16),
+            })
+        },
+        ASEPRITE_COLOR_PROFILE_CHUNK_MAGIC => {
+            let profile_type = AsepriteColorProfileType::from(slice_to!(u16, &data[6..8]));
+
+            Chunk::ColorProfile(AsepriteColorProfileChunk {
+                profile_type,
+                flags:        slice_to!(u16, &data[ 8..10]),
+                gamma:        fixed_point(slice_to!(i32, &data[10..14])),
+                // reserved:     slice_cnt!(data, 14, 8),
+                icc_data:     if profile_type == AsepriteColorProfileType::EmbeddedIcc {
+                                  let icc_len = slice_to!(u32, data.get(22..26).unwrap_or_default()) as usize;
+                                  data.get(26..((26 + icc_len).min(data.len()))).map(|x| x.into())
+                              } else { None },
+            })
+        },
+        ASEPRITE_USER_DATA_CHUNK_MAGIC => {
+            let mut user_data = AsepriteUserDataChunk {
+                flags:  slice_to!(u32, &data[6..10]),
+                text:   None,
+                colour: None,
+            };
+
+            let mut offset = 10;
+
+            if user_data.flags & AsepriteUserDataFlags::Text > 0 {
+                let (text, text_size) = AsepriteString::read_from_bytes(data.get(offset..).unwrap_or_default());
+                user_data.text = Some(text);
+                offset += text_size;
+            }
+
+            if user_data.flags & AsepriteUserDataFlags::Colour > 0 {
+                user_data.colour = data.get(offset..(4 + offset)).map(|x| [x[0], x[1], x[2], x[3]]);
+            }
+
+            Chunk::UserData(user_data)
+        },
+        ASEPRITE_SLICE_CHUNK_MAGIC => {
+            let (name, name_size) = AsepriteString::read_from_bytes(data.get(18..).unwrap_or_default());
+
+            let mut slice = AsepriteSliceChunk {
+                key_count: slice_to!(u32, &data[ 6..10]),
+                flags:     slice_to!(u32, &data[10..14]),
+                // reserved:  slice_to!(u32, &data[14..18]),
+                name,
+                keys:      Vec::<AsepriteSliceKey>::new(),
+                user_data: None
+            };
+
+            let nine_patch = slice.flags & AsepriteSliceFlags::NinePatch > 0;
+            let has_pivot = slice.flags & AsepriteSliceFlags::HasPivot > 0;
+
+            // the optional parts are only there when their flags are set
+            let key_size = 20 + if nine_patch { 16 } else { 0 } + if has_pivot { 8 } else { 0 };
+
+            let mut offset = 18 + name_size;
+            for key_index in 0..slice.key_count {
+                let Some(key) = data.get(offset..(key_size + offset)) else {
+                    warnings.push(format!("slice chunk ran out after {key_index} of {} keys", slice.key_count));
+                    break;
+                };
+
+                let mut extra = 20;
+
+                let center = if nine_patch {
+                    extra += 16;
+                    Some(AsepriteSliceCenter {
+                        x:      slice_to!(i32, &key[20..24]),
+                        y:      slice_to!(i32, &key[24..28]),
+                        width:  slice_to!(u32, &key[28..32]),
+                        height: slice_to!(u32, &key[32..36]),
+                    })
+                } else { None };
+
+                let pivot = if has_pivot {
+                    Some((
+                        slice_to!(i32, &key[extra..(4 + extra)]),
+                        slice_to!(i32, &key[(4 + extra)..(8 + extra)]),
+                    ))
+                } else { None };
+
+                slice.keys.push(AsepriteSliceKey {
+                    frame:  slice_to!(u32, &key[ 0.. 4]),
+                    x:      slice_to!(i32, &key[ 4.. 8]),
+                    y:      slice_to!(i32, &key[ 8..12]),
+                    width:  slice_to!(u32, &key[12..16]),
+                    height: slice_to!(u32, &key[16..20]),
+                    center, pivot
+                });
+
+                offset += key_size;
+            }
+
+            Chunk::Slice(slice)
+        },
+        ASEPRITE_TILESET_CHUNK_MAGIC => {
+            let mut tileset = AsepriteTilesetChunk {
+                id:          slice_to!(u32, &data[ 6..10]),
+                flags:       slice_to!(u32, &data[10..14]),
+                tile_count:  slice_to!(u32, &data[14..18]),
+                tile_width:  slice_to!(u16, &data[18..20]),
+                tile_height: slice_to!(u16, &data[20..22]),
+                base_index:  slice_to!(i16, &data[22..24]),
+                // reserved:    slice_cnt!(data, 24, 14),
+                name:        AsepriteString::read_from_bytes(&[]).0,
+                external:    None,
+                tiles:       None,
+            };
+
+            let (name, name_size) = AsepriteString::read_from_bytes(data.get(38..).unwrap_or_default());
+            tileset.name = name;
+
+            let mut offset = 38 + name_size;
+
+            if tileset.flags & AsepriteTilesetFlags::ExternalFile > 0 {
+                tileset.external = Some((
+                    slice_to!(u32, data.get(offset..(4 + offset)).unwrap_or_default()),
+                    slice_to!(u32, data.get((4 + offset)..(8 + offset)).unwrap_or_default()),
+                ));
+                offset += 8;
+            }
+
+            if tileset.flags & AsepriteTilesetFlags::TilesInFile > 0 {
+                let compressed_len = slice_to!(u32, data.get(offset..(4 + offset)).unwrap_or_default()) as usize;
+                let compressed_end = ((4 + offset) + compressed_len).min(data.len());
+                let strip = inflate(data.get((4 + offset)..compressed_end).unwrap_or_default())?;
+
+                // tiles are stacked on top of each other in one tall image
+                let tile_size = tileset.tile_width as usize * tileset.tile_height as usize
+                    * (header.colour_depth as usize / 8);
+
+                tileset.tiles = Some(match tile_size {
+                    0 => vec![],
+                    _ => strip.chunks(tile_size).map(|x| x.into()).collect(),
+                });
+            }
+
+            Chunk::Tileset(tileset)
+        },
+        ASEPRITE_OLD_PALETTE_CHUNK_MAGIC | ASEPRITE_OLDER_PALETTE_CHUNK_MAGIC => {
+            let mut palette_data = AsepriteOldPaletteChunk {
+                packet_count: slice_to!(u16, &data[6..8]),
+                packets:      Vec::<AsepriteOldPalettePacket>::new()
+            };
+
+            let six_bit = chunk_type == ASEPRITE_OLDER_PALETTE_CHUNK_MAGIC;
+
+            let mut offset = 8;
+            'packets: for packet_index in 0..palette_data.packet_count {
+                if data.len() < 2 + offset {
+                    warnings.push(format!("old palette chunk ran out at packet {packet_index}"));
+                    break;
+                }
+
+                let skip = data[offset];
+                // 0 colours in a packet means all 256 of them
+                let colour_count = match data[1 + offset] { 0 => 256, n => n as usize };
+                offset += 2;
+
+                let mut packet = AsepriteOldPalettePacket { skip, colours: Vec::with_capacity(colour_count) };
+
+                for _ in 0..colour_count {
+                    let Some(rgb) = data.get(offset..(3 + offset)) else {
+                        warnings.push(format!("old palette chunk ran out in packet {packet_index}"));
+                        palette_data.packets.push(packet);
+                        break 'packets;
+                    };
+
+                    packet.colours.push(if six_bit {
+                        [0, 1, 2].map(|i| ((rgb[i] as u16 * 255) / 63).min(255) as u8)
+                    } else {
+                        [rgb[0], rgb[1], rgb[2]]
+                    });
+
+                    offset += 3;
+                }
+
+                palette_data.packets.push(packet);
+            }
+
+            Chunk::OldPalette(palette_data)
+        },
+        _ => Chunk::Unknown(RawAsepriteChunk { size, chunk_type, data: data.into() })
+    })
+}
+
+/// Reads a whole file from `from`. It's read into memory first and then parsed
+/// with `read_from_slice`.
 pub fn read<T: io::Read + io::Seek>(from: &mut T) -> Result<Aseprite, AsepriteError> {
-    let mut header: Vec<u8> = vec![0; READ_HEADER_SIZE];
-    
-    match from.read(&mut header) {
-        Ok(count) => if count != READ_HEADER_SIZE { return Err(AsepriteError::RanOutAtHeader) },
-        Err(e) => return Err(AsepriteError::Other(Box::new(e)))
+    let mut bytes = vec![];
+    from.read_to_end(&mut bytes)?;
+
+    read_from_slice(&bytes)
+}
+
+/// Reads a whole file that's already in memory.
+pub fn read_from_slice(from: &[u8]) -> Result<Aseprite, AsepriteError> {
+    let Some(header) = from.get(0..READ_HEADER_SIZE) else {
+        return Err(AsepriteError::RanOutAtHeader);
     };
     
     let mut result = Aseprite{
@@ -398,7 +1322,7 @@ pub fn read<T: io::Read + io::Seek>(from: &mut T) -> Result<Aseprite, AsepriteEr
             flags:         slice_to!(u32, &header[14..18]),
             speed:         slice_to!(u16, &header[18..20]),
             // zero:          slice_cnt!(header, 20, 8),
-            // palette_entry: header[28],
+            palette_entry: header[28],
             // ignore:        slice_cnt!(header, 29, 3),
             colour_count:  slice_to!(u16, &header[32..34]),
             pixel_width:   header[34],
@@ -410,24 +1334,29 @@ pub fn read<T: io::Read + io::Seek>(from: &mut T) -> Result<Aseprite, AsepriteEr
             // future:        slice_cnt!(header, 44, 84)
         },
         frames: Default::default(),
+        warnings: vec![],
     };
 
     if result.header.magic != ASEPRITE_MAGIC_HEADER {
         return Err(AsepriteError::HeaderMagicMismatch);
     }
 
-    if result.header.flags & 0x1 == 0 {
-        println!("file has an invalid layer opacity value flag unset, continuing..")
+    if ![8, 16, 32].contains(&result.header.colour_depth) {
+        return Err(AsepriteError::Other(format!("unsupported colour depth {}", result.header.colour_depth).into()));
     }
 
-    let mut frame_buffer: Vec<u8> = vec![0; 16];
+    if result.header.flags & 0x1 == 0 {
+        result.warnings.push("file has the valid layer opacity flag unset".to_owned());
+    }
 
+    let mut position = READ_HEADER_SIZE;
     let mut frame_count = 0;
 
-    while from.read(&mut frame_buffer).unwrap_or(0) > 0 {
-        if result.header.frames < frame_count {
-            println!("frame count in header is lower than what is in file, continuing..");
-            println!("-> decoding frame no. {frame_count} when header states only {} frames", result.header.frames);
+    // running out partway through a frame header is treated as the end of the file
+    while let Some(frame_buffer) = from.get(position..(16 + position)) {
+        // only said the once, on the first frame the header doesn't count
+        if result.header.frames == frame_count {
+            result.warnings.push(format!("file has more frames than the {frame_count} its header says"));
         }
 
         let mut frame = AsepriteFrame{
@@ -440,170 +1369,451 @@ pub fn read<T: io::Read + io::Seek>(from: &mut T) -> Result<Aseprite, AsepriteEr
             chunks:         Vec::new(),
         };
 
-        if frame.frame_duration == 0 {
-            frame.frame_duration = result.header.speed
-        }
-
         if frame.magic != ASEPRITE_MAGIC_FRAMES {
             return Err(AsepriteError::FrameMagicMismatch);
         }
 
-        let frames_end = from.stream_position().unwrap_or_default() + frame.size as u64;
+        // nothing in a frame can be bigger than what's left of the file
+        let frames_end = (position + frame.size as usize).min(from.len());
+        position += 16;
 
+        // very old files leave the newer 32 bit count as 0 and only fill in the 16 bit one
         let file_chunk_count = if frame.chunk_count == 0 { frame.old_chunks as u32 } else { frame.chunk_count };
 
-        for _ in 0..file_chunk_count {
-            let current_position = from.stream_position().unwrap_or_default();
+        let mut tags_given_user_data = 0;
 
-            if current_position >= frames_end {
-                println!("frame data spills out of the size in the header at pos {current_position}!\ngoing to continue..")
-            }
-
-            let size = {
-                let mut buffer = [0u8; size_of::<u32>()];
-                let _ = from.read(&mut buffer).unwrap();
-    
-                u32::from_le_bytes(buffer)
+        for _ in 0..file_chunk_count {
+            let Some(chunk_header) = from.get(position..(6 + position)) else {
+                return Err(AsepriteError::Other("file ended partway through a chunk".into()));
             };
 
-            let chunk_type = {
-                let mut buffer = [0u8; size_of::<u16>()];
-                let _ = from.read(&mut buffer).unwrap();
-
-                u16::from_le_bytes(buffer)
-            };
+            let size = slice_to!(u32, &chunk_header[0..4]);
+            let chunk_type = slice_to!(u16, &chunk_header[4..6]);
 
-            let mut data: Vec<u8> = vec![0; size.try_into().unwrap()];
-
-            from.seek(io::SeekFrom::Start(current_position)).unwrap();
-            let _ = from.read(&mut data).unwrap();
-
-            frame.chunks.push(
-                match chunk_type {
-                    ASEPRITE_LAYER_CHUNK_MAGIC => {
-                        let layer_type = AsepriteLayerType::from(slice_to!(u16, &data[ 8..10]));
-                        let is_tilemap = layer_type == AsepriteLayerType::Tilemap;
-                        Chunk::Layer(AsepriteLayerChunk {
-                            flags:          slice_to!(u16, &data[ 6.. 8]),
-                            // layer_type                       [08..10]
-                            child_level:    slice_to!(u16, &data[10..12]),
-                            // default_width:  slice_to!(u16, &data[12..14]),
-                            // default_height: slice_to!(u16, &data[14..16]),
-                            blend_mode:     AsepriteBlendMode::from(slice_to!(u16, &data[16..18])),
-                            opacity:        data[18],
-                            // future:         slice_cnt!(data, 19, 3),
-                            name:           AsepriteString::read_from_bytes(
-                                                &data[22..(data.len() - if is_tilemap { 4 } else { 0 })]
-                                            ),
-                            
-                            // tileset_index:  if is_tilemap {
-                            //                     Some(slice_to!(u32, &data[data.len()-4..data.len()]))
-                            //                 } else { None },
-                            
-                            layer_type
-                        })
-                    },
-                    ASEPRITE_CEL_CHUNK_MAGIC => {
-                        let mut r = Chunk::Cel(AsepriteCelChunk {
-                            layer_index: slice_to!(u16, &data[ 6.. 8]),
-                            x_pos:       slice_to!(i16, &data[ 8..10]),
-                            y_pos:       slice_to!(i16, &data[10..12]),
-                            opacity:     data[12],
-                            cel_type:    AsepriteCelType::from(slice_to!(u16, &data[13..15])),
-                            // z_index:     slice_to!(i16, &data[15..17]),
-                            // future:      slice_cnt!(data, 17, 5),
-                            
-                            // cel specific fields set below
-                            // cel type 0
-                            width: None,
-                            height: None,
-                            raw_data: None,
-                            
-                            // cel type 1
-                            linked_to: None,
-                            
-                            // cel type 2
-                            // width,
-                            // height,
-                            compressed_data: None
-                        });
+            let chunk_end = position + size as usize;
+            if chunk_end > frames_end {
+                return Err(AsepriteError::Other(format!(
+                    "chunk at pos {position} is {size} bytes, but only {} are left in the frame",
+                    frames_end.saturating_sub(position)
+                ).into()));
+            }
 
-                        if let Chunk::Cel(ref mut c) = &mut r {
-                            match c.cel_type {
-                                AsepriteCelType::Raw => {
-                                    c.width =    Some(slice_to!(u16, &data[22..24]));
-                                    c.height =   Some(slice_to!(u16, &data[24..26]));
-                                    c.raw_data = Some(data[26..].into());
-                                },
-
-                                AsepriteCelType::Linked => {
-                                    c.linked_to = Some(slice_to!(u16, &data[22..24]));
-                                },
-
-                                AsepriteCelType::CompressedImage => {
-                                    c.width =           Some(slice_to!(u16, &data[22..24]));
-                                    c.height =          Some(slice_to!(u16, &data[24..26]));
-                                    c.compressed_data = Some(data[26..].into());
-
-                                    // decompress the image data now for easier use later
-
-                                    let slicedata = c.compressed_data.as_ref().unwrap();
-                                    let slicedata = slicedata.as_ref();
-                                    let mut z = ZlibDecoder::new(slicedata);
-                                    let mut r = vec![];
-                                    match z.read_to_end(&mut r) {
-                                        Ok(_) => (),
-                                        Err(e) => return Err(AsepriteError::Other(Box::new(e))),
-                                    }
-
-                                    c.raw_data = Some(r.into());
-                                },
-
-                                AsepriteCelType::CompressedTilemap => {
-                                    unimplemented!("compressed tilemap unsupported");
-                                },
-                            };
-                        }
+            let data = &from[position..chunk_end];
+            position = chunk_end;
 
-                        r
-                    },
-                    ASEPRITE_TAG_CHUNK_MAGIC => {
-                        let mut tag_data = AsepriteTagChunk {
-                            tag_count:  slice_to!(u16, &data[6..8]),
-                            // future:     slice_cnt!(data, 8, 8),
-                            tags:       Vec::<AsepriteTag>::new() 
-                        };
+            frame.chunks.push(read_chunk(chunk_type, data, &result.header, &mut result.warnings)?);
 
-                        let mut offset = 16;
-                        for _ in 0..tag_data.tag_count {
-                            let name_len = slice_to!(u16, &data[(17 + offset)..(19 + offset)]) as usize;
-
-                            tag_data.tags.push(AsepriteTag {
-                                from:           slice_to!(u16, &data[(    offset)..(2 + offset)]),
-                                to:             slice_to!(u16, &data[(2 + offset)..(4 + offset)]),
-                                direction:      AsepriteTagDirection::from(data[4 + offset]),
-                                repeat_count:   slice_to!(u16, &data[(5 + offset)..(7 + offset)]),
-                                // reserved:       slice_cnt!(data, {7 + offset}, 6),
-                                // colour:         slice_cnt!(data, {13 + offset}, 3),
-                                // extra:          data[ 16 + offset],
-                                name:           AsepriteString::read_from_bytes(
-                                                    &data[(17 + offset)..((19 + offset) + name_len)]
-                                                )
-                            });
-
-                            offset += 19 + name_len
+            // user data and cel extras always come right after what they belong to
+            match frame.chunks.last() {
+                Some(Chunk::UserData(_)) => {
+                    if let Some(Chunk::UserData(user_data)) = frame.chunks.pop() {
+                        if let Some(orphan) = attach_user_data(frame.chunks.last_mut(), &mut tags_given_user_data, user_data) {
+                            frame.chunks.push(Chunk::UserData(orphan));
                         }
-
-                        Chunk::Tag(tag_data)
-                    },
-                    _ => Chunk::Unknown(RawAsepriteChunk { size, chunk_type, data: data.into() })
-                }
-            );
+                    }
+                },
+                Some(Chunk::CelExtra(_)) => {
+                    if let Some(Chunk::CelExtra(extra)) = frame.chunks.pop() {
+                        match frame.chunks.last_mut() {
+                            Some(Chunk::Cel(cchunk)) if cchunk.extra.is_none() => cchunk.extra = Some(extra),
+                            _ => frame.chunks.push(Chunk::CelExtra(extra)),
+                        }
+                    }
+                },
+                _ => tags_given_user_data = 0,
+            }
         }
     
         result.frames.push(frame);
         frame_count += 1;
     }
 
+    inflate_cels(&mut result.frames)?;
+
     Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::*;
+    use crate::ase::fixtures::*;
+
+    /// Reads a file with one frame holding `chunks`.
+    fn one_frame(chunks: &[Vec<u8>]) -> Aseprite {
+        read_from_slice(&file(32, &[frame(chunks)])).unwrap()
+    }
+
+    #[test]
+    fn short_headers_run_out() {
+        let result = read(&mut Cursor::new(vec![0; 50]));
+        assert!(matches!(result, Err(AsepriteError::RanOutAtHeader)));
+    }
+
+    #[test]
+    fn bare_headers_are_read() {
+        let sprite = read(&mut Cursor::new(header(0, 32))).unwrap();
+        assert_eq!(sprite.header.width, 2);
+        assert_eq!(sprite.header.height, 2);
+        assert!(sprite.frames.is_empty());
+        assert!(sprite.warnings.is_empty());
+    }
+
+    #[test]
+    fn bad_magic_numbers_are_errors() {
+        let mut data = sample();
+        data[4] = 0;
+        assert!(matches!(read_from_slice(&data), Err(AsepriteError::HeaderMagicMismatch)));
+
+        let mut data = sample();
+        data[128 + 4] = 0;
+        assert!(matches!(read_from_slice(&data), Err(AsepriteError::FrameMagicMismatch)));
+    }
+
+    #[test]
+    fn unsupported_colour_depths_are_errors() {
+        let mut data = sample();
+        data[12] = 24;
+        assert!(matches!(read_from_slice(&data), Err(AsepriteError::Other(_))));
+    }
+
+    #[test]
+    fn broken_files_never_panic() {
+        let mut compressed_cel = 0u16.to_le_bytes().to_vec();
+        compressed_cel.extend([0; 4]);
+        compressed_cel.push(255);
+        compressed_cel.extend(2u16.to_le_bytes());
+        compressed_cel.extend([0; 7]);
+        compressed_cel.extend(2u16.to_le_bytes());
+        compressed_cel.extend(2u16.to_le_bytes());
+        compressed_cel.extend(zlib(&[7; 16]));
+
+        let data = file(32, &[
+            frame(&[layer("bg", 0), user_data("note", [1; 4]), chunk(0x2005, &compressed_cel)]),
+            frame(&[tags(&["idle", "walk"]), user_data("tag", [2; 4]), raw_cel(0, 1, 1, 1, 1, &[1, 2, 3, 4])]),
+        ]);
+        assert!(read_from_slice(&data).is_ok());
+
+        // errors are fine, only panics fail
+        for len in 0..data.len() {
+            let _ = read_from_slice(&data[..len]);
+        }
+
+        for at in 0..data.len() {
+            for value in [0x00, 0x7F, 0xFF] {
+                let mut broken = data.clone();
+                broken[at] = value;
+                let _ = read_from_slice(&broken);
+            }
+        }
+    }
+
+    #[test]
+    fn chunks_have_to_fit_their_fields() {
+        assert!(check_chunk_size(&[0; 22], ASEPRITE_LAYER_CHUNK_MAGIC, 22).is_ok());
+        assert!(check_chunk_size(&[0; 21], ASEPRITE_LAYER_CHUNK_MAGIC, 22).is_err());
+
+        // too short for a layer, and for a raw cel's size
+        for short in [chunk(0x2004, &[0; 8]), chunk(0x2005, &[0; 18])] {
+            let result = read_from_slice(&file(32, &[frame(&[short])]));
+            assert!(matches!(result, Err(AsepriteError::Other(_))));
+        }
+    }
+
+    #[test]
+    fn chunks_bigger_than_their_frame_are_errors() {
+        let mut data = sample();
+        // the first layer chunk, right after the first frame's header
+        data[(128 + 16)..(128 + 20)].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let Err(AsepriteError::Other(e)) = read_from_slice(&data) else { panic!("read a chunk bigger than its frame") };
+        assert!(e.to_string().contains("left in the frame"));
+
+        // a chunk can't reach into the next frame either, even though the file has the bytes
+        let mut data = file(32, &[frame(&[layer("bg", 0)]), frame(&[layer("fg", 0)])]);
+        let spill = (layer("bg", 0).len() + 1) as u32;
+        data[(128 + 16)..(128 + 20)].copy_from_slice(&spill.to_le_bytes());
+        assert!(matches!(read_from_slice(&data), Err(AsepriteError::Other(_))));
+    }
+
+    #[test]
+    fn files_ending_in_a_chunk_are_errors() {
+        let data = sample();
+        // partway through the first chunk's size and type
+        let result = read_from_slice(&data[..(128 + 16 + 3)]);
+        assert!(matches!(result, Err(AsepriteError::Other(_))));
+    }
+
+    #[test]
+    fn old_chunk_counts_are_used_when_the_new_one_is_0() {
+        let mut data = sample();
+        // the second frame's header, after the first frame
+        let second = 128 + u32::from_le_bytes(data[128..132].try_into().unwrap()) as usize;
+
+        // the new count wins when both are set
+        data[128 + 6..128 + 8].copy_from_slice(&1u16.to_le_bytes());
+        data[128 + 12..128 + 16].copy_from_slice(&3u32.to_le_bytes());
+        // very old files only fill in the 16 bit count
+        data[second + 12..second + 16].copy_from_slice(&0u32.to_le_bytes());
+
+        let sprite = read_from_slice(&data).unwrap();
+        assert_eq!(sprite.frames[0].chunks.len(), 3);
+        assert_eq!(sprite.frames[1].chunks.len(), 1);
+    }
+
+    #[test]
+    fn strings_stop_at_their_length() {
+        let mut data = string("idle");
+        data.extend([1, 2, 3]);
+
+        let (text, size) = AsepriteString::read_from_bytes(&data);
+        assert_eq!(text.as_str(), Ok("idle"));
+        assert_eq!(size, 6);
+        assert!(!text.is_truncated());
+
+        let (cut, size) = AsepriteString::read_from_bytes(&data[..4]);
+        assert_eq!(cut.as_str(), Ok("id"));
+        assert_eq!(size, 4);
+        assert!(cut.is_truncated());
+    }
+
+    #[test]
+    fn layers_and_cels_are_read() {
+        let sprite = read_from_slice(&sample()).unwrap();
+        let Chunk::Layer(layer) = &sprite.frames[0].chunks[1] else { panic!("not a layer") };
+        assert_eq!(layer.name.as_str(), Ok("fg"));
+        assert_eq!(layer.opacity, 255);
+        assert!(layer.tileset_index.is_none());
+
+        let Chunk::Cel(cel) = &sprite.frames[1].chunks[0] else { panic!("not a cel") };
+        assert_eq!((cel.layer_index, cel.x_pos, cel.y_pos), (1, 1, 1));
+        assert_eq!((cel.width, cel.height), (Some(1), Some(1)));
+        assert_eq!(cel.raw_data.as_deref(), Some(&[1, 2, 3, 4][..]));
+        assert_eq!(sprite.frames[1].frame_duration, 100);
+    }
+
+    #[test]
+    fn tag_names_are_read() {
+        let sprite = one_frame(&[tags(&["idle", "walk"])]);
+        let Chunk::Tag(tag) = &sprite.frames[0].chunks[0] else { panic!("not a tag") };
+        let names: Vec<_> = tag.tags.iter().map(|x| x.name.as_str().unwrap()).collect();
+        assert_eq!(names, ["idle", "walk"]);
+    }
+
+    #[test]
+    fn user_data_goes_on_the_chunk_before_it() {
+        let mut text_only = 1u32.to_le_bytes().to_vec();
+        text_only.extend(string("note"));
+
+        let sprite = one_frame(&[
+            layer("bg", 0), chunk(0x2020, &text_only),
+            raw_cel(0, 0, 0, 1, 1, &[0; 4]), user_data("cel", [1, 2, 3, 4]),
+        ]);
+        assert_eq!(sprite.frames[0].chunks.len(), 2);
+
+        let Chunk::Layer(layer) = &sprite.frames[0].chunks[0] else { panic!("not a layer") };
+        let layer_data = layer.user_data.as_ref().unwrap();
+        assert_eq!(layer_data.text.as_ref().map(|x| x.as_str()), Some(Ok("note")));
+        assert!(layer_data.colour.is_none());
+
+        let Chunk::Cel(cel) = &sprite.frames[0].chunks[1] else { panic!("not a cel") };
+        let cel_data = cel.user_data.as_ref().unwrap();
+        assert_eq!(cel_data.text.as_ref().map(|x| x.as_str()), Some(Ok("cel")));
+        assert_eq!(cel_data.colour, Some([1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn each_tag_gets_the_user_data_after_it_in_turn() {
+        let sprite = one_frame(&[
+            tags(&["idle", "walk"]),
+            user_data("first", [255, 0, 0, 255]),
+            user_data("second", [0, 255, 0, 255]),
+            // there's no third tag for this one
+            user_data("extra", [0; 4]),
+        ]);
+
+        let chunks = &sprite.frames[0].chunks;
+        assert_eq!(chunks.len(), 2);
+        assert!(matches!(chunks[1], Chunk::UserData(_)));
+
+        let Chunk::Tag(tag) = &chunks[0] else { panic!("not a tag") };
+        assert_eq!(tag.tags[0].display_colour(), [255, 0, 0, 255]);
+        assert_eq!(tag.tags[1].display_colour(), [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn user_data_with_nothing_to_go_on_is_given_back() {
+        let user_data = || AsepriteUserDataChunk { flags: 0, text: None, colour: None };
+        let mut tags_done = 0;
+
+        assert!(attach_user_data(None, &mut tags_done, user_data()).is_some());
+
+        let mut sprite = read_from_slice(&sample()).unwrap();
+        let cel = &mut sprite.frames[1].chunks[0];
+        assert!(attach_user_data(Some(cel), &mut tags_done, user_data()).is_none());
+        // a chunk only has room for one
+        assert!(attach_user_data(Some(cel), &mut tags_done, user_data()).is_some());
+        assert_eq!(tags_done, 0);
+    }
+
+    #[test]
+    fn tag_names_longer_than_their_chunk_are_cut_short() {
+        // only the 4 bytes of "idle" are really there
+        for name_len in [5u16, 255, 0x7FFF, 0xFFFF] {
+            let mut body = 1u16.to_le_bytes().to_vec();
+            body.extend([0; 8]);
+            body.extend([0; 17]);
+            body.extend(name_len.to_le_bytes());
+            body.extend(b"idle");
+
+            let sprite = read_from_slice(&file(32, &[frame(&[chunk(0x2018, &body)])]));
+            let sprite = sprite.unwrap_or_else(|e| panic!("a name {name_len} long stopped the read: {e}"));
+
+            let Chunk::Tag(tag) = &sprite.frames[0].chunks[0] else { panic!("not a tag") };
+            assert_eq!(tag.tags.len(), 1);
+            assert_eq!(tag.tags[0].name.as_str(), Ok("idle"));
+            assert!(tag.tags[0].name.is_truncated());
+            assert!(sprite.warnings.iter().any(|x| x == "tag 0 has a name longer than its chunk"));
+        }
+    }
+
+    #[test]
+    fn palette_entries_keep_their_names() {
+        let mut body = 2u32.to_le_bytes().to_vec();
+        body.extend(0u32.to_le_bytes());
+        body.extend(1u32.to_le_bytes());
+        body.extend([0; 8]);
+        body.extend(1u16.to_le_bytes());
+        body.extend([255, 0, 0, 255]);
+        body.extend(string("red"));
+        body.extend(0u16.to_le_bytes());
+        body.extend([0, 0, 255, 128]);
+
+        let sprite = one_frame(&[chunk(0x2019, &body)]);
+        assert_eq!(sprite.palette(), [[255, 0, 0, 255], [0, 0, 255, 128]]);
+        assert_eq!(sprite.palette_names(), [Some("red".to_owned()), None]);
+        assert!(sprite.warnings.is_empty());
+    }
+
+    #[test]
+    fn old_palettes_are_scaled_to_8_bits() {
+        let mut body = 1u16.to_le_bytes().to_vec();
+        body.extend([1, 2]);
+        body.extend([63, 0, 0, 0, 63, 0]);
+
+        let sprite = one_frame(&[chunk(0x0011, &body)]);
+        assert_eq!(sprite.palette(), [[0, 0, 0, 0], [255, 0, 0, 255], [0, 255, 0, 255]]);
+    }
+
+    #[test]
+    fn colour_profiles_keep_their_gamma_and_icc_data() {
+        let mut body = 2u16.to_le_bytes().to_vec();
+        body.extend(1u16.to_le_bytes());
+        body.extend(0x18000i32.to_le_bytes());
+        body.extend([0; 8]);
+        body.extend(3u32.to_le_bytes());
+        body.extend([1, 2, 3]);
+
+        let sprite = one_frame(&[chunk(0x2007, &body)]);
+        let profile = sprite.color_profile().unwrap();
+        assert!(profile.profile_type == AsepriteColorProfileType::EmbeddedIcc);
+        assert_eq!(profile.fixed_gamma(), Some(1.5));
+        assert_eq!(profile.icc_data.as_deref(), Some(&[1, 2, 3][..]));
+    }
+
+    #[test]
+    fn cel_extras_go_on_their_cel() {
+        let mut body = 1u32.to_le_bytes().to_vec();
+        for x in [0x8000i32, 0x10000, 2 << 16, 3 << 16] {
+            body.extend(x.to_le_bytes());
+        }
+        body.extend([0; 16]);
+
+        let sprite = one_frame(&[layer("bg", 0), raw_cel(0, 0, 0, 1, 1, &[0; 4]), chunk(0x2006, &body)]);
+        assert_eq!(sprite.frames[0].chunks.len(), 2);
+
+        let Chunk::Cel(cel) = &sprite.frames[0].chunks[1] else { panic!("not a cel") };
+        assert_eq!(cel.extra.as_ref().and_then(|x| x.precise_bounds()), Some((0.5, 1.0, 2.0, 3.0)));
+    }
+
+    #[test]
+    fn slice_keys_have_their_optional_parts() {
+        let mut body = 1u32.to_le_bytes().to_vec();
+        body.extend(3u32.to_le_bytes());
+        body.extend([0; 4]);
+        body.extend(string("hitbox"));
+        for x in [0i32, 1, -2, 3, 4, 1, 1, 1, 1, 5, 6] {
+            body.extend(x.to_le_bytes());
+        }
+
+        let sprite = one_frame(&[chunk(0x2022, &body)]);
+        let slices = sprite.slices();
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0].name.as_str(), Ok("hitbox"));
+
+        let key = &slices[0].keys[0];
+        assert_eq!((key.frame, key.x, key.y, key.width, key.height), (0, 1, -2, 3, 4));
+        assert!(key.center.as_ref().is_some_and(|x| (x.x, x.y, x.width, x.height) == (1, 1, 1, 1)));
+        assert_eq!(key.pivot, Some((5, 6)));
+    }
+
+    #[test]
+    fn tilesets_are_split_into_tiles() {
+        let strip = zlib(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let mut body = 0u32.to_le_bytes().to_vec();
+        body.extend(2u32.to_le_bytes());
+        body.extend(2u32.to_le_bytes());
+        body.extend(1u16.to_le_bytes());
+        body.extend(1u16.to_le_bytes());
+        body.extend(1i16.to_le_bytes());
+        body.extend([0; 14]);
+        body.extend(string("tiles"));
+        body.extend((strip.len() as u32).to_le_bytes());
+        body.extend(strip);
+
+        let sprite = one_frame(&[chunk(0x2023, &body)]);
+        let tilesets = sprite.tilesets();
+        assert_eq!(tilesets[0].name.as_str(), Ok("tiles"));
+        assert!(tilesets[0].external.is_none());
+
+        let tiles: Vec<&[u8]> = tilesets[0].tiles.iter().flatten().map(|x| &x[..]).collect();
+        assert_eq!(tiles, [&[1, 2, 3, 4][..], &[5, 6, 7, 8][..]]);
+    }
+
+    #[test]
+    fn tilemap_cels_keep_their_tiles_and_masks() {
+        let mut body = 0u16.to_le_bytes().to_vec();
+        body.extend([0; 4]);
+        body.push(255);
+        body.extend(3u16.to_le_bytes());
+        body.extend([0; 7]);
+        body.extend(2u16.to_le_bytes());
+        body.extend(1u16.to_le_bytes());
+        body.extend(32u16.to_le_bytes());
+        for mask in [0x1fffffffu32, 0x80000000, 0x40000000, 0x20000000] {
+            body.extend(mask.to_le_bytes());
+        }
+        body.extend([0; 10]);
+        body.extend(zlib(&[1u32, 0x80000002].map(|x| x.to_le_bytes()).concat()));
+
+        let sprite = one_frame(&[chunk(0x2005, &body)]);
+        let Chunk::Cel(cel) = &sprite.frames[0].chunks[0] else { panic!("not a cel") };
+        assert_eq!(cel.tiles.as_deref(), Some(&[1, 0x80000002][..]));
+        assert_eq!(cel.tile_masks.unwrap().split(0x80000002), (2, true, false, false));
+    }
+
+    #[test]
+    fn short_frame_headers_at_the_end_are_left_off() {
+        for trailing in 0..16 {
+            let mut data = sample();
+            data.extend(vec![0xFA; trailing]);
+
+            let sprite = read_from_slice(&data).unwrap();
+            assert_eq!(sprite.frames.len(), 2, "with {trailing} bytes left over");
+            assert_eq!(sprite.frames[0].chunks.len(), 3);
+            assert_eq!(sprite.frames[1].chunks.len(), 1);
+            assert!(sprite.warnings.is_empty());
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,138 @@
+//! Builds Aseprite files byte by byte for tests, laid out the way the spec
+//! says. Everything is a bare minimum, with whatever isn't asked for zeroed.
+
+use std::io::Write;
+use flate2::{write::ZlibEncoder, Compression};
+
+/// Gets a 128 byte header for a 2x2 sprite with square pixels.
+pub(crate) fn header(frames: u16, colour_depth: u16) -> Vec<u8> {
+    let mut result = vec![0; 128];
+    result[4..6].copy_from_slice(&0xA5E0u16.to_le_bytes());
+    result[6..8].copy_from_slice(&frames.to_le_bytes());
+    result[8..10].copy_from_slice(&2u16.to_le_bytes());
+    result[10..12].copy_from_slice(&2u16.to_le_bytes());
+    result[12..14].copy_from_slice(&colour_depth.to_le_bytes());
+    // layer opacity is valid
+    result[14..18].copy_from_slice(&1u32.to_le_bytes());
+    result[18..20].copy_from_slice(&100u16.to_le_bytes());
+    result[32..34].copy_from_slice(&4u16.to_le_bytes());
+    result[34] = 1;
+    result[35] = 1;
+    result
+}
+
+/// Wraps a chunk's body with its size and type.
+pub(crate) fn chunk(chunk_type: u16, body: &[u8]) -> Vec<u8> {
+    let mut result = ((body.len() + 6) as u32).to_le_bytes().to_vec();
+    result.extend(chunk_type.to_le_bytes());
+    result.extend(body);
+    result
+}
+
+/// Wraps chunks in a 100ms frame, with both chunk counts filled in.
+pub(crate) fn frame(chunks: &[Vec<u8>]) -> Vec<u8> {
+    let body = chunks.concat();
+
+    let mut result = ((body.len() + 16) as u32).to_le_bytes().to_vec();
+    result.extend(0xF1FAu16.to_le_bytes());
+    result.extend((chunks.len() as u16).to_le_bytes());
+    result.extend(100u16.to_le_bytes());
+    result.extend([0, 0]);
+    result.extend((chunks.len() as u32).to_le_bytes());
+    result.extend(body);
+    result
+}
+
+/// Puts a header and frames together, filling in the file size.
+pub(crate) fn file(colour_depth: u16, frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut result = header(frames.len() as u16, colour_depth);
+    result.extend(frames.concat());
+
+    let len = result.len() as u32;
+    result[0..4].copy_from_slice(&len.to_le_bytes());
+    result
+}
+
+/// Gets a string the way Aseprite stores them, prefixed with its length.
+pub(crate) fn string(text: &str) -> Vec<u8> {
+    let mut result = (text.len() as u16).to_le_bytes().to_vec();
+    result.extend(text.as_bytes());
+    result
+}
+
+/// Compresses data the way cels and tilesets are.
+pub(crate) fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Gets a visible, fully opaque normal layer chunk.
+pub(crate) fn layer(name: &str, child_level: u16) -> Vec<u8> {
+    let mut body = 1u16.to_le_bytes().to_vec();
+    body.extend(0u16.to_le_bytes());
+    body.extend(child_level.to_le_bytes());
+    body.extend([0; 4]);
+    body.extend(0u16.to_le_bytes());
+    body.push(255);
+    body.extend([0; 3]);
+    body.extend(string(name));
+    chunk(0x2004, &body)
+}
+
+/// Gets a fully opaque cel chunk with its pixels stored uncompressed.
+pub(crate) fn raw_cel(layer_index: u16, x: i16, y: i16, width: u16, height: u16, pixels: &[u8]) -> Vec<u8> {
+    let mut body = layer_index.to_le_bytes().to_vec();
+    body.extend(x.to_le_bytes());
+    body.extend(y.to_le_bytes());
+    body.push(255);
+    body.extend(0u16.to_le_bytes());
+    body.extend(0i16.to_le_bytes());
+    body.extend([0; 5]);
+    body.extend(width.to_le_bytes());
+    body.extend(height.to_le_bytes());
+    body.extend(pixels);
+    chunk(0x2005, &body)
+}
+
+/// Gets a user data chunk with some text and a colour.
+pub(crate) fn user_data(text: &str, colour: [u8; 4]) -> Vec<u8> {
+    let mut body = 3u32.to_le_bytes().to_vec();
+    body.extend(string(text));
+    body.extend(colour);
+    chunk(0x2020, &body)
+}
+
+/// Gets a palette chunk with unnamed entries, starting at index 0.
+pub(crate) fn palette(colours: &[[u8; 4]]) -> Vec<u8> {
+    let mut body = (colours.len() as u32).to_le_bytes().to_vec();
+    body.extend(0u32.to_le_bytes());
+    body.extend((colours.len() as u32).saturating_sub(1).to_le_bytes());
+    body.extend([0; 8]);
+
+    for colour in colours {
+        body.extend(0u16.to_le_bytes());
+        body.extend(colour);
+    }
+    chunk(0x2019, &body)
+}
+
+/// Gets a forward tag chunk with a tag for each name, spanning frame 0.
+pub(crate) fn tags(names: &[&str]) -> Vec<u8> {
+    let mut body = (names.len() as u16).to_le_bytes().to_vec();
+    body.extend([0; 8]);
+
+    for name in names {
+        body.extend([0; 17]);
+        body.extend(string(name));
+    }
+    chunk(0x2018, &body)
+}
+
+/// Gets a two frame RGBA sprite with two layers, each with a cel.
+pub(crate) fn sample() -> Vec<u8> {
+    file(32, &[
+        frame(&[layer("bg", 0), layer("fg", 0), raw_cel(0, 0, 0, 2, 2, &[255; 16])]),
+        frame(&[raw_cel(1, 1, 1, 1, 1, &[1, 2, 3, 4])]),
+    ])
+}
\ No newline at end of file
@@ -1 +1,4 @@
-pub mod aseprite;
\ No newline at end of file
+pub mod aseprite;
+
+#[cfg(test)]
+pub(crate) mod fixtures;
\ No newline at end of file
@@ -1,69 +1,207 @@
 mod ui;
 mod ase;
 
-use std::{fs::File, io};
+use std::{env, fs::File, io::{self, Write}};
 
 use ase::aseprite;
-use ui::ui_main;
+use ui::{headless, ui_main};
+
+const USAGE: &str = "usage: aseprite-viewer-rust [<file> | --dump <file> [output] | --dump-json <file> [output] | --export <file> <output> [--frame <n> | --sheet [horizontal|vertical|grid] | --gif [tag]]]";
 
 fn main() -> io::Result<()> {
-    ui_main::ui();
-    Ok(())
-    
-    // open_test()
+    let args: Vec<String> = env::args().collect();
+
+    match args.get(1).map(|x| x.as_str()) {
+        Some("--dump") => {
+            let Some(fpath) = args.get(2) else {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, USAGE));
+            };
+
+            match args.get(3) {
+                Some(out) => dump(fpath, &mut File::create(out)?),
+                None      => dump(fpath, &mut io::stdout().lock()),
+            }
+        },
+        #[cfg(feature = "serde")]
+        Some("--dump-json") => {
+            let Some(fpath) = args.get(2) else {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, USAGE));
+            };
+
+            match args.get(3) {
+                Some(out) => dump_json(fpath, &mut File::create(out)?),
+                None      => dump_json(fpath, &mut io::stdout().lock()),
+            }
+        },
+        #[cfg(not(feature = "serde"))]
+        Some("--dump-json") => Err(io::Error::new(io::ErrorKind::Unsupported, "--dump-json needs the viewer built with the serde feature")),
+        Some("--export") => headless::export(&args[2..], USAGE),
+        Some(x) if x.starts_with("--") => Err(io::Error::new(io::ErrorKind::InvalidInput, USAGE)),
+        open => {
+            ui_main::ui(open);
+            Ok(())
+        }
+    }
 }
 
-#[allow(dead_code)]
-fn open_test() -> io::Result<()> {
-    let fpath = "select.aseprite";
+/// Writes a human readable tree of everything read out of an Aseprite file.
+fn dump(fpath: &str, out: &mut impl Write) -> io::Result<()> {
     let mut f_in = File::open(fpath)?;
 
-    let data = aseprite::read(&mut f_in).unwrap();
+    let data = aseprite::read(&mut f_in).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
 
-    println!("{0}\nheader\n\t{1}b\n\tcanvas {2} by {3}\n\tgrid @ {4}, {5}; {6} by {7}\n\t{8}bpp, {9} colours",
+    writeln!(out, "{0}\nheader\n\t{1}b\n\tcanvas {2} by {3}\n\tgrid @ {4}, {5}; {6} by {7}\n\t{8}bpp, {9} colours\n\t{10}ms a frame (deprecated)",
         fpath, 
         data.header.fsize, 
         data.header.width, data.header.height, 
         data.header.grid_xpos, data.header.grid_ypos, data.header.grid_width, data.header.grid_height,
-        data.header.colour_depth, data.header.colour_count
-    );
-    println!("frames");
-    for f in data.frames {
-        println!("\t{0}ms\n\t{1} chunks", f.frame_duration, f.chunk_count);
-        for c in f.chunks {
-            print!("\t\t{0}", c.name());
+        data.header.colour_depth, data.header.colour_count,
+        data.header.speed
+    )?;
+    if !data.warnings.is_empty() {
+        writeln!(out, "warnings")?;
+        for w in &data.warnings {
+            writeln!(out, "\t{w}")?;
+        }
+    }
+    writeln!(out, "palette\n\t{0} entries", data.palette().len())?;
+    if let Some(profile) = data.color_profile() {
+        writeln!(out, "color profile\n\t{0}, gamma {1}\n\t{2}b icc",
+            profile.profile_type,
+            profile.fixed_gamma().map(|x| x.to_string()).unwrap_or("unset".to_owned()),
+            profile.icc_data.as_ref().map(|x| x.len()).unwrap_or(0)
+        )?;
+    }
+    writeln!(out, "slices")?;
+    for s in data.slices() {
+        writeln!(out, "\t{0}, {1} keys", s.name.as_str().unwrap_or("?"), s.key_count)?;
+        for k in &s.keys {
+            writeln!(out, "\t\tfrom frame {0}: {1}, {2}; {3} by {4}", k.frame, k.x, k.y, k.width, k.height)?;
+            if let Some(c) = &k.center {
+                writeln!(out, "\t\t\tcenter {0}, {1}; {2} by {3}", c.x, c.y, c.width, c.height)?;
+            }
+            if let Some((x, y)) = k.pivot {
+                writeln!(out, "\t\t\tpivot {x}, {y}")?;
+            }
+        }
+    }
+    writeln!(out, "frames")?;
+    for (i, f) in data.frames.iter().enumerate() {
+        writeln!(out, "\tframe {0}\n\t{1}ms\n\t{2} chunks", i, f.frame_duration, f.chunk_count)?;
+        for c in &f.chunks {
+            write!(out, "\t\t{0}", c.name())?;
             match c {
                 aseprite::Chunk::Unknown(rchunk) => {
-                    println!("\t\ttype x{0:04x} {1}b", rchunk.chunk_type, rchunk.size)
+                    writeln!(out, "\t\ttype x{0:04x} {1}b", rchunk.chunk_type, rchunk.size)?
                 },
                 aseprite::Chunk::Layer(lchunk) => {
-                    println!("\t{0}\n\t\t\tblend {1} at {2} opacity\n\t\t\tchild lvl {3}", 
-                        lchunk.name.as_str().unwrap(),
+                    writeln!(out, "\t{0}\n\t\t\tblend {1} at {2} opacity\n\t\t\tchild lvl {3}", 
+                        lchunk.name.as_str().unwrap_or("?"),
                         lchunk.blend_mode, lchunk.opacity,
                         lchunk.child_level
-                    )
+                    )?;
+                    dump_user_data(out, lchunk.user_data.as_ref(), "\t\t\t")?
                 },
                 aseprite::Chunk::Cel(cchunk)  => {
-                    println!("\t{4}\n\t\t\t@ on layer idx {7}; {0}, {1}; {2} by {3}\n\t\t\t{5}b\n\t\t\tlinked to {6}",
+                    writeln!(out, "\t{4}\n\t\t\t@ on layer idx {7} z {8}; {0}, {1}; {2} by {3}\n\t\t\t{5}b\n\t\t\tlinked to {6}",
                         cchunk.x_pos, cchunk.y_pos, cchunk.width.unwrap_or(0), cchunk.height.unwrap_or(0),
                         cchunk.cel_type,
-                        cchunk.raw_data.unwrap_or(cchunk.compressed_data.unwrap_or(vec![0].into())).len(),
+                        cchunk.raw_data.as_ref().or(cchunk.compressed_data.as_ref()).map(|x| x.len()).unwrap_or(0),
                         cchunk.linked_to.unwrap_or(0xFFFF),
-                        cchunk.layer_index
-                    )
+                        cchunk.layer_index, cchunk.z_index
+                    )?;
+
+                    if let (Some(tiles), Some(masks)) = (&cchunk.tiles, &cchunk.tile_masks) {
+                        writeln!(out, "\t\t\t{0} tiles at {1} bits\n\t\t\tmasks id {2:08x} x {3:08x} y {4:08x} diagonal {5:08x}",
+                            tiles.len(), masks.bits_per_tile,
+                            masks.tile_id, masks.x_flip, masks.y_flip, masks.diagonal_flip
+                        )?
+                    }
+                    if let Some((x, y, w, h)) = cchunk.extra.as_ref().and_then(|x| x.precise_bounds()) {
+                        writeln!(out, "\t\t\tprecisely @ {x}, {y}; {w} by {h}")?
+                    }
+                    dump_user_data(out, cchunk.user_data.as_ref(), "\t\t\t")?
                 },
                 aseprite::Chunk::Tag(tchunk)  => {
-                    println!("\tcount {0}", tchunk.tag_count);
-                    for t in tchunk.tags {
-                        println!("\t\t\t\t{0}\n\t\t\t\t{1} -> {2}, going {3} {4} times",
-                            t.name.as_str().unwrap(),
+                    writeln!(out, "\tcount {0}", tchunk.tag_count)?;
+                    for t in &tchunk.tags {
+                        let [r, g, b, a] = t.display_colour();
+                        writeln!(out, "\t\t\t\t{0}\n\t\t\t\t{1} -> {2}, going {3} {4} times\n\t\t\t\tcolour {r:02x}{g:02x}{b:02x}{a:02x}",
+                            t.name.as_str().unwrap_or("?"),
                             t.from, t.to, t.direction, t.repeat_count
-                        )
+                        )?;
+                        dump_user_data(out, t.user_data.as_ref(), "\t\t\t\t")?
+                    }
+                },
+                aseprite::Chunk::Palette(pchunk) => {
+                    writeln!(out, "\t{0} entries, setting {1} to {2}", pchunk.palette_size, pchunk.first_index, pchunk.last_index)?;
+                    for (i, e) in pchunk.entries.iter().enumerate() {
+                        writeln!(out, "\t\t\t{0}: #{1:02x}{2:02x}{3:02x}{4:02x} {5}",
+                            pchunk.first_index as usize + i,
+                            e.rgba[0], e.rgba[1], e.rgba[2], e.rgba[3],
+                            e.name.as_ref().and_then(|x| x.as_str().ok()).unwrap_or("")
+                        )?
+                    }
+                },
+                aseprite::Chunk::Slice(schunk) => {
+                    writeln!(out, "\t{0}", schunk.name.as_str().unwrap_or("?"))?;
+                    dump_user_data(out, schunk.user_data.as_ref(), "\t\t\t")?
+                },
+                aseprite::Chunk::CelExtra(cechunk) => {
+                    writeln!(out, "\tnot attached to a cel, flags {0}", cechunk.flags)?
+                },
+                aseprite::Chunk::ColorProfile(cpchunk) => {
+                    writeln!(out, "\t{0}", cpchunk.profile_type)?
+                },
+                aseprite::Chunk::UserData(uchunk) => {
+                    writeln!(out, "\tnot attached to anything")?;
+                    dump_user_data(out, Some(uchunk), "\t\t\t")?
+                },
+                aseprite::Chunk::Tileset(tschunk) => {
+                    writeln!(out, "\t{0} (id {1}, flags {2:03b})\n\t\t\t{3} tiles of {4} by {5}, numbered from {6}\n\t\t\t{7} tiles in file",
+                        tschunk.name.as_str().unwrap_or("?"), tschunk.id, tschunk.flags,
+                        tschunk.tile_count, tschunk.tile_width, tschunk.tile_height, tschunk.base_index,
+                        tschunk.tiles.as_ref().map(|x| x.len()).unwrap_or(0)
+                    )?;
+                    if let Some((file, id)) = tschunk.external {
+                        writeln!(out, "\t\t\tfrom external file {file}, tileset {id}")?
+                    }
+                },
+                aseprite::Chunk::OldPalette(opchunk) => {
+                    writeln!(out, "\t{0} packets", opchunk.packet_count)?;
+                    for p in &opchunk.packets {
+                        writeln!(out, "\t\t\t\tskip {0}, {1} colours", p.skip, p.colours.len())?
                     }
                 },
             }
         }
     }
 
+    Ok(())
+}
+
+/// Writes everything read out of an Aseprite file as JSON.
+#[cfg(feature = "serde")]
+fn dump_json(fpath: &str, out: &mut impl Write) -> io::Result<()> {
+    let mut f_in = File::open(fpath)?;
+
+    let data = aseprite::read(&mut f_in).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+    serde_json::to_writer_pretty(&mut *out, &data)?;
+    writeln!(out)
+}
+
+/// Writes out the user data given to a chunk, if there is any.
+fn dump_user_data(out: &mut impl Write, user_data: Option<&aseprite::AsepriteUserDataChunk>, indent: &str) -> io::Result<()> {
+    let Some(user_data) = user_data else { return Ok(()) };
+
+    if let Some(text) = &user_data.text {
+        writeln!(out, "{indent}user text \"{0}\"", text.as_str().unwrap_or("?"))?;
+    }
+
+    if let Some([r, g, b, a]) = user_data.colour {
+        writeln!(out, "{indent}user colour #{r:02x}{g:02x}{b:02x}{a:02x}")?;
+    }
+
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,238 @@
+use crate::ase::aseprite::AsepriteBlendMode;
+
+/// Multiplies two 0 to 255 values as if they were 0 to 1, rounding like
+/// Aseprite does.
+fn mul_un8(a: i32, b: i32) -> i32 {
+    let t = a * b + 0x80;
+    ((t >> 8) + t) >> 8
+}
+
+/// Divides two 0 to 255 values as if they were 0 to 1, rounding like Aseprite
+/// does. `b` can't be 0.
+fn div_un8(a: i32, b: i32) -> i32 {
+    (a * 0xFF + b / 2) / b
+}
+
+fn blend_hard_light(b: i32, s: i32) -> i32 {
+    if s < 128 {
+        mul_un8(b, s << 1)
+    } else {
+        let s = (s << 1) - 255;
+        b + s - mul_un8(b, s)
+    }
+}
+
+fn blend_soft_light(b: i32, s: i32) -> i32 {
+    let (b, s) = (b as f64 / 255.0, s as f64 / 255.0);
+
+    let d = if b <= 0.25 { ((16.0 * b - 12.0) * b + 4.0) * b } else { b.sqrt() };
+    let r = if s <= 0.5 {
+        b - (1.0 - 2.0 * s) * b * (1.0 - b)
+    } else {
+        b + (2.0 * s - 1.0) * (d - b)
+    };
+
+    (r * 255.0 + 0.5) as i32
+}
+
+/// Blends a single channel for the modes that treat each channel on its own.
+fn blend_channel(mode: AsepriteBlendMode, b: i32, s: i32) -> i32 {
+    match mode {
+        AsepriteBlendMode::Multiply   => mul_un8(b, s),
+        AsepriteBlendMode::Screen     => b + s - mul_un8(b, s),
+        AsepriteBlendMode::Overlay    => blend_hard_light(s, b),
+        AsepriteBlendMode::Darken     => b.min(s),
+        AsepriteBlendMode::Lighten    => b.max(s),
+        AsepriteBlendMode::ColorDodge => match b {
+            0 => 0,
+            _ if b >= 255 - s => 255,
+            _ => div_un8(b, 255 - s),
+        },
+        AsepriteBlendMode::ColorBurn  => match b {
+            255 => 255,
+            _ if 255 - b >= s => 0,
+            _ => 255 - div_un8(255 - b, s),
+        },
+        AsepriteBlendMode::HardLight  => blend_hard_light(b, s),
+        AsepriteBlendMode::SoftLight  => blend_soft_light(b, s),
+        AsepriteBlendMode::Difference => (b - s).abs(),
+        AsepriteBlendMode::Exclusion  => b + s - 2 * mul_un8(b, s),
+        AsepriteBlendMode::Addition   => (b + s).min(255),
+        AsepriteBlendMode::Subtract   => (b - s).max(0),
+        AsepriteBlendMode::Divide     => match b {
+            0 => 0,
+            _ if b >= s => 255,
+            _ => div_un8(b, s),
+        },
+        _ => s,
+    }
+}
+
+fn lum(c: [f64; 3]) -> f64 {
+    0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+fn sat(c: [f64; 3]) -> f64 {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+fn set_lum(c: [f64; 3], l: f64) -> [f64; 3] {
+    let d = l - lum(c);
+    let c = c.map(|x| x + d);
+
+    // bring the colour back in range without changing its luminosity
+    let l = lum(c);
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+
+    c.map(|v| {
+        let v = if n < 0.0 { l + (v - l) * l / (l - n) } else { v };
+        if x > 1.0 { l + (v - l) * (1.0 - l) / (x - l) } else { v }
+    })
+}
+
+fn set_sat(c: [f64; 3], s: f64) -> [f64; 3] {
+    let mut order = [0, 1, 2];
+    order.sort_by(|&a, &b| c[a].total_cmp(&c[b]));
+    let [min, mid, max] = order;
+
+    let mut r = [0.0; 3];
+    if c[max] > c[min] {
+        r[mid] = (c[mid] - c[min]) * s / (c[max] - c[min]);
+        r[max] = s;
+    }
+
+    r
+}
+
+/// Blends the colour of a pixel for the modes that work on hue, saturation and
+/// luminosity, which can't be done a channel at a time.
+fn blend_non_separable(mode: AsepriteBlendMode, b: [u8; 3], s: [u8; 3]) -> [u8; 3] {
+    let (b, s) = (b.map(|x| x as f64 / 255.0), s.map(|x| x as f64 / 255.0));
+
+    let r = match mode {
+        AsepriteBlendMode::Hue        => set_lum(set_sat(s, sat(b)), lum(b)),
+        AsepriteBlendMode::Saturation => set_lum(set_sat(b, sat(s)), lum(b)),
+        AsepriteBlendMode::Color      => set_lum(s, lum(b)),
+        AsepriteBlendMode::Luminosity => set_lum(b, lum(s)),
+        _ => s,
+    };
+
+    r.map(|x| (x * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+/// Lays a pixel over another with Aseprite's normal blend, with `opacity` on
+/// top of the pixel's own alpha.
+fn blend_normal(backdrop: [u8; 4], src: [u8; 4], opacity: u8) -> [u8; 4] {
+    let src_alpha = mul_un8(src[3] as i32, opacity as i32);
+    if src_alpha == 0 {
+        return backdrop;
+    }
+
+    let back_alpha = backdrop[3] as i32;
+    let alpha = src_alpha + back_alpha - mul_un8(back_alpha, src_alpha);
+
+    let mix = |b: u8, s: u8| (b as i32 + (s as i32 - b as i32) * src_alpha / alpha) as u8;
+    [mix(backdrop[0], src[0]), mix(backdrop[1], src[1]), mix(backdrop[2], src[2]), alpha as u8]
+}
+
+/// Lays the pixel `src` over `backdrop` with a blend mode, with `opacity` on top
+/// of the pixel's own alpha.
+///
+/// Like Aseprite, the blend mode only decides the colour, which then goes over
+/// the backdrop as a normal blend would. Over fully transparent pixels there is
+/// nothing to blend with, so the pixel goes down as it is.
+pub(crate) fn blend_pixel(mode: AsepriteBlendMode, backdrop: [u8; 4], src: [u8; 4], opacity: u8) -> [u8; 4] {
+    if matches!(mode, AsepriteBlendMode::Normal) || backdrop[3] == 0 {
+        return blend_normal(backdrop, src, opacity);
+    }
+
+    let [r, g, b] = match mode {
+        AsepriteBlendMode::Hue | AsepriteBlendMode::Saturation | AsepriteBlendMode::Color | AsepriteBlendMode::Luminosity =>
+            blend_non_separable(mode, [backdrop[0], backdrop[1], backdrop[2]], [src[0], src[1], src[2]]),
+        _ => [0, 1, 2].map(|i| blend_channel(mode, backdrop[i] as i32, src[i] as i32).clamp(0, 255) as u8),
+    };
+
+    blend_normal(backdrop, [r, g, b, src[3]], opacity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Blends an opaque pixel over an opaque backdrop at full opacity.
+    fn opaque(mode: u16, backdrop: [u8; 3], src: [u8; 3]) -> [u8; 3] {
+        let [r, g, b, a] = blend_pixel(AsepriteBlendMode::from(mode), [backdrop[0], backdrop[1], backdrop[2], 255], [src[0], src[1], src[2], 255], 255);
+        assert_eq!(a, 255);
+        [r, g, b]
+    }
+
+    #[test]
+    fn separable_modes_match_aseprite() {
+        let (backdrop, grey) = ([255, 128, 0], [128, 128, 128]);
+        let other = [100, 200, 50];
+
+        assert_eq!(opaque( 0, backdrop, other), other);
+        assert_eq!(opaque( 1, backdrop, grey),  [128,  64,   0]);
+        assert_eq!(opaque( 2, backdrop, grey),  [255, 192, 128]);
+        // overlaying middle grey leaves the backdrop as it was
+        assert_eq!(opaque( 3, backdrop, grey),  backdrop);
+        assert_eq!(opaque( 4, backdrop, other), [100, 128,   0]);
+        assert_eq!(opaque( 5, backdrop, other), [255, 200,  50]);
+        assert_eq!(opaque( 8, grey, backdrop),  [255, 128,   0]);
+        assert_eq!(opaque(10, backdrop, other), [155,  72,  50]);
+        assert_eq!(opaque(11, backdrop, [0; 3]), backdrop);
+        assert_eq!(opaque(16, backdrop, other), [255, 255,  50]);
+        assert_eq!(opaque(17, backdrop, other), [155,   0,   0]);
+
+        assert_eq!(opaque( 6, [128, 0,  64], grey), [255, 0, 129]);
+        assert_eq!(opaque( 7, [255, 0, 192], grey), [255, 0, 129]);
+        assert_eq!(opaque(18, [ 64, 0, 200], grey), [128, 0, 255]);
+    }
+
+    #[test]
+    fn soft_light_with_middle_grey_leaves_the_backdrop() {
+        let [r, g, b] = opaque(9, [255, 128, 0], [128, 128, 128]);
+        assert_eq!([r, b], [255, 0]);
+        assert!(g.abs_diff(128) <= 1);
+    }
+
+    #[test]
+    fn non_separable_modes_keep_greys_grey() {
+        let (grey, red) = ([128, 128, 128], [255, 0, 0]);
+
+        // grey has no hue or saturation to give
+        assert_eq!(opaque(12, grey, red), grey);
+        assert_eq!(opaque(13, red, grey), [77, 77, 77]);
+        assert_eq!(opaque(14, grey, grey), grey);
+        assert_eq!(opaque(15, grey, [64, 64, 64]), [64, 64, 64]);
+    }
+
+    #[test]
+    fn transparent_pixels_and_opacity_are_respected_in_every_mode() {
+        let backdrop = [200, 100, 50, 255];
+        let src = [10, 20, 30, 255];
+
+        for mode in 0..AsepriteBlendMode::COUNT {
+            let mode = || AsepriteBlendMode::from(mode);
+
+            // nothing to lay down
+            assert_eq!(blend_pixel(mode(), backdrop, [10, 20, 30, 0], 255), backdrop);
+            assert_eq!(blend_pixel(mode(), backdrop, src, 0), backdrop);
+            // nothing to blend with
+            assert_eq!(blend_pixel(mode(), [0; 4], src, 255), src);
+            assert_eq!(blend_pixel(mode(), [0; 4], [10, 20, 30, 128], 255), [10, 20, 30, 128]);
+            // opaque over opaque stays opaque
+            assert_eq!(blend_pixel(mode(), backdrop, src, 255)[3], 255);
+        }
+    }
+
+    #[test]
+    fn opacity_mixes_with_the_backdrop() {
+        let mode = AsepriteBlendMode::Normal;
+        assert_eq!(blend_pixel(mode, [0, 0, 0, 255], [255, 255, 255, 255], 128), [128, 128, 128, 255]);
+        // half alpha at half opacity is a quarter
+        assert_eq!(blend_pixel(mode, [0, 0, 0, 0], [255, 255, 255, 128], 128), [255, 255, 255, 64]);
+        assert_eq!(blend_pixel(mode, [0, 0, 0, 128], [255, 255, 255, 128], 255), [170, 170, 170, 192]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,156 @@
+use std::{env, fs, path::PathBuf};
+
+use super::{ui_main::{ACCEPTED_TYPES, IMAGE_TYPES}, ui_traits::OverlayCorner};
+
+/// The folder inside the platform's config directory that settings are kept in.
+const CONFIG_DIR_NAME:  &str = "aseprite-viewer-rust";
+/// The name of the settings file inside `CONFIG_DIR_NAME`.
+pub(crate) const CONFIG_FILE_NAME: &str = "config.txt";
+
+/// User settings read from a plain text file of `key = value` lines, where `#`
+/// starts a comment.
+/// 
+/// A missing file, unknown keys, or values that can't be read all fall back to
+/// the defaults.
+pub(crate) struct Config {
+    /// The frame rate the window aims for, 0 leaves it uncapped.
+    pub target_fps: u32,
+    /// Waits for vertical sync, only applied when the window is created.
+    pub vsync:      bool,
+
+    /// The corner of the window toasts show up in, if it's set. Otherwise it's
+    /// wherever it was last time, see `Layout`.
+    pub toast_corner: Option<OverlayCorner>,
+
+    /// File extensions (with the leading `.`) loaded as Aseprite files.
+    pub sprite_extensions: Vec<String>,
+    /// File extensions (with the leading `.`) loaded as plain images.
+    pub image_extensions:  Vec<String>,
+
+    /// Opens sprites straight into the preview, playing their first tag or every
+    /// frame if they have no tags.
+    pub auto_play: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            target_fps: 60,
+            vsync:      false,
+
+            toast_corner: None,
+
+            sprite_extensions: ACCEPTED_TYPES.iter().map(|x| x.to_string()).collect(),
+            image_extensions:  IMAGE_TYPES.iter().map(|x| x.to_string()).collect(),
+
+            auto_play: false,
+        }
+    }
+}
+
+impl Config {
+    /// Gets the folder the config file and anything else kept between runs go
+    /// in for this platform, if there's anywhere sensible for it.
+    pub fn dir() -> Option<PathBuf> {
+        let base = if cfg!(target_os = "windows") {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            env::var_os("HOME").map(|x| PathBuf::from(x).join("Library").join("Application Support"))
+        } else {
+            env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|x| PathBuf::from(x).join(".config")))
+        };
+
+        base.map(|x| x.join(CONFIG_DIR_NAME))
+    }
+
+    /// Gets where the config file should be for this platform.
+    pub fn path() -> Option<PathBuf> {
+        Self::dir().map(|x| x.join(CONFIG_FILE_NAME))
+    }
+
+    /// Reads the config file, using the defaults for anything that's missing.
+    pub fn load() -> Self {
+        match Self::path().and_then(|x| fs::read_to_string(x).ok()) {
+            Some(text) => Self::parse(&text),
+            None       => Self::default(),
+        }
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut result = Self::default();
+
+        for (key, value) in key_values(text) {
+            match key {
+                "target_fps" => if let Ok(v) = value.parse() { result.target_fps = v },
+                "vsync"      => if let Ok(v) = value.parse() { result.vsync = v },
+
+                "toast_corner" => if let Ok(v) = value.parse() { result.toast_corner = Some(v) },
+
+                "sprite_extensions" => result.sprite_extensions = parse_extensions(value),
+                "image_extensions"  => result.image_extensions = parse_extensions(value),
+
+                "auto_play" => if let Ok(v) = value.parse() { result.auto_play = v },
+                _ => (),
+            }
+        }
+
+        result
+    }
+}
+
+/// Reads the `key = value` lines out of a settings file, trimmed and with any
+/// comments and lines that aren't settings left out.
+pub(crate) fn key_values(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    text.lines()
+        .map(|x| x.split('#').next().unwrap_or_default().trim())
+        .filter_map(|x| x.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+}
+
+/// Reads a comma separated list of extensions, adding the leading `.` to any
+/// that were written without one.
+fn parse_extensions(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| if x.starts_with('.') { x.to_owned() } else { format!(".{x}") })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions_get_their_dot_and_lose_empty_entries() {
+        let config = Config::parse("sprite_extensions = ase, .aseprite,, foo\n");
+        assert_eq!(config.sprite_extensions, [".ase", ".aseprite", ".foo"]);
+        // the other list is left alone
+        assert_eq!(config.image_extensions, IMAGE_TYPES);
+    }
+
+    #[test]
+    fn unreadable_values_keep_their_defaults() {
+        let config = Config::parse("target_fps = fast\nvsync = yes\nauto_play = 1\ntarget_fps = -30\n");
+        let defaults = Config::default();
+
+        assert_eq!(config.target_fps, defaults.target_fps);
+        assert_eq!(config.vsync, defaults.vsync);
+        assert_eq!(config.auto_play, defaults.auto_play);
+    }
+
+    #[test]
+    fn unknown_toast_corners_are_left_unset() {
+        assert!(Config::parse("toast_corner = middle\n").toast_corner.is_none());
+        assert!(matches!(Config::parse("toast_corner = bottom left\n").toast_corner, Some(OverlayCorner::BottomLeft)));
+    }
+
+    #[test]
+    fn settings_are_read() {
+        let config = Config::parse("# a comment\ntarget_fps = 144 # smooth\nvsync = true\nauto_play = true\n");
+        assert_eq!(config.target_fps, 144);
+        assert!(config.vsync);
+        assert!(config.auto_play);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+
+/// Pixels at least this opaque are kept, anything under it is left transparent
+/// since GIFs only have on or off transparency.
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// The most colours a GIF's colour table can hold.
+const MAX_COLOURS: usize = 256;
+
+/// LZW codes can't get longer than this many bits in a GIF.
+const MAX_CODE_SIZE: u8 = 12;
+
+/// The colours a GIF's frames are mapped onto, with one index always left for
+/// transparent pixels.
+pub(crate) struct GifPalette {
+    colours:     Vec<[u8; 3]>,
+    transparent: u8,
+
+    /// Which index each colour seen so far was mapped onto.
+    lookup: HashMap<[u8; 3], u8>,
+}
+
+impl GifPalette {
+    /// Uses a sprite's own palette as it is, for indexed sprites. Colours made by
+    /// blending that aren't in it get the closest one.
+    pub fn from_palette(palette: &[[u8; 4]], transparent_index: u8) -> Self {
+        let mut colours: Vec<[u8; 3]> = palette.iter().take(MAX_COLOURS).map(|x| [x[0], x[1], x[2]]).collect();
+        if colours.len() <= transparent_index as usize {
+            colours.resize(transparent_index as usize + 1, [0; 3]);
+        }
+
+        // the first entry wins when the palette has the same colour twice
+        let mut lookup = HashMap::new();
+        for (i, colour) in colours.iter().enumerate().rev().filter(|(i, _)| *i != transparent_index as usize) {
+            lookup.insert(*colour, i as u8);
+        }
+
+        Self { colours, transparent: transparent_index, lookup }
+    }
+
+    /// Picks colours for RGBA frames by median cut, splitting the colours used
+    /// into boxes at the median of their widest channel until there's a box for
+    /// every colour the table has room for.
+    pub fn median_cut<'a>(frames: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let mut counts: HashMap<[u8; 3], u64> = HashMap::new();
+        for px in frames.into_iter().flat_map(|x| x.chunks_exact(4)) {
+            if px[3] >= ALPHA_THRESHOLD {
+                *counts.entry([px[0], px[1], px[2]]).or_default() += 1;
+            }
+        }
+
+        // sorted so the same frames always give the same palette
+        let mut all: Vec<([u8; 3], u64)> = counts.into_iter().collect();
+        all.sort_unstable();
+
+        let mut boxes = if all.is_empty() { vec![] } else { vec![all] };
+
+        // the last slot goes to transparency
+        while boxes.len() < MAX_COLOURS - 1 {
+            let widest = boxes.iter().enumerate()
+                .filter(|(_, b)| b.len() > 1)
+                .map(|(i, b)| {
+                    let (channel, range) = widest_channel(b);
+                    (i, channel, range)
+                })
+                .max_by_key(|(_, _, range)| *range);
+
+            let Some((at, channel, _)) = widest else { break };
+
+            let mut first = boxes.swap_remove(at);
+            first.sort_unstable_by_key(|(c, _)| c[channel]);
+
+            // split where about half the pixels land on either side
+            let total: u64 = first.iter().map(|(_, n)| n).sum();
+            let mut seen = 0;
+            let median = first.iter().position(|(_, n)| {
+                seen += n;
+                seen * 2 >= total
+            }).unwrap_or(0);
+
+            let second = first.split_off((median + 1).clamp(1, first.len() - 1));
+            boxes.push(first);
+            boxes.push(second);
+        }
+
+        let mut lookup = HashMap::new();
+        let mut colours = Vec::with_capacity(boxes.len() + 1);
+
+        for (i, b) in boxes.iter().enumerate() {
+            let total: u64 = b.iter().map(|(_, n)| n).sum::<u64>().max(1);
+            let average = |ch: usize| (b.iter().map(|(c, n)| c[ch] as u64 * n).sum::<u64>() / total) as u8;
+
+            colours.push([average(0), average(1), average(2)]);
+            for (c, _) in b {
+                lookup.insert(*c, i as u8);
+            }
+        }
+
+        let transparent = colours.len() as u8;
+        colours.push([0; 3]);
+
+        Self { colours, transparent, lookup }
+    }
+
+    /// Gets the index an RGBA pixel maps onto.
+    fn index_of(&mut self, px: &[u8]) -> u8 {
+        if px[3] < ALPHA_THRESHOLD {
+            return self.transparent;
+        }
+
+        let colour = [px[0], px[1], px[2]];
+        if let Some(i) = self.lookup.get(&colour) {
+            return *i;
+        }
+
+        let distance = |c: &[u8; 3]| (0..3).map(|ch| (c[ch] as i32 - colour[ch] as i32).pow(2)).sum::<i32>();
+        let nearest = self.colours.iter().enumerate()
+            .filter(|(i, _)| *i != self.transparent as usize)
+            .min_by_key(|(_, c)| distance(c))
+            .map_or(self.transparent, |(i, _)| i as u8);
+
+        self.lookup.insert(colour, nearest);
+        nearest
+    }
+
+    /// Gets how many bits the colour table's indices take up, which is at least 1.
+    fn table_bits(&self) -> u8 {
+        let mut bits = 1;
+        while (1 << bits) < self.colours.len() {
+            bits += 1;
+        }
+
+        bits
+    }
+}
+
+/// Gets the channel a box of colours spans the most of, and how far it spans.
+fn widest_channel(colours: &[([u8; 3], u64)]) -> (usize, u8) {
+    (0..3).map(|ch| {
+        let min = colours.iter().map(|(c, _)| c[ch]).min().unwrap_or(0);
+        let max = colours.iter().map(|(c, _)| c[ch]).max().unwrap_or(0);
+        (ch, max - min)
+    }).max_by_key(|(_, range)| *range).unwrap_or((0, 0))
+}
+
+/// Packs variable length codes into bytes, least significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count:  u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.count;
+        self.count += size;
+
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+
+        self.bytes
+    }
+}
+
+/// Compresses colour indices with GIF's flavour of LZW.
+fn lzw_compress(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut out = BitWriter::default();
+    let mut dictionary: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = min_code_size + 1;
+    let mut next_code = end + 1;
+
+    out.write(clear, code_size);
+
+    let Some((&first, rest)) = indices.split_first() else {
+        out.write(end, code_size);
+        return out.finish();
+    };
+
+    let mut prefix = first as u16;
+    for &index in rest {
+        if let Some(&code) = dictionary.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+
+        out.write(prefix, code_size);
+
+        // decoders add an entry for every code read and grow the code size once
+        // the next entry no longer fits, which this has to stay in step with
+        if next_code < (1 << MAX_CODE_SIZE) {
+            if next_code >= (1 << code_size) && code_size < MAX_CODE_SIZE {
+                code_size += 1;
+            }
+
+            dictionary.insert((prefix, index), next_code);
+            next_code += 1;
+        } else {
+            out.write(clear, code_size);
+            dictionary.clear();
+            code_size = min_code_size + 1;
+            next_code = end + 1;
+        }
+
+        prefix = index as u16;
+    }
+
+    out.write(prefix, code_size);
+    if next_code >= (1 << code_size) && code_size < MAX_CODE_SIZE {
+        code_size += 1;
+    }
+    out.write(end, code_size);
+
+    out.finish()
+}
+
+/// Writes data as GIF sub-blocks of at most 255 bytes each, ending with an empty
+/// one.
+fn write_sub_blocks(gif: &mut Vec<u8>, data: &[u8]) {
+    for block in data.chunks(255) {
+        gif.push(block.len() as u8);
+        gif.extend_from_slice(block);
+    }
+
+    gif.push(0);
+}
+
+/// Encodes RGBA frames of the same size as an animated GIF, each shown for its
+/// duration in milliseconds. When `looping` is false it plays through once.
+/// `on_frame` is called with how many frames are done after each one.
+pub(crate) fn encode(width: u16, height: u16, frames: &[(&[u8], u16)], palette: &mut GifPalette, looping: bool, mut on_frame: impl FnMut(usize)) -> Vec<u8> {
+    let table_bits = palette.table_bits();
+    let min_code_size = table_bits.max(2);
+
+    let mut gif = b"GIF89a".to_vec();
+
+    gif.extend_from_slice(&width.to_le_bytes());
+    gif.extend_from_slice(&height.to_le_bytes());
+    // a global colour table with 8 bits per channel, then the background colour
+    // and pixel aspect ratio which are both left alone
+    gif.extend_from_slice(&[0x80 | 0x70 | (table_bits - 1), 0, 0]);
+
+    for i in 0..(1usize << table_bits) {
+        gif.extend_from_slice(&palette.colours.get(i).copied().unwrap_or([0; 3]));
+    }
+
+    if looping {
+        gif.extend_from_slice(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+    }
+
+    for (done, (pixels, duration)) in frames.iter().enumerate() {
+        // most viewers slow anything under 2 centiseconds right down
+        let delay = (duration.saturating_add(5) / 10).max(2);
+
+        // each frame is cleared away before the next so transparent pixels don't
+        // show the last frame through
+        gif.extend_from_slice(&[0x21, 0xF9, 0x04, (2 << 2) | 1]);
+        gif.extend_from_slice(&delay.to_le_bytes());
+        gif.extend_from_slice(&[palette.transparent, 0]);
+
+        gif.push(0x2C);
+        gif.extend_from_slice(&[0, 0, 0, 0]);
+        gif.extend_from_slice(&width.to_le_bytes());
+        gif.extend_from_slice(&height.to_le_bytes());
+        gif.push(0);
+
+        let indices: Vec<u8> = pixels.chunks_exact(4).map(|px| palette.index_of(px)).collect();
+
+        gif.push(min_code_size);
+        write_sub_blocks(&mut gif, &lzw_compress(&indices, min_code_size));
+        on_frame(done + 1);
+    }
+
+    gif.push(0x3B);
+    gif
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes GIF flavoured LZW the way a viewer would, also giving back how
+    /// many clear codes there were and the longest code read.
+    fn lzw_decompress(data: &[u8], min_code_size: u8) -> (Vec<u8>, usize, u8) {
+        let clear = 1usize << min_code_size;
+        let end = clear + 1;
+        let fresh = || (0..clear).map(|x| vec![x as u8]).chain([vec![], vec![]]).collect::<Vec<_>>();
+
+        let mut table = fresh();
+        let mut code_size = min_code_size + 1;
+        let (mut clears, mut longest) = (0, code_size);
+        let mut prev: Option<Vec<u8>> = None;
+        let mut out = vec![];
+        let (mut buffer, mut count, mut bytes) = (0u32, 0u8, data.iter());
+
+        loop {
+            while count < code_size {
+                buffer |= (*bytes.next().expect("ran out before the end code") as u32) << count;
+                count += 8;
+            }
+            let code = (buffer & ((1 << code_size) - 1)) as usize;
+            buffer >>= code_size;
+            count -= code_size;
+            longest = longest.max(code_size);
+
+            if code == clear {
+                table = fresh();
+                code_size = min_code_size + 1;
+                prev = None;
+                clears += 1;
+                continue;
+            }
+            if code == end {
+                break;
+            }
+
+            let entry = match (table.get(code), &prev) {
+                (Some(x), _) => x.clone(),
+                (None, Some(p)) if code == table.len() => [&p[..], &p[..1]].concat(),
+                _ => panic!("code {code} isn't in the table yet"),
+            };
+            out.extend(&entry);
+
+            if let Some(p) = prev {
+                if table.len() < (1 << MAX_CODE_SIZE) {
+                    table.push([&p[..], &entry[..1]].concat());
+                }
+            }
+            if table.len() >= (1 << code_size) && code_size < MAX_CODE_SIZE {
+                code_size += 1;
+            }
+            prev = Some(entry);
+        }
+
+        (out, clears, longest)
+    }
+
+    /// Gets the same noisy looking indices for a seed every time.
+    fn noise(len: usize, colours: u32, seed: u32) -> Vec<u8> {
+        let mut state = seed;
+        (0..len).map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            ((state >> 16) % colours) as u8
+        }).collect()
+    }
+
+    #[test]
+    fn lzw_round_trips() {
+        for (indices, min_code_size) in [
+            (vec![], 2),
+            (vec![3], 2),
+            (vec![0, 1, 2, 3, 0, 1, 2, 3, 0, 1], 2),
+            (vec![1; 1000], 2),
+            (noise(500, 256, 7), 8),
+        ] {
+            let (decoded, clears, _) = lzw_decompress(&lzw_compress(&indices, min_code_size), min_code_size);
+            assert_eq!(decoded, indices);
+            assert_eq!(clears, 1, "only the clear code at the start");
+        }
+    }
+
+    #[test]
+    fn lzw_codes_grow_and_clear_at_4096() {
+        // not enough to fill the table, but codes have to grow past where they start
+        let indices = noise(3000, 4, 1);
+        let (decoded, clears, longest) = lzw_decompress(&lzw_compress(&indices, 2), 2);
+        assert_eq!(decoded, indices);
+        assert_eq!(clears, 1);
+        assert!((4..MAX_CODE_SIZE).contains(&longest), "longest code was {longest} bits");
+
+        // noise over every colour fills the table a few times over
+        let indices = noise(40000, 256, 2);
+        let (decoded, clears, longest) = lzw_decompress(&lzw_compress(&indices, 8), 8);
+        assert_eq!(decoded, indices);
+        assert!(clears > 2, "{clears} clear codes");
+        assert_eq!(longest, MAX_CODE_SIZE);
+    }
+
+    /// Makes opaque RGBA pixels out of colours.
+    fn rgba(colours: &[[u8; 3]]) -> Vec<u8> {
+        colours.iter().flat_map(|[r, g, b]| [*r, *g, *b, 255]).collect()
+    }
+
+    #[test]
+    fn median_cut_keeps_every_colour_when_there_is_room() {
+        let colours = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [10, 20, 30]];
+        let mut pixels = rgba(&colours);
+        // under the alpha threshold, so it doesn't get a colour of its own
+        pixels.extend([1, 2, 3, ALPHA_THRESHOLD - 1]);
+
+        let mut palette = GifPalette::median_cut([&pixels[..]]);
+        assert_eq!(palette.colours.len(), 5);
+        assert_eq!(palette.transparent, 4);
+
+        for px in pixels.chunks_exact(4).take(4) {
+            let i = palette.index_of(px) as usize;
+            assert_eq!(palette.colours[i], [px[0], px[1], px[2]]);
+        }
+        assert_eq!(palette.index_of(&pixels[16..]), palette.transparent);
+        assert_eq!(palette.table_bits(), 3);
+    }
+
+    #[test]
+    fn median_cut_fills_the_table_and_maps_nearby() {
+        let colours: Vec<[u8; 3]> = (0..1024u32).map(|i| [(i % 32 * 8) as u8, (i / 32 * 8) as u8, 128]).collect();
+        let pixels = rgba(&colours);
+
+        let mut palette = GifPalette::median_cut([&pixels[..]]);
+        assert_eq!(palette.colours.len(), MAX_COLOURS);
+        assert_eq!(palette.transparent as usize, MAX_COLOURS - 1);
+        assert_eq!(palette.table_bits(), 8);
+
+        // the same frames always give the same palette
+        assert_eq!(GifPalette::median_cut([&pixels[..]]).colours, palette.colours);
+
+        for px in pixels.chunks_exact(4) {
+            let i = palette.index_of(px) as usize;
+            let colour = palette.colours[i];
+            let off = (0..3).map(|ch| colour[ch].abs_diff(px[ch])).max().unwrap();
+            assert!(off <= 16, "{px:?} went to {colour:?}");
+        }
+    }
+
+    #[test]
+    fn median_cut_of_nothing_is_just_transparency() {
+        let palette = GifPalette::median_cut([&[0u8, 0, 0, 0][..]]);
+        assert_eq!(palette.colours, [[0; 3]]);
+        assert_eq!(palette.transparent, 0);
+    }
+
+    #[test]
+    fn long_frames_dont_overflow_the_delay() {
+        let pixels = [0u8, 0, 0, 255];
+        let mut palette = GifPalette::median_cut([&pixels[..]]);
+        let gif = encode(1, 1, &[(&pixels, u16::MAX)], &mut palette, false, |_| ());
+
+        let at = gif.windows(3).position(|x| x == [0x21, 0xF9, 0x04]).unwrap();
+        assert_eq!(u16::from_le_bytes([gif[at + 4], gif[at + 5]]), 6553);
+    }
+}
\ No newline at end of file
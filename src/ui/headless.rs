@@ -0,0 +1,70 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::loaded_aseprite::{LoadedSprite, SheetLayout};
+
+/// Reads a sprite and saves a frame, a sprite sheet or a GIF of it without ever
+/// opening a window, for build scripts and the like. `args` are everything after
+/// `--export`: the sprite, where to save to, then optionally one of
+/// `--frame <n>`, `--sheet [horizontal|vertical|grid]` or `--gif [tag]`. The
+/// first frame is saved if none of them are given.
+pub fn export(args: &[String], usage: &str) -> io::Result<()> {
+    let invalid = |text: &str| io::Error::new(io::ErrorKind::InvalidInput, text.to_owned());
+
+    let (Some(input), Some(output)) = (args.first(), args.get(1)) else {
+        return Err(invalid(usage));
+    };
+    let output = Path::new(output);
+
+    let sprite = LoadedSprite::read(input).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    for warning in &sprite.warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    let option = args.get(3).map(|x| x.as_str());
+
+    match args.get(2).map(|x| x.as_str()) {
+        None => export_frame(&sprite, output, 0),
+        Some("--frame") => {
+            let frame = option.and_then(|x| x.parse().ok()).ok_or_else(|| invalid(usage))?;
+            export_frame(&sprite, output, frame)
+        },
+        Some("--sheet") => {
+            let layout = match option {
+                None | Some("grid") => SheetLayout::square(sprite.frame_count),
+                Some("horizontal")  => SheetLayout::Horizontal,
+                Some("vertical")    => SheetLayout::Vertical,
+                Some(_)             => return Err(invalid(usage)),
+            };
+
+            sprite.export_sheet(output, layout)
+        },
+        Some("--gif") => {
+            let tag = match option {
+                Some(name) => Some(
+                    (0..sprite.loaded_tags.len())
+                        .find(|x| sprite.tag_name(*x) == Some(name))
+                        .ok_or_else(|| invalid(&format!("{input} has no tag named {name}")))?
+                ),
+                None => None,
+            };
+
+            sprite.export_gif(output, tag)
+        },
+        Some(_) => Err(invalid(usage)),
+    }
+}
+
+/// Saves one composited frame of a sprite as a PNG.
+fn export_frame(sprite: &LoadedSprite, output: &Path, frame: usize) -> io::Result<()> {
+    if frame >= sprite.frame_count {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame {frame} is out of range, there are {} frames", sprite.frame_count)
+        ));
+    }
+
+    let png = sprite.frame_png(frame).ok_or_else(|| io::Error::other(format!("couldn't encode frame {frame}")))?;
+    fs::write(output, png)
+}
\ No newline at end of file
@@ -0,0 +1,160 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use super::{config::{key_values, Config, CONFIG_FILE_NAME}, ui_main::{LAYER_LIST_MIN_WIDTH, LAYER_LIST_WINDOW_GAP, WINDOW_H, WINDOW_W}, ui_traits::OverlayCorner};
+
+/// The name of the file the layout is kept in, next to the config file.
+const LAYOUT_FILE_NAME: &str = "layout.txt";
+
+/// The smallest the window can be, so a bad layout file can't leave it too
+/// small to use.
+pub(crate) const MIN_WINDOW_W: i32 = 320;
+pub(crate) const MIN_WINDOW_H: i32 = 240;
+
+/// How the window was laid out when the viewer was last closed, so it opens
+/// the same way again. Kept as `key = value` lines like the config, but
+/// written by the viewer itself.
+///
+/// A missing file, unknown keys, or values that can't be read all fall back to
+/// the defaults.
+pub(crate) struct Layout {
+    pub window_w: i32,
+    pub window_h: i32,
+
+    pub layer_list_visible: bool,
+    pub layer_list_width:   f32,
+
+    pub toast_corner: OverlayCorner,
+    /// How zoomed in the view was, if there was a sprite being shown.
+    pub zoom:         Option<f32>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            window_w: WINDOW_W,
+            window_h: WINDOW_H,
+
+            layer_list_visible: true,
+            layer_list_width:   120.0,
+
+            toast_corner: OverlayCorner::default(),
+            zoom:         None,
+        }
+    }
+}
+
+impl Layout {
+    /// Gets where the layout is kept, if there's anywhere sensible for it.
+    fn path() -> Option<PathBuf> {
+        Config::dir().map(|x| x.join(LAYOUT_FILE_NAME))
+    }
+
+    /// Reads the layout back, using the defaults for anything that's missing.
+    pub fn load() -> Self {
+        Self::path().map_or_else(Self::default, |x| Self::load_from(&x))
+    }
+
+    /// Reads the layout back from `path`, which doesn't have to be there.
+    fn load_from(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(_)   => Self::default(),
+        }
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut result = Self::default();
+
+        for (key, value) in key_values(text) {
+            match key {
+                "window_w" => if let Ok(v) = value.parse::<i32>() { result.window_w = v.max(MIN_WINDOW_W) },
+                "window_h" => if let Ok(v) = value.parse::<i32>() { result.window_h = v.max(MIN_WINDOW_H) },
+
+                "layer_list_visible" => if let Ok(v) = value.parse() { result.layer_list_visible = v },
+                "layer_list_width"   => if let Ok(v) = value.parse::<f32>() {
+                    if v.is_finite() { result.layer_list_width = v }
+                },
+
+                "toast_corner" => if let Ok(v) = value.parse() { result.toast_corner = v },
+                "zoom"         => if let Ok(v) = value.parse::<f32>() { result.zoom = v.is_finite().then_some(v) },
+                _ => (),
+            }
+        }
+
+        // kept to what dragging the edge of the layer list allows
+        let widest = (result.window_w as f32 - LAYER_LIST_WINDOW_GAP).max(LAYER_LIST_MIN_WIDTH);
+        result.layer_list_width = result.layer_list_width.clamp(LAYER_LIST_MIN_WIDTH, widest);
+
+        result
+    }
+
+    /// Writes the layout out, making the config directory if it isn't there.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::path() else { return Ok(()) };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut text = format!(
+            "# written by the viewer when it's closed, settings go in {}\nwindow_w = {}\nwindow_h = {}\nlayer_list_visible = {}\nlayer_list_width = {}\ntoast_corner = {}\n",
+            CONFIG_FILE_NAME,
+            self.window_w, self.window_h,
+            self.layer_list_visible, self.layer_list_width,
+            self.toast_corner,
+        );
+
+        if let Some(zoom) = self.zoom {
+            text.push_str(format!("zoom = {zoom}\n").as_str());
+        }
+
+        fs::write(path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn garbage_values_fall_back_to_the_defaults() {
+        let layout = Layout::parse(
+            "window_w = wide\nwindow_h = -5\nlayer_list_visible = maybe\nlayer_list_width = inf\ntoast_corner = middle\nzoom = NaN\nnot a setting\n"
+        );
+
+        assert_eq!(layout.window_w, WINDOW_W);
+        // a number, just too small for a window
+        assert_eq!(layout.window_h, MIN_WINDOW_H);
+        assert!(layout.layer_list_visible);
+        assert_eq!(layout.layer_list_width, Layout::default().layer_list_width);
+        assert!(matches!(layout.toast_corner, OverlayCorner::TopRight));
+        assert_eq!(layout.zoom, None);
+    }
+
+    #[test]
+    fn missing_files_are_the_defaults() {
+        let layout = Layout::load_from(Path::new("/there/is/no/layout.txt"));
+        assert_eq!((layout.window_w, layout.window_h), (WINDOW_W, WINDOW_H));
+        assert!(layout.zoom.is_none());
+    }
+
+    #[test]
+    fn layer_lists_are_kept_narrower_than_the_window() {
+        let layout = Layout::parse("window_w = 400\nlayer_list_width = 100000\n");
+        assert_eq!(layout.layer_list_width, 400. - LAYER_LIST_WINDOW_GAP);
+
+        // even windows at their smallest have room for the narrowest one
+        let layout = Layout::parse("window_w = 1\nlayer_list_width = 0\n");
+        assert_eq!(layout.window_w, MIN_WINDOW_W);
+        assert_eq!(layout.layer_list_width, LAYER_LIST_MIN_WIDTH);
+    }
+
+    #[test]
+    fn layouts_are_read_back() {
+        let layout = Layout::parse("window_w = 1000 # wide\nlayer_list_visible = false\ntoast_corner = bottom_left\nzoom = 2.5\n");
+        assert_eq!(layout.window_w, 1000);
+        assert!(!layout.layer_list_visible);
+        assert!(matches!(layout.toast_corner, OverlayCorner::BottomLeft));
+        assert_eq!(layout.zoom, Some(2.5));
+    }
+}
\ No newline at end of file
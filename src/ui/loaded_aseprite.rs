@@ -1,12 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Div, Mul, Sub};
-use std::{f32::consts::FRAC_PI_3, fs::File};
+use std::{f32::consts::FRAC_PI_3, fs::{self, File}, io, path::{Path, PathBuf}, rc::Rc, sync::Arc};
 
 use raylib::prelude::*;
-use raylib::{camera::Camera2D, color::Color, math::{Rectangle, Vector2}, texture::{RaylibTexture2D, Texture2D}, RaylibHandle, RaylibThread};
+use raylib::{camera::Camera2D, color::Color, math::{Rectangle, Vector2}, texture::Texture2D, RaylibHandle, RaylibThread};
 
-use crate::ase::aseprite::{self, Aseprite, AsepriteBlendMode, AsepriteError, AsepriteLayerFlags, AsepriteLayerType, AsepriteTagDirection};
+use crate::ase::aseprite::{self, Aseprite, AsepriteBlendMode, AsepriteError, AsepriteLayerFlags, AsepriteLayerType, AsepriteTagDirection, AsepriteTileMasks, AsepriteTilesetChunk, AsepriteTilesetFlags};
 
+use super::blend::blend_pixel;
+use super::gif::{self, GifPalette};
+use super::palette_file::PaletteFormat;
+use super::playback::{frame_duration, OnionSkin, Playback};
 use super::ui_main::{self, FONT_SIZE_BIG, FONT_SIZE_REG};
 
 /// Used as the gap between cels on the grid.
@@ -20,33 +24,261 @@ const BIG_LINE_COLOR:   Color = Color::GRAY;
 /// Used for the layer names and frame numbers on the grid.
 const LABEL_COLOR:      Color = SMALL_LINE_COLOR;
 
+/// Drawn just behind labels so they stay readable over light sprites.
+const LABEL_SHADOW_COLOR: Color = Color{r: 0, g: 0, b: 0, a: 192};
+
 /// The colour used to signify linked cels and the animation pointing to the cel.
 const LINKED_COLOR:     Color = Color::ORANGERED;
+/// The colour of the placeholder grid drawn for tilemap cels.
+const TILEMAP_COLOR:    Color = Color::SKYBLUE;
 /// A generic error colour for trying to determine if something was drawn proper.
 const ERR_COLOR:        Color = Color::FUCHSIA;
+/// The border colour of the cels pinned by the user.
+const PINNED_COLOR:     Color = Color::GOLD;
+
+/// The two colours of the checkerboard drawn behind cels to show transparency.
+const CHECKER_COLOR_LIGHT: Color = Color{r: 204, g: 204, b: 204, a: 255};
+const CHECKER_COLOR_DARK:  Color = Color{r: 153, g: 153, b: 153, a: 255};
+/// How big the checkerboard's squares are on screen, whatever the zoom.
+const CHECKER_SCREEN_SIZE: f32 = 16.0;
 
 /// A general number to signify no parent. Should be a reasonably infeasible
 /// number.
-const NO_PARENT:       usize = usize::MAX;
-/// How far the recursive functions can go until they stop.
-const RECURSIVE_LIMIT: u8    = 16;
+const NO_PARENT: usize = usize::MAX;
+
+/// The zoom level the crosshair over the hovered pixel starts showing at.
+const CROSSHAIR_MIN_ZOOM: f32 = 4.0;
+/// The colour of the crosshair over the hovered pixel.
+const CROSSHAIR_COLOR:    Color = Color::WHITE;
+
+/// The zoom level the pixel grid starts fading in at.
+const PIXEL_GRID_MIN_ZOOM: f32 = 8.0;
+/// The colour of the pixel grid, alpha is ignored.
+const PIXEL_GRID_COLOR:    Color = Color::BLACK;
+/// How opaque the pixel grid gets once it's fully faded in.
+const PIXEL_GRID_MAX_ALPHA: u8 = 48;
+
+/// How long a cel's texture is kept on the GPU after it goes off screen, in
+/// seconds. Panning back and forth shouldn't have to upload it again each time.
+const TEXTURE_KEEP_SECONDS: f64 = 10.0;
+
+/// The colour of slices the user didn't give one, the same as Aseprite's.
+const SLICE_COLOR: Color = Color{r: 0, g: 0, b: 255, a: 255};
 
 const DEBUG_VISUALS: bool = false;
 
+/// Draws a checkerboard over the part of `area` inside `visible`, with squares
+/// `size` across lined up with the top left corner of `area` so they stay put
+/// while the view moves.
+fn draw_checkerboard(d: &mut impl RaylibDraw, area: Rectangle, visible: &Rectangle, size: f32) {
+    let Some(clip) = area.get_collision_rec(visible) else { return };
+    d.draw_rectangle_rec(clip, CHECKER_COLOR_LIGHT);
+
+    let first_x = ((clip.x - area.x) / size).floor() as i32;
+    let first_y = ((clip.y - area.y) / size).floor() as i32;
+    let last_x = ((clip.x + clip.width - area.x) / size).ceil() as i32;
+    let last_y = ((clip.y + clip.height - area.y) / size).ceil() as i32;
+
+    for y in first_y..last_y {
+        for x in first_x..last_x {
+            if (x + y) % 2 == 0 {
+                continue;
+            }
+
+            let square = Rectangle{
+                x:      area.x + x as f32 * size,
+                y:      area.y + y as f32 * size,
+                width:  size,
+                height: size,
+            };
+
+            if let Some(square) = square.get_collision_rec(&clip) {
+                d.draw_rectangle_rec(square, CHECKER_COLOR_DARK);
+            }
+        }
+    }
+}
+
+/// Draws text with a 1px shadow under it so it can be read on top of both light
+/// and dark backgrounds.
+pub(crate) fn draw_label(d: &mut impl RaylibDraw, text: &str, x: i32, y: i32, font_size: i32, colour: Color) {
+    d.draw_text(text, x + 1, y + 1, font_size, LABEL_SHADOW_COLOR);
+    d.draw_text(text, x, y, font_size, colour);
+}
+
+/// Maps indexed pixels through a palette into RGBA pixels. The transparent
+/// index becomes fully transparent, and indices outside the palette show up as
+/// the error colour.
+fn indexed_to_rgba(indices: &[u8], palette: &[[u8; 4]], transparent_index: u8) -> Vec<u8> {
+    indices.iter().flat_map(|&i| {
+        if i == transparent_index {
+            [0; 4]
+        } else {
+            palette.get(i as usize).copied().unwrap_or([ERR_COLOR.r, ERR_COLOR.g, ERR_COLOR.b, ERR_COLOR.a])
+        }
+    }).collect()
+}
+
+/// Gets a single fully transparent pixel in a sprite's own pixel format.
+fn empty_pixel(colour_depth: u16, transparent_index: u8) -> Vec<u8> {
+    match colour_depth {
+         8 => vec![transparent_index],
+        16 => vec![0; 2],
+        _  => vec![0; 4],
+    }
+}
+
+/// Lays a tilemap's tiles out into one image, `columns` tiles across, with
+/// pixels in the same format as the tileset's. Anything not covered by a tile
+/// is filled with `empty`, which is a whole pixel of bytes.
+fn blit_tilemap(tiles: &[u32], masks: AsepriteTileMasks, columns: usize, rows: usize, tileset: &AsepriteTilesetChunk, empty: &[u8]) -> Vec<u8> {
+    let bytes_per_pixel = empty.len();
+    let (tile_w, tile_h) = (tileset.tile_width as usize, tileset.tile_height as usize);
+    let width = columns * tile_w;
+
+    let mut out = empty.repeat(width * rows * tile_h);
+    let source = tileset.tiles.as_deref().unwrap_or_default();
+    let skip_zero = tileset.flags & AsepriteTilesetFlags::EmptyTileIs0 > 0;
+
+    for (i, &tile) in tiles.iter().enumerate().take(columns * rows) {
+        let (id, x_flip, y_flip, diagonal_flip) = masks.split(tile);
+        if id == 0 && skip_zero {
+            continue;
+        }
+
+        // tiles past the end of the tileset are left empty, like Aseprite does
+        let Some(pixels) = source.get(id as usize) else { continue };
+        let (left, top) = ((i % columns) * tile_w, (i / columns) * tile_h);
+
+        // Aseprite only lets square tiles be flipped diagonally
+        let diagonal_flip = diagonal_flip && tile_w == tile_h;
+
+        for y in 0..tile_h {
+            for x in 0..tile_w {
+                // the diagonal flip swaps x and y before the other two flips, so
+                // they're undone the other way round to find where a pixel came from
+                let sx = if x_flip { tile_w - 1 - x } else { x };
+                let sy = if y_flip { tile_h - 1 - y } else { y };
+                let (sx, sy) = if diagonal_flip { (sy, sx) } else { (sx, sy) };
+
+                let from = (sy * tile_w + sx) * bytes_per_pixel;
+                let Some(px) = pixels.get(from..(from + bytes_per_pixel)) else { continue };
+
+                let to = ((top + y) * width + left + x) * bytes_per_pixel;
+                out[to..(to + bytes_per_pixel)].copy_from_slice(px);
+            }
+        }
+    }
+
+    out
+}
+
+/// Spreads greyscale pixels with alpha out into RGBA pixels.
+fn grey_alpha_to_rgba(pixels: &[u8]) -> Vec<u8> {
+    pixels.chunks_exact(2).flat_map(|x| [x[0], x[0], x[0], x[1]]).collect()
+}
+
+/// Makes an image from tightly packed RGBA pixels, copying them straight into a
+/// buffer raylib owns so it's freed along with the image. Anything `pixels`
+/// doesn't cover is left transparent.
+fn image_from_rgba(width: i32, height: i32, pixels: &[u8]) -> Image {
+    let size = width.max(0) as usize * height.max(0) as usize * 4;
+
+    // far too big for raylib to do anything with anyway
+    let Ok(alloc_size) = u32::try_from(size) else {
+        return Image::gen_image_color(0, 0, Color::BLANK);
+    };
+
+    unsafe {
+        // raylib's allocations start zeroed
+        let data = ffi::MemAlloc(alloc_size) as *mut u8;
+        if !data.is_null() {
+            std::ptr::copy_nonoverlapping(pixels.as_ptr(), data, pixels.len().min(size));
+        }
+
+        Image::from_raw(ffi::Image {
+            data: data as *mut _,
+            width, height,
+            mipmaps: 1,
+            format:  PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8 as i32,
+        })
+    }
+}
+
+/// Quotes and escapes a string for writing into JSON.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"'  => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Combines a layer's opacity with the opacity of one of its cels.
+fn combined_opacity(layer_opacity: u8, cel_opacity: u8) -> u8 {
+    let l = (layer_opacity as f64) / 255.0;
+    let r = (cel_opacity as f64) / 255.0;
+    (l * r * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Gets a cel ready to be laid over a frame with a blend mode and opacity.
+/// Returns `None` for cels without pixels.
+fn stacked_cel(cel: &PreparedCel, blend_mode: AsepriteBlendMode, opacity: u8) -> Option<StackedCel> {
+    Some(StackedCel {
+        pixels: cel.pixels.clone()?,
+        x:      cel.position.x as i32,
+        y:      cel.position.y as i32,
+        width:  cel.size.x as i32,
+        height: cel.size.y as i32,
+        blend_mode, opacity,
+    })
+}
+
 pub struct PreparedCel {
     // image:       Option<Image>,
+    /// Only uploaded once the cel comes on screen, see `stream_textures`.
     texture:     Option<Texture2D>,
+    /// When the cel was last needed on screen, in seconds since the window was
+    /// opened. Negative infinity if it never has been.
+    last_seen:   f64,
+    /// Set once the GPU turns the cel down, so it isn't tried again every frame.
+    upload_failed: bool,
+    /// The cel's pixels as RGBA, kept for compositing frames without the gpu.
+    /// Shared with exports flattening frames on another thread.
+    pixels:      Option<Arc<[u8]>>,
+    /// The palette index of each pixel, for cels in indexed sprites.
+    indices:     Option<Rc<[u8]>>,
     frame_index: usize,
     layer_index: u16,
     position:    Vector2,
     size:        Vector2,
     opacity:     u8,
+    /// Moves the cel up or down past the cels of other layers in the same frame.
+    z_index:     i16,
 
     linked_to_frame: Option<u16>,
 
     content_bounds:   Rectangle,
     collision_bounds: Rectangle,
 
+    /// The size the cel is drawn at, if it's different to the size of its
+    /// texture. Only cels on reference layers get scaled.
+    scaled_size: Option<Vector2>,
+
+    /// The size in tiles of a tilemap cel whose tileset isn't in the file, drawn
+    /// as a placeholder grid instead of its tiles.
+    tile_grid: Option<(u16, u16)>,
+
     hover: bool
 }
 
@@ -56,27 +288,510 @@ pub struct PreparedLayer {
     pub opacity:      u8,
     pub name:         String,
     pub layer_type:   AsepriteLayerType,
+    /// The id of the tileset a tilemap layer's tiles come from.
+    pub tileset_index: Option<u32>,
 
     pub visible:      bool,
     pub background:   bool,
     pub is_reference: bool,
+    /// Groups only, hides the layers inside it in the layer list.
+    pub collapsed:    bool,
 
     pub parent_index: usize,
-    pub full_name:    Option<String>
+    pub full_name:    Option<String>,
+
+    /// The text the user gave the layer in Aseprite, if any.
+    pub note:         Option<String>
 }
 
 pub struct PreparedTag {
     from:      usize,
     to:        usize,
     direction: AsepriteTagDirection,
-    name:      String
+    name:      String,
+    /// The colour of the tag's bar over the frames it spans.
+    colour:    Color,
+
+    /// How many times the tag plays, where 0 means it loops forever.
+    repeat_count: u16
+}
+
+impl PreparedTag {
+    /// Checks if a frame is one the tag spans.
+    fn spans(&self, frame_index: usize) -> bool {
+        (self.from.min(self.to)..=self.from.max(self.to)).contains(&frame_index)
+    }
+
+    /// Gets the tag's direction the way Aseprite writes it in sheet JSON.
+    fn direction_name(&self) -> &'static str {
+        match self.direction {
+            AsepriteTagDirection::Forward         => "forward",
+            AsepriteTagDirection::Reverse         => "reverse",
+            AsepriteTagDirection::PingPong        => "pingpong",
+            AsepriteTagDirection::PingPongReverse => "pingpong_reverse",
+        }
+    }
+
+    /// Gets a short label for how many times the tag plays.
+    pub fn repeat_label(&self) -> String {
+        match self.repeat_count {
+            0 => ui_main::TAG_REPEAT_FOREVER.to_owned(),
+            n => format!("x{n}"),
+        }
+    }
+}
+
+/// Where a slice is from a frame onwards, in sprite pixels.
+struct PreparedSliceKey {
+    frame:  usize,
+    bounds: Rectangle,
+
+    /// The middle of a 9-patch slice, relative to the sprite like `bounds`.
+    center: Option<Rectangle>,
+    /// The pivot pixel, relative to the sprite like `bounds`.
+    pivot:  Option<Vector2>,
+}
+
+pub struct PreparedSlice {
+    name:   String,
+    colour: Color,
+
+    /// Sorted by frame, each one holding until the next one's frame.
+    keys: Vec<PreparedSliceKey>,
+}
+
+impl PreparedSlice {
+    fn from_chunk(index: usize, slice: &aseprite::AsepriteSliceChunk) -> Self {
+        let mut keys: Vec<PreparedSliceKey> = slice.keys.iter().map(|k| {
+            let (x, y) = (k.x as f32, k.y as f32);
+
+            PreparedSliceKey {
+                frame:  k.frame as usize,
+                bounds: Rectangle{ x, y, width: k.width as f32, height: k.height as f32 },
+                center: k.center.as_ref().map(|c| Rectangle{
+                    x:      x + c.x as f32,
+                    y:      y + c.y as f32,
+                    width:  c.width as f32,
+                    height: c.height as f32,
+                }),
+                pivot:  k.pivot.map(|(px, py)| Vector2{ x: x + px as f32, y: y + py as f32 }),
+            }
+        }).collect();
+        keys.sort_by_key(|x| x.frame);
+
+        Self {
+            name:   slice.name.as_str().unwrap_or(format!("Slice {index}").as_str()).to_owned(),
+            colour: match slice.user_data.as_ref().and_then(|x| x.colour) {
+                Some([r, g, b, a]) => Color::new(r, g, b, a),
+                None               => SLICE_COLOR,
+            },
+            keys,
+        }
+    }
+
+    /// Gets the key a frame uses, if the slice has started by then.
+    fn key_at(&self, frame_index: usize) -> Option<&PreparedSliceKey> {
+        self.keys.iter().rev().find(|x| x.frame <= frame_index)
+    }
+}
+
+/// How many tiles go across each row of a tileset's atlas, which is how they're
+/// shown in the tileset panel.
+pub(crate) const TILESET_COLUMNS: usize = 8;
+
+pub struct PreparedTileset {
+    pub name:        String,
+    pub tile_count:  usize,
+    pub tile_width:  u16,
+    pub tile_height: u16,
+    /// The number tile 1 is shown as in Aseprite's UI.
+    pub base_index:  i16,
+    /// The tiles are kept in another file, so there aren't any to show.
+    pub external:    bool,
+
+    /// Every tile as RGBA, laid out `TILESET_COLUMNS` across in one image.
+    atlas:         Option<Rc<[u8]>>,
+    /// Only uploaded once the tileset panel is opened, see `upload_tilesets`.
+    texture:       Option<Texture2D>,
+    upload_failed: bool,
+}
+
+impl PreparedTileset {
+    fn from_chunk(index: usize, tileset: &AsepriteTilesetChunk, colour_depth: u16, palette: &[[u8; 4]], transparent_index: u8) -> Self {
+        let atlas = tileset.tiles.as_ref().map(|tiles| {
+            let ids: Vec<u32> = (0..tiles.len() as u32).collect();
+            // none of the bits are flips, so every tile is laid out as it is
+            let masks = AsepriteTileMasks { bits_per_tile: 32, tile_id: u32::MAX, x_flip: 0, y_flip: 0, diagonal_flip: 0 };
+            let laid_out = blit_tilemap(&ids, masks, TILESET_COLUMNS, tiles.len().div_ceil(TILESET_COLUMNS), tileset, &empty_pixel(colour_depth, transparent_index));
+
+            match colour_depth {
+                 8 => indexed_to_rgba(&laid_out, palette, transparent_index),
+                16 => grey_alpha_to_rgba(&laid_out),
+                _  => laid_out,
+            }.into()
+        });
+
+        Self {
+            name:        tileset.name.as_str().unwrap_or(format!("Tileset {index}").as_str()).to_owned(),
+            tile_count:  tileset.tiles.as_ref().map_or(tileset.tile_count as usize, |x| x.len()),
+            tile_width:  tileset.tile_width,
+            tile_height: tileset.tile_height,
+            base_index:  tileset.base_index,
+            external:    tileset.external.is_some() && atlas.is_none(),
+            atlas,
+            texture:       None,
+            upload_failed: false,
+        }
+    }
+
+    /// Gets how many rows of tiles the atlas has.
+    pub fn rows(&self) -> usize {
+        self.tile_count.div_ceil(TILESET_COLUMNS)
+    }
+
+    /// Gets the number a tile is shown as in Aseprite, which the base index
+    /// moves. Tile 0 is always the empty one, so it has no number.
+    pub fn shown_index(&self, index: usize) -> Option<i64> {
+        (index > 0).then(|| index as i64 + self.base_index as i64 - 1)
+    }
+
+    /// Gets the atlas of every tile, if it's been uploaded.
+    pub fn texture(&self) -> Option<&Texture2D> {
+        self.texture.as_ref()
+    }
+}
+
+/// Gets the direction a tag really plays in. A single frame has nothing to turn
+/// around on, so ping-pong tags of one frame play like forward ones.
+fn play_direction(tag: &PreparedTag) -> AsepriteTagDirection {
+    match tag.direction {
+        AsepriteTagDirection::PingPong | AsepriteTagDirection::PingPongReverse if tag.from == tag.to => {
+            AsepriteTagDirection::Forward
+        },
+        direction => direction,
+    }
+}
+
+/// Expands a tag into the frames it plays in order, going through it `repeat`
+/// times. Like Aseprite, each way through a ping-pong tag counts as one repeat
+/// and the frame it turns around on isn't played twice.
+/// 
+/// A `repeat` of 0 gives a single loop that can be played over and over, which
+/// for ping-pong tags stops just before the frame it started on.
+pub(crate) fn tag_frame_sequence(tag: &PreparedTag, repeat: u16) -> Vec<usize> {
+    let (lo, hi) = (tag.from.min(tag.to), tag.from.max(tag.to));
+    let forward: Vec<usize> = (lo..=hi).collect();
+    let reverse: Vec<usize> = (lo..=hi).rev().collect();
+
+    let (first, second) = match play_direction(tag) {
+        AsepriteTagDirection::Forward         => return forward.repeat(repeat.max(1) as usize),
+        AsepriteTagDirection::Reverse         => return reverse.repeat(repeat.max(1) as usize),
+        AsepriteTagDirection::PingPong        => (forward, reverse),
+        AsepriteTagDirection::PingPongReverse => (reverse, forward),
+    };
+
+    if repeat == 0 {
+        let turn_back = second.get(1..second.len() - 1).unwrap_or_default();
+        return first.iter().chain(turn_back).copied().collect();
+    }
+
+    let mut result = first.clone();
+    for pass in 1..repeat {
+        let way = if pass % 2 == 0 { &first } else { &second };
+        result.extend_from_slice(&way[1..]);
+    }
+
+    result
+}
+
+/// Gets where in `tag_frame_sequence(tag, repeat)` each of the `repeat` loops
+/// starts, following the same rules for ping-pong tags.
+pub(crate) fn tag_loop_starts(tag: &PreparedTag, repeat: u16) -> Vec<usize> {
+    let len = tag.from.abs_diff(tag.to) + 1;
+
+    match play_direction(tag) {
+        AsepriteTagDirection::Forward | AsepriteTagDirection::Reverse => {
+            (0..repeat as usize).map(|x| x * len).collect()
+        },
+        // every way through after the first skips the frame it turns around on
+        AsepriteTagDirection::PingPong | AsepriteTagDirection::PingPongReverse => {
+            (0..repeat as usize).map(|x| if x == 0 { 0 } else { x * (len - 1) + 1 }).collect()
+        },
+    }
+}
+
+/// Gets the nearest of `starts`, which are in order, after `frame`, or before it
+/// if `forward` is false.
+fn next_boundary(starts: &[usize], frame: usize, forward: bool) -> Option<usize> {
+    match forward {
+        true  => starts.iter().copied().find(|x| *x > frame),
+        false => starts.iter().copied().rev().find(|x| *x < frame),
+    }
+}
+
+/// How big a sprite is and how much is in it, kept from before a reload to say
+/// what the reload changed.
+#[derive(Clone, Copy)]
+pub(crate) struct SpriteSummary {
+    pub frames: usize,
+    pub layers: usize,
+    pub tags:   usize,
+    pub width:  u16,
+    pub height: u16,
+}
+
+impl SpriteSummary {
+    /// Says what's different about the sprite since `old`, like
+    /// "+1 layer, frames 12→16", or that nothing is.
+    pub fn changes_from(&self, old: &Self) -> String {
+        let counted = |noun: &str, old: usize, new: usize| {
+            let change = new as i64 - old as i64;
+            (change != 0).then(|| format!("{change:+} {noun}{}", if change.abs() == 1 { "" } else { "s" }))
+        };
+
+        let changes: Vec<String> = [
+            counted("layer", old.layers, self.layers),
+            counted("tag", old.tags, self.tags),
+            (old.frames != self.frames).then(|| format!("frames {}→{}", old.frames, self.frames)),
+            (old.width != self.width || old.height != self.height)
+                .then(|| format!("canvas {}x{}→{}x{}", old.width, old.height, self.width, self.height)),
+        ].into_iter().flatten().collect();
+
+        match changes.is_empty() {
+            true  => "nothing changed".to_owned(),
+            false => changes.join(", "),
+        }
+    }
+}
+
+/// A single pixel of the sprite, as found on the grid.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct SpritePixel {
+    pub frame_index: usize,
+    pub layer_index: usize,
+    /// Position in the canvas, in sprite pixels rather than screen pixels.
+    pub x:           u16,
+    pub y:           u16,
+}
+
+/// View settings that change how `LoadedSprite::draw` renders the grid. These
+/// stay the same between loaded sprites.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct DrawOptions {
+    /// Hides linked cels so only the cels with their own image stand out.
+    pub keyframes_only: bool,
+    /// Draws lines between sprite pixels once zoomed in far enough.
+    pub pixel_grid:     bool,
+    /// Draws every slice over each cel of the frames it's on.
+    pub slices:         bool,
+}
+
+/// How frames are laid out in an exported sprite sheet.
+#[derive(Clone, Copy)]
+pub(crate) enum SheetLayout {
+    /// Every frame in one row.
+    Horizontal,
+    /// Every frame in one column.
+    Vertical,
+    /// Frames go left to right in rows this many frames wide.
+    Grid(usize),
+}
+
+impl SheetLayout {
+    /// Picks a grid about as wide as it is tall.
+    pub fn square(frame_count: usize) -> Self {
+        SheetLayout::Grid((frame_count as f64).sqrt().ceil() as usize)
+    }
+
+    /// Gets how many frames go in each row of the sheet.
+    fn columns(self, frame_count: usize) -> usize {
+        let columns = match self {
+            SheetLayout::Horizontal    => frame_count,
+            SheetLayout::Vertical      => 1,
+            SheetLayout::Grid(columns) => columns,
+        };
+
+        columns.clamp(1, frame_count.max(1))
+    }
+}
+
+/// A cel's pixels along with how they're laid over a frame, which doesn't need
+/// the sprite and so can be sent off to another thread.
+#[derive(Clone)]
+struct StackedCel {
+    pixels:     Arc<[u8]>,
+    x:          i32,
+    y:          i32,
+    width:      i32,
+    height:     i32,
+    blend_mode: AsepriteBlendMode,
+    opacity:    u8,
+}
+
+/// Lays a cel's pixels over an RGBA canvas `width` by `height` pixels big, at
+/// the cel's position.
+fn blend_cel_onto(canvas: &mut [u8], width: i32, height: i32, cel: &StackedCel) {
+    // only the part of the cel over the canvas is gone through, which clips
+    // cels hanging off any of its edges
+    for y in cel.y.max(0)..(cel.y + cel.height).min(height) {
+        for x in cel.x.max(0)..(cel.x + cel.width).min(width) {
+            let src_at = (((y - cel.y) * cel.width + (x - cel.x)) * 4) as usize;
+            let Some(src) = cel.pixels.get(src_at..(4 + src_at)) else { continue };
+
+            let at = ((y * width + x) * 4) as usize;
+            let Some(dst) = canvas.get_mut(at..(4 + at)) else { continue };
+
+            let blended = blend_pixel(cel.blend_mode, [dst[0], dst[1], dst[2], dst[3]], [src[0], src[1], src[2], src[3]], cel.opacity);
+            dst.copy_from_slice(&blended);
+        }
+    }
+}
+
+/// The cels of every frame of a sprite in the order they're drawn, enough to
+/// flatten the frames without the sprite itself.
+struct FrameStacks {
+    width:  u16,
+    height: u16,
+    frames: Vec<Vec<StackedCel>>,
+}
+
+impl FrameStacks {
+    /// Flattens a frame into RGBA bytes the size of the sprite. Frames the
+    /// sprite doesn't have come out transparent.
+    fn flatten(&self, frame_index: usize) -> Vec<u8> {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let mut canvas = vec![0u8; self.width as usize * self.height as usize * 4];
+
+        for cel in self.frames.get(frame_index).into_iter().flatten() {
+            blend_cel_onto(&mut canvas, width, height, cel);
+        }
+
+        canvas
+    }
+}
+
+/// A sprite sheet waiting to be flattened, packed and saved. It doesn't need
+/// the sprite any more, so it can be done on another thread.
+pub(crate) struct SheetExport {
+    path:    PathBuf,
+    stacks:  FrameStacks,
+    columns: usize,
+    json:    String,
+}
+
+impl SheetExport {
+    /// Flattens and packs every frame into one PNG and writes it out with its
+    /// JSON, calling `progress` with how many steps are done out of how many.
+    pub fn save(self, mut progress: impl FnMut(usize, usize)) -> io::Result<()> {
+        let frame_count = self.stacks.frames.len();
+        // one more for encoding the PNG at the end
+        let steps = frame_count + 1;
+
+        let rows = frame_count.div_ceil(self.columns);
+        let (frame_w, frame_h) = (self.stacks.width as usize, self.stacks.height as usize);
+        let (sheet_w, sheet_h) = (frame_w * self.columns, frame_h * rows);
+        let mut sheet = vec![0u8; sheet_w * sheet_h * 4];
+
+        for frame in 0..frame_count {
+            let (x, y) = (frame % self.columns * frame_w, frame / self.columns * frame_h);
+            let flat = self.stacks.flatten(frame);
+
+            for row in 0..frame_h {
+                let from = row * frame_w * 4;
+                let to = ((y + row) * sheet_w + x) * 4;
+                sheet[to..(to + frame_w * 4)].copy_from_slice(&flat[from..(from + frame_w * 4)]);
+            }
+
+            progress(frame + 1, steps);
+        }
+
+        let png = image_from_rgba(sheet_w as i32, sheet_h as i32, &sheet)
+            .export_image_to_memory(".png")
+            .map_err(|e| io::Error::other(e.to_string()))?
+            .to_vec();
+
+        fs::write(&self.path, png)?;
+        fs::write(self.path.with_extension("json"), self.json)?;
+
+        progress(steps, steps);
+        Ok(())
+    }
+}
+
+/// An animated GIF waiting to be flattened, encoded and saved. It doesn't need
+/// the sprite any more, so it can be done on another thread.
+pub(crate) struct GifExport {
+    path:      PathBuf,
+    stacks:    FrameStacks,
+    /// The frames played in order, each with how long it lasts.
+    sequence:  Vec<(usize, u16)>,
+    looping:   bool,
+    /// The sprite's own palette, for indexed sprites. Anything else gets one
+    /// picked by median cut once the frames are flattened.
+    palette:   Option<GifPalette>,
+}
+
+impl GifExport {
+    /// Flattens and encodes the frames and writes out the GIF, calling `progress`
+    /// with how many steps are done out of how many.
+    pub fn save(self, mut progress: impl FnMut(usize, usize)) -> io::Result<()> {
+        // frames come up more than once in ping-pong tags, so each is only
+        // flattened the once
+        let mut unique: Vec<usize> = self.sequence.iter().map(|(x, _)| *x).collect();
+        unique.sort_unstable();
+        unique.dedup();
+
+        let steps = unique.len() + self.sequence.len();
+
+        let mut flattened: HashMap<usize, Vec<u8>> = HashMap::new();
+        for (i, frame) in unique.iter().enumerate() {
+            flattened.insert(*frame, self.stacks.flatten(*frame));
+            progress(i + 1, steps);
+        }
+
+        let mut palette = match self.palette {
+            Some(palette) => palette,
+            None => GifPalette::median_cut(unique.iter().map(|x| flattened[x].as_slice())),
+        };
+
+        let frames: Vec<(&[u8], u16)> = self.sequence.iter()
+            .map(|(frame, duration)| (flattened[frame].as_slice(), *duration))
+            .collect();
+
+        let (width, height) = (self.stacks.width, self.stacks.height);
+        let gif = gif::encode(width, height, &frames, &mut palette, self.looping, |done| progress(unique.len() + done, steps));
+
+        fs::write(&self.path, gif)
+    }
 }
 
 pub(crate) struct LoadedSprite {
+    /// The path the sprite was loaded from.
+    pub file_path: String,
+
     pub loaded_cels:   Vec<PreparedCel>,
     pub loaded_layers: Vec<PreparedLayer>,
     pub loaded_tags:   Vec<PreparedTag>,
+    pub loaded_slices: Vec<PreparedSlice>,
+    pub loaded_tilesets: Vec<PreparedTileset>,
     pub frame_count:   usize,
+    /// How long each frame is shown for in milliseconds.
+    pub frame_durations: Vec<u16>,
+
+    /// How many cels couldn't be uploaded to the GPU so far, like ones bigger
+    /// than the largest texture it supports. They're left out of the grid.
+    pub failed_uploads: usize,
+    /// Anything off about the file that didn't stop it being loaded, and any
+    /// textures that couldn't be uploaded since.
+    pub warnings:       Vec<String>,
+
+    /// Maps the layer index cels refer to onto the position of that layer in
+    /// `loaded_layers`.
+    layer_lookup: HashMap<u16, usize>,
+
+    /// The only layer shown, along with anything inside it, without touching
+    /// any layer's own visibility.
+    soloed_layer: Option<usize>,
 
     offset: Vector2,
 
@@ -86,122 +801,336 @@ pub(crate) struct LoadedSprite {
     pub pixel_width:  u8,
     pub pixel_height: u8,
 
-    cached_list: Option<String>//Option<Box<CString>>
+    /// Bits per pixel, where 8 is indexed, 16 is greyscale and 32 is RGBA.
+    pub colour_depth: u16,
+    /// The sprite's palette, empty for plain images.
+    pub palette:      Vec<[u8; 4]>,
+    /// What each palette entry is called, for the ones given a name.
+    pub palette_names: Vec<Option<String>>,
+    /// The palette index that's fully transparent in indexed sprites.
+    pub transparent_index: u8,
+
+    cached_list: Option<String>,//Option<Box<CString>>
+    /// Which layer each row of `cached_list` is.
+    cached_list_rows: Vec<usize>,
+    /// Only layers with this in their full name go in the layer list.
+    layer_filter: String,
+    cached_palette_labels: Option<Vec<String>>,
+    /// Whether each layer ends up visible, see `is_layer_visible`. Cleared along
+    /// with the layer list.
+    cached_visibility: Option<Vec<bool>>,
 }
 
 impl LoadedSprite {
-    /// Gets a layer's visibilty depending on its parents. Care should be taken
-    /// as it is *recursive* with a depth limit specified by `RECURSIVE_LIMIT`.
+    /// Walks from a layer up through its parents, calling `visit` on each layer
+    /// (starting with the layer itself) until it returns `false` or a layer
+    /// without a parent is reached.
     /// 
-    /// If any layer in the process is not visible, the recursion stops early.
+    /// Returns `false` if the walk runs into a cycle or an out of bounds parent,
+    /// which can only come from a malformed `parent_index`.
+    fn walk_layer_parents<'a>(&'a self, layer_index: usize, mut visit: impl FnMut(&'a PreparedLayer) -> bool) -> bool {
+        let mut current = layer_index;
+
+        // every step has to land on a different layer unless there's a cycle
+        for _ in 0..self.loaded_layers.len() {
+            let Some(layer) = self.loaded_layers.get(current) else { return false };
+
+            if !visit(layer) || layer.parent_index == NO_PARENT {
+                return true;
+            }
+
+            current = layer.parent_index;
+        }
+
+        false
+    }
+
+    /// Gets a layer's visibilty depending on its parents, from the cache if
+    /// `cache_visibility` has filled it since the layers last changed.
     pub(crate) fn is_layer_visible(&self, layer_index: usize) -> bool {
-        self.internal_layer_visible(layer_index, RECURSIVE_LIMIT)
+        match &self.cached_visibility {
+            Some(cache) => cache.get(layer_index).copied().unwrap_or(false),
+            None        => self.walk_layer_visibility(layer_index),
+        }
     }
 
-    fn internal_layer_visible(&self, layer_index: usize, deepness: u8) -> bool {
-        let layer = &self.loaded_layers[layer_index];
-        if layer.visible && layer.parent_index != NO_PARENT && deepness > 0 {
-            layer.visible && self.internal_layer_visible(layer.parent_index, deepness.min(RECURSIVE_LIMIT) - 1)
-        } else { layer.visible } // should be false unless it's really deep
+    /// Works out every layer's visibility once, so drawing doesn't walk up the
+    /// same parents for every cel on every frame.
+    fn cache_visibility(&mut self) {
+        if self.cached_visibility.is_none() {
+            self.cached_visibility = Some((0..self.loaded_layers.len()).map(|x| self.walk_layer_visibility(x)).collect());
+        }
     }
 
-    /// Gets full name of a layer. Should **NOT** be repeatedly called as it is:
-    /// - *Recursive* (to a maximum depth specified by `RECURSIVE_LIMIT`)
-    /// - *Clones* all `String`s from a layer and its parent's, and so on
+    /// Works out a layer's visibilty by walking up its parents.
     /// 
-    /// Internally this is called with `LoadedSprite::load()` for layers and is
+    /// If any layer in the process is not visible, the walk stops early. While a
+    /// layer is soloed only it and the layers inside it can be visible, and the
+    /// walk stops at it without looking at its own flag or its parents.
+    fn walk_layer_visibility(&self, layer_index: usize) -> bool {
+        let solo = self.soloed_layer.and_then(|x| self.loaded_layers.get(x));
+        let mut inside_solo = solo.is_none();
+
+        let mut visible = true;
+        self.walk_layer_parents(layer_index, |layer| {
+            if solo.is_some_and(|x| std::ptr::eq(x, layer)) {
+                inside_solo = true;
+                return false;
+            }
+
+            visible = layer.visible;
+            visible
+        });
+        visible && inside_solo
+    }
+
+    /// Gets the layer being soloed, if any.
+    pub(crate) fn soloed_layer(&self) -> Option<usize> {
+        self.soloed_layer
+    }
+
+    /// Solos a layer so only it shows, or stops soloing it if it already was.
+    pub(crate) fn toggle_solo(&mut self, layer_index: usize) {
+        self.soloed_layer = match self.soloed_layer {
+            Some(x) if x == layer_index => None,
+            _ => Some(layer_index),
+        };
+
+        self.invalidate_layer_list();
+    }
+
+    /// Gets full name of a layer. Should **NOT** be repeatedly called as it
+    /// *clones* all `String`s from a layer and its parent's, and so on.
+    /// 
+    /// Internally this is called with `LoadedSprite::read()` for layers and is
     /// stored as its `full_name` within an option, and using the layer's
     /// `full_name` should be used instead of calling this.
     pub(crate) fn layer_name(&self, layer_index: usize) -> String {
-        self.internal_layer_name(layer_index, RECURSIVE_LIMIT)
+        let mut names: Vec<&str> = vec![];
+        self.walk_layer_parents(layer_index, |layer| {
+            names.push(&layer.name);
+            true
+        });
+        names.join(".")
     }
 
-    fn internal_layer_name(&self, layer_index: usize, deepness: u8) -> String {
-        let layer = &self.loaded_layers[layer_index];
-        let mut result = layer.name.clone();
-        if layer.parent_index != NO_PARENT && deepness > 0 {
-            result = format!("{}.{}", result, self.internal_layer_name(layer.parent_index, deepness.min(RECURSIVE_LIMIT) - 1))
-        }
-        result
+    /// Gets the position in `loaded_layers`, and so the grid row, of the layer a
+    /// cel refers to, if a layer with that index was ever loaded.
+    pub(crate) fn layer_of(&self, layer_index: u16) -> Option<usize> {
+        self.layer_lookup.get(&layer_index).copied()
     }
 
-    pub fn load(fname: &str, rl: &mut RaylibHandle, thread: &RaylibThread) -> Result<Self, AsepriteError> {
-        let mut f_in = match File::open(fname) {
-            Ok(f) => f,
-            Err(e) => return Err(AsepriteError::Other(Box::new(e))),
-        };
+    /// Checks if any layer's parent chain loops back on itself.
+    pub(crate) fn has_layer_cycle(&self) -> bool {
+        (0..self.loaded_layers.len()).any(|i| !self.walk_layer_parents(i, |_| true))
+    }
+
+    /// Reads an Aseprite file into everything needed to composite and export
+    /// its frames, without touching the GPU. Cels are only uploaded once they
+    /// come on screen, see `stream_textures`.
+    pub fn read(fname: &str) -> Result<Self, AsepriteError> {
+        let mut f_in = File::open(fname)?;
     
-        let mut main_data: Aseprite = aseprite::read(&mut f_in)?;
+        let main_data: Aseprite = aseprite::read(&mut f_in)?;
+        Ok(Self::from_aseprite(fname, main_data))
+    }
+
+    /// Gets everything needed to composite and export the frames of an Aseprite
+    /// file that's already been read, with `fname` kept as where it came from.
+    pub(crate) fn from_aseprite(fname: &str, mut main_data: Aseprite) -> Self {
 
         let mut loaded_cels = vec![];
         let mut loaded_layers = vec![];
         let mut loaded_tags = vec![];
+        let mut layer_lookup = HashMap::new();
+        let mut warnings = std::mem::take(&mut main_data.warnings);
+
+        let palette = main_data.palette();
 
         let (image_width, image_height) = (main_data.header.width, main_data.header.height);
         let (pixel_width, pixel_height) = (main_data.header.pixel_width.max(1), main_data.header.pixel_height.max(1));
         
+        // done as floats so a huge sprite with wide pixels can't overflow
         let offset = Vector2{
-            x: (image_width * pixel_width as u16 + GAP) as f32,
-            y: (image_height * pixel_height as u16 + GAP) as f32
+            x: image_width as f32 * pixel_width as f32 + GAP as f32,
+            y: image_height as f32 * pixel_height as f32 + GAP as f32
         };
 
-        for (frame_idx, frame) in main_data.frames.iter_mut().enumerate() {
-            for (chunk_idx, chunk) in frame.chunks.iter_mut().enumerate() {
+        let tilesets: HashMap<u32, &AsepriteTilesetChunk> = main_data.tilesets().into_iter().map(|x| (x.id, x)).collect();
+
+        // cels name their layer by the order layer chunks show up in the file, and
+        // nothing says those all come before the cels or even in the first frame,
+        // so every layer is found before any cel goes looking for one
+        let layer_chunks = main_data.frames.iter().enumerate()
+            .flat_map(|(frame_idx, frame)| frame.chunks.iter().enumerate().map(move |(chunk_idx, chunk)| (frame_idx, chunk_idx, chunk)))
+            .filter_map(|(frame_idx, chunk_idx, chunk)| match chunk {
+                aseprite::Chunk::Layer(lchunk) => Some((frame_idx, chunk_idx, lchunk)),
+                _ => None
+            });
+
+        for (file_order, (frame_idx, chunk_idx, lchunk)) in layer_chunks.enumerate() {
+            let Ok(file_order) = u16::try_from(file_order) else {
+                warnings.push(format!("more than {} layers, the rest are left out", u16::MAX as usize + 1));
+                break;
+            };
+
+            layer_lookup.insert(file_order, loaded_layers.len());
+            loaded_layers.push(PreparedLayer {
+                child_level:  lchunk.child_level,
+                blend_mode:   lchunk.blend_mode,
+                opacity:      lchunk.opacity,
+                layer_type:   lchunk.layer_type,
+                tileset_index: lchunk.tileset_index,
+                visible:      lchunk.flags & AsepriteLayerFlags::Visible > 0,
+                background:   lchunk.flags & AsepriteLayerFlags::Background > 0,
+                is_reference: lchunk.flags & AsepriteLayerFlags::IsReference > 0,
+                collapsed:    lchunk.flags & AsepriteLayerFlags::IsCollapsed > 0,
+                name:         lchunk.name.as_str().ok().map(|x| x.to_owned()).unwrap_or(format!("frame{frame_idx} chunk{chunk_idx}")),
+                full_name:    None,
+                note:         lchunk.user_data.as_ref()
+                                  .and_then(|x| x.text.as_ref())
+                                  .and_then(|x| x.as_str().ok())
+                                  .map(|x| x.to_owned()),
+
+                parent_index: NO_PARENT,
+            });
+        }
+
+        for (frame_idx, frame) in main_data.frames.iter().enumerate() {
+            for chunk in frame.chunks.iter() {
                 match chunk {
-                    aseprite::Chunk::Layer(lchunk) => {
-                        loaded_layers.push(PreparedLayer {
-                            child_level:  lchunk.child_level,
-                            blend_mode:   lchunk.blend_mode,
-                            opacity:      lchunk.opacity,
-                            layer_type:   lchunk.layer_type,
-                            visible:      lchunk.flags & AsepriteLayerFlags::Visible > 0,
-                            background:   lchunk.flags & AsepriteLayerFlags::Background > 0,
-                            is_reference: lchunk.flags & AsepriteLayerFlags::IsReference > 0,
-                            name:         lchunk.name.as_str().ok().map(|x| x.to_owned()).unwrap_or(format!("frame{frame_idx} chunk{chunk_idx}")),
-                            full_name:    None,
-
-                            parent_index: NO_PARENT,
-                        });
-                    },
                     aseprite::Chunk::Cel(cel) => {
+                        let Some(row) = layer_lookup.get(&cel.layer_index).copied() else {
+                            warnings.push(format!(
+                                "cel on frame {frame_idx} is on layer {}, which isn't in the file, left out",
+                                cel.layer_index
+                            ));
+                            continue;
+                        };
+
                         match cel.cel_type {
-                            aseprite::AsepriteCelType::Raw | aseprite::AsepriteCelType::CompressedImage => {
-                                if let Some(img_data) = &mut cel.raw_data {
-                                    let mut img = raylib::texture::Image::gen_image_color(
-                                        cel.width.unwrap_or(1).into(), cel.height.unwrap_or(1).into(),
-                                        ERR_COLOR
-                                    );
-                                    
-                                    img.set_format(match &main_data.header.colour_depth {
-                                        32 => raylib::consts::PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8,
-                                        16 => raylib::consts::PixelFormat::PIXELFORMAT_UNCOMPRESSED_GRAY_ALPHA,
-                                        _ => panic!("unsupported colour depth")
-                                    });
+                            aseprite::AsepriteCelType::Raw | aseprite::AsepriteCelType::CompressedImage | aseprite::AsepriteCelType::CompressedTilemap => {
+                                // tilemaps are laid out into an image in the sprite's own pixel
+                                // format first, so they go through everything any other cel does
+                                let image = match cel.cel_type {
+                                    aseprite::AsepriteCelType::CompressedTilemap => {
+                                        let tileset = loaded_layers.get(row)
+                                            .and_then(|x| x.tileset_index)
+                                            .and_then(|x| tilesets.get(&x))
+                                            .filter(|x| x.tiles.is_some());
+
+                                        match (tileset, &cel.tiles, cel.tile_masks) {
+                                            (Some(tileset), Some(tiles), Some(masks)) => {
+                                                let (columns, rows) = (cel.width.unwrap_or(0), cel.height.unwrap_or(0));
+                                                let empty = empty_pixel(main_data.header.colour_depth, main_data.header.palette_entry);
+
+                                                Some((
+                                                    blit_tilemap(tiles, masks, columns as usize, rows as usize, tileset, &empty).into(),
+                                                    columns.saturating_mul(tileset.tile_width),
+                                                    rows.saturating_mul(tileset.tile_height),
+                                                ))
+                                            },
+                                            _ => None,
+                                        }
+                                    },
+                                    _ => cel.raw_data.clone().map(|x| (x, cel.width.unwrap_or(0), cel.height.unwrap_or(0))),
+                                };
+
+                                if let Some((img_data, width, height)) = image {
+                                    // the gpu has no idea about palettes, so indexed cels go up as rgba
+                                    let mut pixels = match main_data.header.colour_depth {
+                                        8 => indexed_to_rgba(&img_data, &palette, main_data.header.palette_entry).into(),
+                                        _ => img_data.clone(),
+                                    };
+
+                                    // raylib reads exactly width * height pixels out of whatever it's given,
+                                    // and the reader turns away any colour depth other than 8, 16 or 32
+                                    let bytes_per_pixel = if main_data.header.colour_depth == 16 { 2 } else { 4 };
+                                    let expected_len = width as usize * height as usize * bytes_per_pixel;
+
+                                    if pixels.len() != expected_len {
+                                        warnings.push(format!(
+                                            "cel on frame {frame_idx} layer {} has {} bytes of pixels instead of {expected_len}, padded or cut to fit",
+                                            cel.layer_index, pixels.len()
+                                        ));
+
+                                        let mut fitted = pixels.to_vec();
+                                        fitted.resize(expected_len, 0);
+                                        pixels = fitted.into();
+                                    }
         
-                                    let mut txtr = rl.load_texture_from_image(thread, &img).unwrap();
-                                    let _ = txtr.update_texture(img_data);
+                                    // reference layers can be scaled and moved by less than a pixel
+                                    let precise = cel.extra.as_ref().and_then(|x| x.precise_bounds());
         
                                     loaded_cels.push(PreparedCel{
                                         // image:           Some(img),
                                         layer_index:     cel.layer_index,
                                         frame_index:     frame_idx,
-                                        texture:         Some(txtr),
+                                        texture:         None,
+                                        last_seen:       f64::NEG_INFINITY,
+                                        upload_failed:   false,
+                                        pixels:          Some(match main_data.header.colour_depth {
+                                            16 => grey_alpha_to_rgba(&pixels).into(),
+                                            _  => Arc::from(&*pixels),
+                                        }),
+                                        indices:         (main_data.header.colour_depth == 8).then_some(img_data),
                                         linked_to_frame: None,
-                                        position:        Vector2 { x: cel.x_pos as f32, y: cel.y_pos as f32 },
-                                        size:            Vector2 { x: cel.width.unwrap_or(0) as f32, y: cel.height.unwrap_or(0) as f32 },
+                                        position:        match precise {
+                                            Some((x, y, _, _)) => Vector2 { x, y },
+                                            None => Vector2 { x: cel.x_pos as f32, y: cel.y_pos as f32 },
+                                        },
+                                        size:            Vector2 { x: width as f32, y: height as f32 },
                                         opacity:         cel.opacity,
+                                        z_index:         cel.z_index,
                                         content_bounds:          Rectangle {
                                             x:      frame_idx as f32 + cel.x_pos as f32,
-                                            y:      -(cel.layer_index as f32 - cel.y_pos as f32),
-                                            width:  cel.width.unwrap_or(0) as f32,
-                                            height: cel.height.unwrap_or(0) as f32
+                                            y:      -(row as f32 - cel.y_pos as f32),
+                                            width:  width as f32,
+                                            height: height as f32
                                         },
                                         collision_bounds:       Rectangle{
                                             x:      frame_idx as f32 * offset.x,
-                                            y:      -(cel.layer_index as f32 * offset.y),
+                                            y:      -(row as f32 * offset.y),
                                             width:  image_width as f32 * pixel_width as f32,
                                             height: image_height as f32 * pixel_height as f32,
                                         },
+                                        scaled_size: precise.map(|(_, _, width, height)| Vector2 { x: width, y: height }),
+                                        tile_grid: None,
+                                        hover: false
+                                    });
+                                } else if matches!(cel.cel_type, aseprite::AsepriteCelType::CompressedTilemap) {
+                                    warnings.push(format!(
+                                        "tilemap cel on frame {frame_idx} layer {} has no tileset in this file to draw its tiles with, drawn as a grid instead",
+                                        cel.layer_index
+                                    ));
+
+                                    loaded_cels.push(PreparedCel{
+                                        layer_index:     cel.layer_index,
+                                        frame_index:     frame_idx,
+                                        texture:         None,
+                                        last_seen:       f64::NEG_INFINITY,
+                                        upload_failed:   false,
+                                        pixels:          None,
+                                        indices:         None,
+                                        linked_to_frame: None,
+                                        position:        Vector2 { x: cel.x_pos as f32, y: cel.y_pos as f32 },
+                                        size:            Vector2 { x: image_width as f32, y: image_height as f32 },
+                                        opacity:         cel.opacity,
+                                        z_index:         cel.z_index,
+                                        content_bounds:          Rectangle {
+                                            x:      frame_idx as f32,
+                                            y:      -(row as f32),
+                                            width:  image_width as f32,
+                                            height: image_height as f32
+                                        },
+                                        collision_bounds:       Rectangle{
+                                            x:      frame_idx as f32 * offset.x,
+                                            y:      -(row as f32 * offset.y),
+                                            width:  image_width as f32 * pixel_width as f32,
+                                            height: image_height as f32 * pixel_height as f32,
+                                        },
+                                        scaled_size: None,
+                                        tile_grid: Some((cel.width.unwrap_or(0), cel.height.unwrap_or(0))),
                                         hover: false
                                     });
                                 }
@@ -212,26 +1141,32 @@ impl LoadedSprite {
                                     layer_index:     cel.layer_index,
                                     frame_index:     frame_idx,
                                     texture:         None,
+                                    last_seen:       f64::NEG_INFINITY,
+                                    upload_failed:   false,
+                                    pixels:          None,
+                                    indices:         None,
                                     linked_to_frame: cel.linked_to,
                                     position:        Vector2 { x: 0.0, y: 0.0 },
                                     size:            Vector2 { x: image_width as f32, y: image_height as f32 },
                                     opacity:         255,
+                                    z_index:         cel.z_index,
                                     content_bounds:          Rectangle {
                                         x:      frame_idx as f32,
-                                        y:      -(cel.layer_index as f32),
+                                        y:      -(row as f32),
                                         width:  image_width as f32,
                                         height: image_height as f32
                                     },
                                     collision_bounds:       Rectangle{
                                         x:      frame_idx as f32 * offset.x,
-                                        y:      -(cel.layer_index as f32 * offset.y),
+                                        y:      -(row as f32 * offset.y),
                                         width:  image_width as f32 * pixel_width as f32,
                                         height: image_height as f32 * pixel_height as f32,
                                     },
+                                    scaled_size: None,
+                                    tile_grid: None,
                                     hover: false
                                 });
                             },
-                            _ => unimplemented!(),
                         };
                     },
                     aseprite::Chunk::Tag(tag) => {
@@ -241,6 +1176,12 @@ impl LoadedSprite {
                                 to:        tag.to.into(),
                                 direction: tag.direction,
                                 name:      tag.name.as_str().unwrap_or(format!("Tag {i}").as_str()).to_owned(),
+                                colour:    {
+                                    let [r, g, b, a] = tag.display_colour();
+                                    Color::new(r, g, b, a)
+                                },
+
+                                repeat_count: tag.repeat_count,
                             });
                         }
                     }
@@ -253,38 +1194,481 @@ impl LoadedSprite {
             let mut parent_map: HashMap<i32, usize> = HashMap::<i32, usize>::new();
             parent_map.insert(-1, NO_PARENT);
 
-            for (layer_idx, layer) in loaded_layers.iter_mut().enumerate() {
-                parent_map.insert(layer.child_level as i32, layer_idx);
+            for (layer_idx, layer) in loaded_layers.iter_mut().enumerate() {
+                parent_map.insert(layer.child_level as i32, layer_idx);
+
+                layer.parent_index = *parent_map.get(&((layer.child_level as i32) - 1)).unwrap_or(&NO_PARENT);
+            }
+        }
+
+        // cels draw in layer order moved by their z-index, with the z-index
+        // itself breaking ties the way Aseprite does it
+        loaded_cels.sort_by_key(|x| {
+            let row = layer_lookup.get(&x.layer_index).copied().unwrap_or(0);
+            (x.frame_index, row as i32 + x.z_index as i32, x.z_index)
+        });
+
+        let loaded_slices = main_data.slices().into_iter().enumerate().map(|(i, x)| PreparedSlice::from_chunk(i, x)).collect();
+        let loaded_tilesets = main_data.tilesets().into_iter().enumerate()
+            .map(|(i, x)| PreparedTileset::from_chunk(i, x, main_data.header.colour_depth, &palette, main_data.header.palette_entry))
+            .collect();
+
+        let frame_count = main_data.frames.len();
+        let frame_durations = main_data.frames.iter().map(|x| x.frame_duration).collect();
+        let mut r = Self {
+            file_path: fname.to_owned(),
+            loaded_cels, loaded_layers, loaded_tags, loaded_slices, loaded_tilesets, frame_count, frame_durations, layer_lookup, offset, warnings,
+            failed_uploads: 0,
+            soloed_layer: None,
+            image_width, image_height, pixel_width, pixel_height,
+            colour_depth: main_data.header.colour_depth,
+            palette,
+            palette_names: main_data.palette_names(),
+            transparent_index: main_data.header.palette_entry,
+
+            cached_list: None,
+            cached_list_rows: vec![],
+            layer_filter: String::new(),
+            cached_palette_labels: None,
+            cached_visibility: None,
+        };
+
+        for layer_index in 0..r.loaded_layers.len() {
+            r.loaded_layers[layer_index].full_name = Some(r.layer_name(layer_index))
+        }
+
+        r
+    }
+
+    /// Reads a plain image (anything raylib can read) as a sprite with a single
+    /// frame and a single layer named after the file, without touching the GPU.
+    pub fn read_image(fname: &str) -> Result<Self, AsepriteError> {
+        let img = match Image::load_image(fname) {
+            Ok(i) => i,
+            Err(e) => return Err(AsepriteError::Other(Box::new(e))),
+        };
+
+        let (image_width, image_height) = match (u16::try_from(img.width()), u16::try_from(img.height())) {
+            (Ok(w), Ok(h)) => (w, h),
+            _ => return Err(AsepriteError::Other(format!(
+                "{}x{} is too big to open as a sprite, the most is {}x{}",
+                img.width(), img.height(), u16::MAX, u16::MAX
+            ).into()))
+        };
+
+        let offset = Vector2{
+            x: image_width as f32 + GAP as f32,
+            y: image_height as f32 + GAP as f32
+        };
+
+        let name = std::path::Path::new(fname).file_stem()
+            .and_then(|x| x.to_str())
+            .unwrap_or("image")
+            .to_owned();
+
+        let loaded_cels = vec![PreparedCel{
+            layer_index:     0,
+            frame_index:     0,
+            texture:         None,
+            last_seen:       f64::NEG_INFINITY,
+            upload_failed:   false,
+            pixels:          Some(img.get_image_data().iter().flat_map(|x| [x.r, x.g, x.b, x.a]).collect()),
+            indices:         None,
+            linked_to_frame: None,
+            position:        Vector2 { x: 0.0, y: 0.0 },
+            size:            Vector2 { x: image_width as f32, y: image_height as f32 },
+            opacity:         255,
+            z_index:         0,
+            content_bounds:          Rectangle {
+                x:      0.0,
+                y:      0.0,
+                width:  image_width as f32,
+                height: image_height as f32
+            },
+            collision_bounds:       Rectangle{
+                x:      0.0,
+                y:      0.0,
+                width:  image_width as f32,
+                height: image_height as f32,
+            },
+            scaled_size: None,
+            tile_grid: None,
+            hover: false
+        }];
+
+        let loaded_layers = vec![PreparedLayer {
+            child_level:  0,
+            blend_mode:   AsepriteBlendMode::Normal,
+            opacity:      255,
+            layer_type:   AsepriteLayerType::Normal,
+            tileset_index: None,
+            visible:      true,
+            background:   false,
+            is_reference: false,
+            collapsed:    false,
+            full_name:    Some(name.clone()),
+            note:         None,
+            name,
+
+            parent_index: NO_PARENT,
+        }];
+
+        Ok(Self {
+            file_path: fname.to_owned(),
+            loaded_cels, loaded_layers, offset,
+            loaded_tags: vec![],
+            loaded_slices: vec![],
+            loaded_tilesets: vec![],
+            frame_count: 1,
+            frame_durations: vec![0],
+            failed_uploads: 0,
+            warnings: vec![],
+            layer_lookup: HashMap::from([(0, 0)]),
+            soloed_layer: None,
+            image_width, image_height,
+            pixel_width: 1, pixel_height: 1,
+            colour_depth: 32,
+            palette: vec![],
+            palette_names: vec![],
+            transparent_index: 0,
+
+            cached_list: None,
+            cached_list_rows: vec![],
+            layer_filter: String::new(),
+            cached_palette_labels: None,
+            cached_visibility: None,
+        })
+    }
+
+    /// Uploads the cels that are on screen, or linked to from a cel that is, and
+    /// drops the textures of cels that have been off screen for longer than
+    /// `TEXTURE_KEEP_SECONDS`, so big sprites only take up as much VRAM as the
+    /// view needs. Call this before `draw` each frame with the same area.
+    /// 
+    /// Cels the GPU turns down, like ones bigger than the largest texture it
+    /// supports, are counted in `failed_uploads` and never tried again.
+    pub fn stream_textures(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, visible_area: &Rectangle) {
+        let now = rl.get_time();
+
+        let on_screen: Vec<usize> = (0..self.loaded_cels.len())
+            .filter(|x| self.loaded_cels[*x].collision_bounds.check_collision_recs(visible_area))
+            .filter_map(|x| self.cel_source_index(x))
+            .collect();
+
+        for cel_index in on_screen {
+            self.loaded_cels[cel_index].last_seen = now;
+        }
+
+        for cel in &mut self.loaded_cels {
+            if now - cel.last_seen > TEXTURE_KEEP_SECONDS {
+                cel.texture = None;
+                continue;
+            }
+
+            let Some(pixels) = &cel.pixels else { continue };
+            if cel.texture.is_some() || cel.upload_failed {
+                continue;
+            }
+
+            // built with the pixels already in, so each cel only goes up the once
+            let img = image_from_rgba(cel.size.x as i32, cel.size.y as i32, pixels);
+            match rl.load_texture_from_image(thread, &img) {
+                Ok(txtr) => cel.texture = Some(txtr),
+                Err(e) => {
+                    self.warnings.push(format!("cel on frame {} layer {} couldn't be uploaded: {e}", cel.frame_index, cel.layer_index));
+                    cel.upload_failed = true;
+                    self.failed_uploads += 1;
+                },
+            }
+        }
+    }
+
+    /// Uploads the atlas of every tileset that isn't up yet, for the tileset
+    /// panel.
+    pub fn upload_tilesets(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) {
+        for tileset in &mut self.loaded_tilesets {
+            let Some(atlas) = &tileset.atlas else { continue };
+            if tileset.texture.is_some() || tileset.upload_failed {
+                continue;
+            }
+
+            let width = TILESET_COLUMNS * tileset.tile_width as usize;
+            let height = tileset.rows() * tileset.tile_height as usize;
+
+            let img = image_from_rgba(width as i32, height as i32, atlas);
+            match rl.load_texture_from_image(thread, &img) {
+                Ok(txtr) => tileset.texture = Some(txtr),
+                Err(e) => {
+                    self.warnings.push(format!("tileset {} couldn't be uploaded: {e}", tileset.name));
+                    tileset.upload_failed = true;
+                },
+            }
+        }
+    }
+
+    /// Drops every cel's and tileset's texture, for sprites that aren't being
+    /// shown. They're uploaded again by `stream_textures` as they come back on
+    /// screen, and by `upload_tilesets`.
+    pub fn release_textures(&mut self) {
+        for cel in &mut self.loaded_cels {
+            cel.texture = None;
+            cel.last_seen = f64::NEG_INFINITY;
+        }
+
+        for tileset in &mut self.loaded_tilesets {
+            tileset.texture = None;
+        }
+    }
+
+    /// Gets the area of the grid that a frame and layer's cel sits in, with the
+    /// pixel size already applied.
+    fn cell_rect(&self, frame_index: usize, layer_index: usize) -> Rectangle {
+        Rectangle{
+            x:        frame_index as f32 * self.offset.x,
+            y:      -(layer_index as f32 * self.offset.y),
+            width:  self.image_width  as f32 * self.pixel_width  as f32,
+            height: self.image_height as f32 * self.pixel_height as f32,
+        }
+    }
+
+    /// Gets the area a tag's label and bar take up above the grid. The bar covers
+    /// every frame column the tag spans.
+    fn tag_rect(&self, tag_index: usize) -> Rectangle {
+        let Some(t) = self.loaded_tags.get(tag_index) else { return Rectangle::default() };
+
+        let first_cell = self.cell_rect(t.from.min(t.to), 0);
+        let last_cell = self.cell_rect(t.from.max(t.to), 0);
+        let text_y = -((self.offset.y * self.loaded_layers.len() as f32 - 1.0) + 16.0) as i32 - ((tag_index + 1) as i32 * FONT_SIZE_REG);
+
+        Rectangle{
+            x:      first_cell.x,
+            y:      text_y as f32,
+            width:  last_cell.x + last_cell.width - first_cell.x,
+            height: (FONT_SIZE_REG + 6) as f32,
+        }
+    }
+
+    /// Finds the tag whose label or bar is at a world position.
+    pub(crate) fn tag_at(&self, world: Vector2) -> Option<usize> {
+        (0..self.loaded_tags.len()).find(|i| self.tag_rect(*i).check_collision_point_rec(world))
+    }
+
+    /// Gets the name of a tag.
+    pub(crate) fn tag_name(&self, tag_index: usize) -> Option<&str> {
+        self.loaded_tags.get(tag_index).map(|x| x.name.as_str())
+    }
+
+    /// Gets the first and last frame a tag spans, in order, along with its colour.
+    pub(crate) fn tag_span(&self, tag_index: usize) -> Option<(usize, usize, Color)> {
+        self.loaded_tags.get(tag_index).map(|x| (x.from.min(x.to), x.from.max(x.to), x.colour))
+    }
+
+    /// Gets the nearest frame a tag starts on after `frame`, or before it if
+    /// `forward` is false.
+    pub(crate) fn tag_boundary(&self, frame: usize, forward: bool) -> Option<usize> {
+        let mut starts: Vec<usize> = (0..self.loaded_tags.len())
+            .filter_map(|x| self.tag_span(x))
+            .map(|(from, _, _)| from)
+            .collect();
+        starts.sort_unstable();
+
+        next_boundary(&starts, frame, forward)
+    }
+
+    /// Sets up playback of just a tag's frames, going the way the tag says to
+    /// and as many times as it says to.
+    pub(crate) fn tag_playback(&self, tag_index: usize) -> Option<Playback> {
+        let tag = self.loaded_tags.get(tag_index)?;
+        let sequence = tag_frame_sequence(tag, tag.repeat_count);
+
+        let playback = match tag.repeat_count {
+            0 => Playback::sequence(sequence, true),
+            n => Playback::looped(sequence, tag_loop_starts(tag, n)),
+        };
+        Some(Playback { tag: Some(tag_index), ..playback })
+    }
+
+    /// Snaps a world position to the sprite pixel under it, taking the pixel size
+    /// into account. Returns `None` if the position isn't inside any cell of the
+    /// grid, including the gaps between them.
+    pub(crate) fn pixel_at(&self, world: Vector2) -> Option<SpritePixel> {
+        let frame = (world.x / self.offset.x).floor();
+        // rows go upwards, with each cell hanging down from its top edge
+        let layer = (-world.y / self.offset.y).ceil();
+
+        if frame < 0.0 || layer < 0.0 || frame as usize >= self.frame_count || layer as usize >= self.loaded_layers.len() {
+            return None;
+        }
+
+        let cell = self.cell_rect(frame as usize, layer as usize);
+        if !cell.check_collision_point_rec(world) {
+            return None;
+        }
+
+        Some(SpritePixel {
+            frame_index: frame as usize,
+            layer_index: layer as usize,
+            x:           ((world.x - cell.x) / self.pixel_width  as f32) as u16,
+            y:           ((world.y - cell.y) / self.pixel_height as f32) as u16,
+        })
+    }
+
+    /// Reads the pixel under a world position out of the cel there, as RGBA along
+    /// with its palette index in indexed sprites. Linked cels are read from the
+    /// cel they're linked to, and anywhere a cel doesn't cover is transparent.
+    /// Returns `None` if there's no cel under the position.
+    pub(crate) fn sample_pixel(&self, world: Vector2) -> Option<(SpritePixel, [u8; 4], Option<u8>)> {
+        let pixel = self.pixel_at(world)?;
+        let cel = self.loaded_cels.iter()
+            .find(|x| x.frame_index == pixel.frame_index && self.layer_of(x.layer_index) == Some(pixel.layer_index))?;
+        let source = self.cel_source(cel)?;
+
+        let (x, y) = (pixel.x as i32 - source.position.x as i32, pixel.y as i32 - source.position.y as i32);
+        let (width, height) = (source.size.x as i32, source.size.y as i32);
+
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return Some((pixel, [0; 4], (self.colour_depth == 8).then_some(self.transparent_index)));
+        }
+
+        let at = (y * width + x) as usize;
+        let rgba = source.pixels.as_ref()
+            .and_then(|p| p.get((at * 4)..(at * 4 + 4)))
+            .map_or([0; 4], |p| [p[0], p[1], p[2], p[3]]);
+        let index = source.indices.as_ref().and_then(|i| i.get(at)).copied();
+
+        Some((pixel, rgba, index))
+    }
+
+    /// Draws lines along the sprite pixel edges over the part of a cell that's in
+    /// view, fading in as the zoom goes past `PIXEL_GRID_MIN_ZOOM`.
+    fn draw_pixel_grid(&self, d: &mut impl RaylibDraw, cam: &Camera2D, cell: Rectangle, visible_area: &Rectangle) {
+        let Some(clip) = cell.get_collision_rec(visible_area) else { return };
+
+        let alpha = ((cam.zoom - PIXEL_GRID_MIN_ZOOM) * 8.).clamp(0., PIXEL_GRID_MAX_ALPHA as f32) as u8;
+        let colour = Color{a: alpha, ..PIXEL_GRID_COLOR};
+        let thickness = 1.0 / cam.zoom;
+        let (pixel_w, pixel_h) = (self.pixel_width as f32, self.pixel_height as f32);
+
+        let first_x = ((clip.x - cell.x) / pixel_w).ceil() as i32;
+        let last_x = ((clip.x + clip.width - cell.x) / pixel_w).floor() as i32;
+        for x in first_x..=last_x {
+            d.draw_rectangle_rec(Rectangle{ x: cell.x + x as f32 * pixel_w, width: thickness, ..clip }, colour);
+        }
+
+        let first_y = ((clip.y - cell.y) / pixel_h).ceil() as i32;
+        let last_y = ((clip.y + clip.height - cell.y) / pixel_h).floor() as i32;
+        for y in first_y..=last_y {
+            d.draw_rectangle_rec(Rectangle{ y: cell.y + y as f32 * pixel_h, height: thickness, ..clip }, colour);
+        }
+    }
+
+    /// Gets the area on the grid a single sprite pixel covers.
+    pub(crate) fn pixel_rect(&self, pixel: &SpritePixel) -> Rectangle {
+        let cell = self.cell_rect(pixel.frame_index, pixel.layer_index);
+        Rectangle{
+            x:      cell.x + pixel.x as f32 * self.pixel_width  as f32,
+            y:      cell.y + pixel.y as f32 * self.pixel_height as f32,
+            width:  self.pixel_width  as f32,
+            height: self.pixel_height as f32,
+        }
+    }
+
+    /// Draws each slice over every cel of the frames it's on, with the middle of
+    /// 9-patch slices and the pivot when it has them.
+    fn draw_slices(&self, d: &mut impl RaylibDraw, cam: &Camera2D, visible_area: &Rectangle) {
+        let (scale_x, scale_y) = (self.pixel_width as f32, self.pixel_height as f32);
+        let thickness = 1.0 / cam.zoom;
+
+        let to_cell = |cell: Rectangle, r: Rectangle| Rectangle{
+            x:      cell.x + r.x * scale_x,
+            y:      cell.y + r.y * scale_y,
+            width:  r.width  * scale_x,
+            height: r.height * scale_y,
+        };
+
+        for slice in &self.loaded_slices {
+            for frame_index in 0..self.frame_count {
+                let Some(key) = slice.key_at(frame_index) else { continue };
+
+                for layer_index in 0..self.loaded_layers.len() {
+                    let cell = self.cell_rect(frame_index, layer_index);
+                    let bounds = to_cell(cell, key.bounds);
+
+                    if !bounds.check_collision_recs(visible_area) {
+                        continue;
+                    }
 
-                layer.parent_index = *parent_map.get(&((layer.child_level as i32) - 1)).unwrap_or(&NO_PARENT);
-            }
-        }
+                    d.draw_rectangle_lines_ex(bounds, thickness, slice.colour);
 
-        let frame_count = main_data.frames.len();
-        let mut r = Self {
-            loaded_cels, loaded_layers, loaded_tags, frame_count, offset,
-            image_width, image_height, pixel_width, pixel_height,
+                    if let Some(center) = key.center {
+                        d.draw_rectangle_lines_ex(to_cell(cell, center), thickness, Color{a: slice.colour.a / 2, ..slice.colour});
+                    }
 
-            cached_list: None
-        };
+                    if let Some(pivot) = key.pivot {
+                        let px = to_cell(cell, Rectangle{ x: pivot.x, y: pivot.y, width: 1.0, height: 1.0 });
+                        d.draw_rectangle_rec(px, Color{a: slice.colour.a / 2, ..slice.colour});
+                        d.draw_rectangle_lines_ex(px, thickness, slice.colour);
+                    }
 
-        for layer_index in 0..r.loaded_layers.len() {
-            r.loaded_layers[layer_index].full_name = Some(r.layer_name(layer_index))
+                    draw_label(d, &slice.name, bounds.x as i32, bounds.y as i32 - FONT_SIZE_REG - 2, FONT_SIZE_REG, slice.colour);
+                }
+            }
         }
-
-        Ok(r)
     }
 
-    pub fn draw(&mut self, d: &mut RaylibMode2D<'_, RaylibDrawHandle<'_>>, cam: &Camera2D, visible_area: &Rectangle) {
+    pub fn draw(&mut self, d: &mut RaylibMode2D<'_, RaylibDrawHandle<'_>>, cam: &Camera2D, visible_area: &Rectangle, options: &DrawOptions, pinned: &[usize]) {
         let scale_x: i32 = self.pixel_width.into();
         let scale_y: i32 = self.pixel_height.into();
 
-        for img in self.loaded_cels.iter() {
-            if !img.collision_bounds.check_collision_recs(visible_area) || !self.is_layer_visible(img.layer_index as usize) {
+        // never smaller than a sprite pixel, so zooming right in doesn't end up
+        // drawing thousands of squares per cel
+        let checker_size = (CHECKER_SCREEN_SIZE / cam.zoom).max(self.pixel_width.min(self.pixel_height) as f32);
+
+        self.cache_visibility();
+
+        for (cel_index, img) in self.loaded_cels.iter().enumerate() {
+            let Some(layer_position) = self.layer_of(img.layer_index) else { continue };
+
+            if !img.collision_bounds.check_collision_recs(visible_area) || !self.is_layer_visible(layer_position) {
                 continue;
             }
 
-            let my_layer = &self.loaded_layers[img.layer_index as usize];
+            if options.keyframes_only && img.linked_to_frame.is_some() {
+                continue;
+            }
+
+            let my_layer = &self.loaded_layers[layer_position];
+
+            // linked cels show the image of the cel they're linked to in their
+            // own slot, with the link drawn over it
+            let source = self.cel_source(img).unwrap_or(img);
+
+            // where the cel's image is actually drawn, which is usually only part of
+            // the canvas
+            let cell = self.cell_rect(img.frame_index, layer_position);
+            let dest = Rectangle{
+                x: cell.x + source.position.x * scale_x as f32,
+                y: cell.y + source.position.y * scale_y as f32,
+                width: source.scaled_size.unwrap_or(source.size).x * scale_x as f32,
+                height: source.scaled_size.unwrap_or(source.size).y * scale_y as f32,
+            };
+
+            if let Some(texture) = &source.texture {
+                draw_checkerboard(d, img.collision_bounds, visible_area, checker_size);
+
+                d.draw_texture_pro(texture,
+                    Rectangle{
+                        x:      0.0,
+                        y:      0.0,
+                        width:  source.size.x,
+                        height: source.size.y,
+                    }, 
+                    dest, 
+                    Vector2{ x: 0.0, y: 0.0 }, 
+                    0.0, 
+                    Color{a: combined_opacity(my_layer.opacity, source.opacity), ..Color::WHITE}
+                );
+            }
 
             let rect_colour = Color{
                 a: if img.hover { 96 } else { 32 },
@@ -294,24 +1678,18 @@ impl LoadedSprite {
                 }
             };
 
-            d.draw_rectangle_lines(
-                (img.content_bounds.x + img.frame_index as f32 * (self.offset.x - 1.0)) as i32 - img.position.x as i32,
-                (img.content_bounds.y - img.layer_index as f32 * (self.offset.y - 1.0)) as i32 - img.position.y as i32,
-                self.image_width as i32 * scale_x,
-                self.image_height as i32 * scale_y,
-                rect_colour
-            );
+            d.draw_rectangle_lines_ex(dest, 1.0, rect_colour);
 
             if let Some(link) = img.linked_to_frame {
                 if img.hover {
                     d.draw_line_ex(
                         Vector2{
                             x:  (img.frame_index as f32 * (self.offset.x) + (self.image_width  as f32 / 2.0)), 
-                            y: -(img.layer_index as f32 * (self.offset.y) - (self.image_height as f32 / 2.0))
+                            y: -(layer_position as f32 * (self.offset.y) - (self.image_height as f32 / 2.0))
                         },
                         Vector2{
                             x: (link as f32 * (self.offset.x) + (self.image_width as f32 / 2.0)),
-                            y: -(img.layer_index as f32 * (self.offset.y) - (self.image_height as f32 / 2.0))
+                            y: -(layer_position as f32 * (self.offset.y) - (self.image_height as f32 / 2.0))
                         },
                         3.0,
                         rect_colour
@@ -319,14 +1697,14 @@ impl LoadedSprite {
 
                     d.draw_circle(
                         (link as f32 * (self.offset.x) + (self.image_width as f32 / 2.0)) as i32, 
-                        -(img.layer_index as f32 * (self.offset.y) - (self.image_height as f32 / 2.0)) as i32, 
+                        -(layer_position as f32 * (self.offset.y) - (self.image_height as f32 / 2.0)) as i32, 
                         6.0 + f32::sin(d.get_time() as f32 * 1.7) * 2.4,
                         rect_colour
                     );
 
                     for i in 0..(img.frame_index as u16 - link) {
                         let cx = ((link + i + 1) as f32 - d.get_time().fract() as f32) * (self.offset.x) + (self.image_width as f32 / 2.0);
-                        let cy = -(img.layer_index as f32 * (self.offset.y) - (self.image_height as f32 / 2.0));
+                        let cy = -(layer_position as f32 * (self.offset.y) - (self.image_height as f32 / 2.0));
                         let r = 3.5;
 
                         let v1 = Vector2{
@@ -358,7 +1736,7 @@ impl LoadedSprite {
                 }
 
                 let tx =  (img.frame_index as f32 * (self.offset.x) + (self.image_width as f32 / 2.0)) as i32;
-                let ty = -(img.layer_index as f32 * (self.offset.y) - (self.image_height as f32 / 2.0) + 16.) as i32;
+                let ty = -(layer_position as f32 * (self.offset.y) - (self.image_height as f32 / 2.0) + 16.) as i32;
                 
                 d.draw_text(
                     format!("{link}").as_str(), 
@@ -367,28 +1745,29 @@ impl LoadedSprite {
                     FONT_SIZE_BIG,
                     rect_colour
                 );
-            } else if let Some(texture) = &img.texture {
-                d.draw_texture_pro(texture,
-                    Rectangle{
-                        x:      0.0,
-                        y:      0.0,
-                        width:  img.size.x,
-                        height: img.size.y,
-                    }, 
-                    Rectangle{
-                        x: (img.content_bounds.x + (img.frame_index as f32 * (self.offset.x - 1.0))) * scale_x as f32,
-                        y: (img.content_bounds.y - (img.layer_index as f32 * (self.offset.y - 1.0))) * scale_y as f32,
-                        width: img.size.x * scale_x as f32,
-                        height: img.size.y * scale_y as f32,
-                    }, 
-                    Vector2{ x: 0.0, y: 0.0 }, 
-                    0.0, 
-                    Color{a: {
-                        let l = (my_layer.opacity as f64) / 255.0;
-                        let r = (img.opacity as f64) / 255.0;
-                        (l * r * 255.0).round().clamp(0.0, 255.0) as u8
-                    }, ..Color::WHITE}
-                );
+            } else if let Some((tiles_w, tiles_h)) = img.tile_grid {
+                // the tile size isn't known without the tileset, so the grid is just
+                // split evenly over the cell
+                let cell = self.cell_rect(img.frame_index, layer_position);
+                let colour = Color{a: 96, ..TILEMAP_COLOR};
+
+                for i in 0..=tiles_w {
+                    let x = cell.x + cell.width * (i as f32 / tiles_w.max(1) as f32);
+                    d.draw_line_v(Vector2{ x, y: cell.y }, Vector2{ x, y: cell.y + cell.height }, colour);
+                }
+
+                for i in 0..=tiles_h {
+                    let y = cell.y + cell.height * (i as f32 / tiles_h.max(1) as f32);
+                    d.draw_line_v(Vector2{ x: cell.x, y }, Vector2{ x: cell.x + cell.width, y }, colour);
+                }
+            }
+
+            if options.pixel_grid && cam.zoom > PIXEL_GRID_MIN_ZOOM && source.texture.is_some() {
+                self.draw_pixel_grid(d, cam, img.collision_bounds, visible_area);
+            }
+
+            if pinned.contains(&cel_index) {
+                d.draw_rectangle_lines_ex(img.collision_bounds, 2.0, PINNED_COLOR);
             }
 
             if DEBUG_VISUALS { d.draw_rectangle_lines_ex(img.collision_bounds, 2.0, ERR_COLOR); }
@@ -396,19 +1775,37 @@ impl LoadedSprite {
 
         let line_alpha = (24. * cam.zoom).clamp(0., 255.) as u8;
 
+        // the labels and lines going along each row and down each column are
+        // culled like the cels, over everything each of them draws
+        let row_height = self.offset.y.max(self.offset.x / 2.0 + FONT_SIZE_REG as f32);
+        let column_top = -(self.offset.y * self.loaded_layers.len().saturating_sub(1) as f32 + 16.0);
+        let column_bottom = self.offset.y + 16.0 + FONT_SIZE_REG as f32;
+
         for (i, l) in self.loaded_layers.iter().enumerate() {
             let m = d.measure_text(l.full_name.as_ref().unwrap(), FONT_SIZE_REG);
             let my_alpha = line_alpha / if l.visible { 1 } else { 2 };
 
-            d.draw_text(
+            let row = Rectangle{
+                x:      -(16 + m) as f32,
+                y:      self.cell_rect(0, i).y,
+                width:  (16 + m) as f32 + self.offset.x * self.frame_count as f32,
+                height: row_height,
+            };
+
+            if !row.check_collision_recs(visible_area) {
+                continue;
+            }
+
+            draw_label(d,
                 l.full_name.as_ref().unwrap(),
                 -(16 + m),
                 -(self.offset.y as i32 * i as i32 - (self.offset.x / 2.0) as i32),
                 FONT_SIZE_REG, LABEL_COLOR
             );
             
-            let line_y = -((self.offset.y) as i32 * i as i32);
-            let line_y2 = line_y + self.image_height as i32 * scale_y;
+            let cell = self.cell_rect(0, i);
+            let line_y = cell.y as i32;
+            let line_y2 = (cell.y + cell.height) as i32;
             
             d.draw_line(
                 -(16 + m), line_y,
@@ -439,25 +1836,37 @@ impl LoadedSprite {
             let fstr = format!("{i}");
             let fstr = fstr.as_str();
 
-            let width = self.image_width as i32 - d.measure_text(fstr, FONT_SIZE_REG);
-            let width = width / 2;
+            let text_width = d.measure_text(fstr, FONT_SIZE_REG);
+            let width = (self.image_width as i32 - text_width) / 2;
+
+            let column = Rectangle{
+                x:      self.offset.x * i as f32 + width.min(0) as f32,
+                y:      column_top,
+                width:  self.offset.x.max(text_width as f32),
+                height: column_bottom - column_top,
+            };
+
+            if !column.check_collision_recs(visible_area) {
+                continue;
+            }
 
-            d.draw_text(fstr,
+            draw_label(d, fstr,
                 ((self.offset.x) * i as f32) as i32 + width,
                 (self.offset.y + 16.0) as i32,
                 FONT_SIZE_REG,
                 LABEL_COLOR
             );
 
-            d.draw_text(fstr,
+            draw_label(d, fstr,
                 ((self.offset.x) * i as f32) as i32 + width,
                 -(self.offset.y * (self.loaded_layers.len() - 1) as f32 + 16.0) as i32,
                 FONT_SIZE_REG,
                 LABEL_COLOR
             );
             
-            let line_x = ((self.offset.x) * i as f32) as i32;
-            let line_x2 = line_x + self.image_width as i32 * scale_x;
+            let cell = self.cell_rect(i, 0);
+            let line_x = cell.x as i32;
+            let line_x2 = (cell.x + cell.width) as i32;
 
             d.draw_line(
                 line_x, (self.offset.y + 4.0) as i32, 
@@ -486,7 +1895,7 @@ impl LoadedSprite {
 
         for (i, t) in self.loaded_tags.iter().enumerate() {
             let tag_text = format!(
-                "{}\n{} {} {}",
+                "{}\n{} {} {} {}",
                 t.name,
                 t.from,
                 match t.direction {
@@ -495,15 +1904,31 @@ impl LoadedSprite {
                     AsepriteTagDirection::PingPong        => ui_main::TAG_DIRECTION_FORWARD_PONG,
                     AsepriteTagDirection::PingPongReverse => ui_main::TAG_DIRECTION_REVERSE_PONG,
                 },
-                t.to
+                t.to,
+                t.repeat_label()
             );
 
             let tag_text = tag_text.as_str();
 
-            let text_y = -((self.offset.y * self.loaded_layers.len() as f32 - 1.0) + 16.0) as i32 - ((i + 1) as i32 * FONT_SIZE_REG);
+            let bounds = self.tag_rect(i);
+
+            // the label's second line and long names can hang past the bar
+            let culled = Rectangle{
+                width:  bounds.width.max(d.measure_text(tag_text, FONT_SIZE_REG) as f32),
+                height: bounds.height + FONT_SIZE_REG as f32,
+                ..bounds
+            };
+
+            if !culled.check_collision_recs(visible_area) {
+                continue;
+            }
+
+            let text_y = bounds.y as i32;
+            let from_x = bounds.x as i32;
+            let to_x = (bounds.x + bounds.width) as i32;
 
-            d.draw_text(tag_text,
-                ((self.offset.x) * (t.from as f32 - 1.0)) as i32 + self.image_width as i32,
+            draw_label(d, tag_text,
+                from_x,
                 text_y,
                 FONT_SIZE_REG,
                 LABEL_COLOR
@@ -511,13 +1936,10 @@ impl LoadedSprite {
 
             let line_y = text_y + FONT_SIZE_REG + 4;
 
-            let from_x = ((self.offset.x) * (t.from as f32 - 1.0)) as i32 + self.image_width as i32;
-            let to_x = ((self.offset.x) * (t.to as f32 - 0.7)) as i32 + self.image_width as i32;
-
-            d.draw_line(
-                from_x, line_y,
-                to_x,line_y,
-                Color{a: (line_alpha as u16 * 2).clamp(0, 255) as u8, ..SMALL_LINE_COLOR}
+            d.draw_rectangle(
+                from_x, line_y - 1,
+                to_x - from_x, 3,
+                Color{a: (line_alpha as u16 * 2).clamp(0, 255) as u8, ..t.colour}
             );
 
             let path = match t.direction {
@@ -533,6 +1955,25 @@ impl LoadedSprite {
             );
         }
 
+        if options.slices {
+            self.draw_slices(d, cam, visible_area);
+        }
+
+        if cam.zoom >= CROSSHAIR_MIN_ZOOM {
+            let mouse_pt = d.get_screen_to_world2D(d.get_mouse_position(), cam);
+
+            if let Some(pixel) = self.pixel_at(mouse_pt) {
+                let cell = self.cell_rect(pixel.frame_index, pixel.layer_index);
+                let px = self.pixel_rect(&pixel);
+                let thickness = 1.0 / cam.zoom;
+                let faint = Color{a: 64, ..CROSSHAIR_COLOR};
+
+                d.draw_rectangle_rec(Rectangle{ y: px.y + px.height / 2.0, height: thickness, ..cell }, faint);
+                d.draw_rectangle_rec(Rectangle{ x: px.x + px.width / 2.0, width: thickness, ..cell }, faint);
+                d.draw_rectangle_lines_ex(px, thickness, CROSSHAIR_COLOR);
+            }
+        }
+
         if DEBUG_VISUALS {
             let mouse_pt = d.get_screen_to_world2D(d.get_mouse_position(), cam);
             d.draw_line(
@@ -564,27 +2005,658 @@ impl LoadedSprite {
         }
     }
 
+    /// Gets the index of the cel under the mouse as of the last `step`.
+    pub fn hovered_cel(&self) -> Option<usize> {
+        self.loaded_cels.iter().position(|x| x.hover)
+    }
+
+    /// Encodes a cel's pixels as a PNG. Returns `None` for cels without their own
+    /// image (like linked cels), or if encoding the image fails.
+    pub fn cel_png(&self, cel_index: usize) -> Option<Vec<u8>> {
+        let cel = self.loaded_cels.get(cel_index)?;
+        let img = image_from_rgba(cel.size.x as i32, cel.size.y as i32, cel.pixels.as_ref()?);
+
+        img.export_image_to_memory(".png").ok().map(|x| x.to_vec())
+    }
+
+    /// Flattens a frame into one image the size of the sprite like Aseprite shows
+    /// it, going through the visible layers in order with the layer and cel
+    /// opacity and blend mode applied. Reference layers are left out, like in
+    /// Aseprite's exports.
+    pub(crate) fn composite_frame(&self, frame_index: usize) -> Image {
+        image_from_rgba(self.image_width as i32, self.image_height as i32, &self.flatten_frame(frame_index))
+    }
+
+    /// Encodes a composited frame as a PNG, see `composite_frame`. Returns `None`
+    /// if encoding the image fails.
+    pub(crate) fn frame_png(&self, frame_index: usize) -> Option<Vec<u8>> {
+        self.composite_frame(frame_index).export_image_to_memory(".png").ok().map(|x| x.to_vec())
+    }
+
+    /// Gets where a frame is saved to by default, which is next to the sprite
+    /// with the frame index after its name.
+    pub(crate) fn frame_png_path(&self, frame_index: usize) -> PathBuf {
+        let path = Path::new(&self.file_path);
+        let stem = path.file_stem().map(|x| x.to_string_lossy()).unwrap_or_else(|| "sprite".into());
+
+        path.with_file_name(format!("{stem}_{frame_index}.png"))
+    }
+
+    /// Gets where a sprite sheet is saved to by default, next to the sprite with
+    /// "sheet" after its name.
+    pub(crate) fn sheet_path(&self) -> PathBuf {
+        let path = Path::new(&self.file_path);
+        let stem = path.file_stem().map(|x| x.to_string_lossy()).unwrap_or_else(|| "sprite".into());
+
+        path.with_file_name(format!("{stem}_sheet.png"))
+    }
+
+    /// Composites every frame and packs them into one PNG at `path`, in order
+    /// along the rows. Next to it goes a JSON file of the same name saying where
+    /// each frame is, how long it lasts and which tags it's in, laid out like
+    /// Aseprite's own sheet export.
+    pub(crate) fn export_sheet(&self, path: &Path, layout: SheetLayout) -> io::Result<()> {
+        self.sheet_export(path, layout).save(|_, _| ())
+    }
+
+    /// Gets everything `export_sheet` needs to save the sheet, so the slow part
+    /// can be done on another thread.
+    pub(crate) fn sheet_export(&self, path: &Path, layout: SheetLayout) -> SheetExport {
+        let columns = layout.columns(self.frame_count);
+        let rows = self.frame_count.div_ceil(columns);
+
+        let (frame_w, frame_h) = (self.image_width as usize, self.image_height as usize);
+        let (sheet_w, sheet_h) = (frame_w * columns, frame_h * rows);
+
+        let stem = Path::new(&self.file_path).file_stem().map(|x| x.to_string_lossy()).unwrap_or_else(|| "sprite".into());
+        let mut frames = Vec::with_capacity(self.frame_count);
+
+        for frame in 0..self.frame_count {
+            let (x, y) = (frame % columns * frame_w, frame / columns * frame_h);
+
+            let tags: Vec<String> = self.loaded_tags.iter()
+                .filter(|t| t.spans(frame))
+                .map(|t| json_string(&t.name))
+                .collect();
+
+            frames.push(format!(
+                "    {{ \"filename\": {}, \"frame\": {{ \"x\": {x}, \"y\": {y}, \"w\": {frame_w}, \"h\": {frame_h} }}, \"duration\": {}, \"tags\": [{}] }}",
+                json_string(&format!("{stem} {frame}")),
+                frame_duration(&self.frame_durations, frame),
+                tags.join(", "),
+            ));
+        }
+
+        let tags: Vec<String> = self.loaded_tags.iter().map(|t| format!(
+            "      {{ \"name\": {}, \"from\": {}, \"to\": {}, \"direction\": \"{}\", \"repeat\": {} }}",
+            json_string(&t.name), t.from.min(t.to), t.from.max(t.to), t.direction_name(), t.repeat_count,
+        )).collect();
+
+        let image_name = path.file_name().map(|x| x.to_string_lossy()).unwrap_or_default();
+        let json = format!(
+            "{{\n  \"frames\": [\n{}\n  ],\n  \"meta\": {{\n    \"image\": {},\n    \"size\": {{ \"w\": {sheet_w}, \"h\": {sheet_h} }},\n    \"frameTags\": [\n{}\n    ]\n  }}\n}}\n",
+            frames.join(",\n"), json_string(&image_name), tags.join(",\n"),
+        );
+
+        SheetExport {
+            path: path.to_owned(),
+            stacks: self.frame_stacks(),
+            columns, json,
+        }
+    }
+
+    /// Gets where the palette is saved to by default, next to the sprite with the
+    /// format's extension.
+    pub(crate) fn palette_path(&self, format: PaletteFormat) -> PathBuf {
+        Path::new(&self.file_path).with_extension(format.extension())
+    }
+
+    /// Saves the palette at `path` in a format other tools can read, with each
+    /// entry's name if the format has room for it.
+    pub(crate) fn export_palette(&self, path: &Path, format: PaletteFormat) -> io::Result<()> {
+        let stem = Path::new(&self.file_path).file_stem().map(|x| x.to_string_lossy()).unwrap_or_else(|| "sprite".into());
+        fs::write(path, format.write(&stem, &self.palette, &self.palette_names))
+    }
+
+    /// Gets where a GIF is saved to by default, next to the sprite and named after
+    /// the tag if there is one.
+    pub(crate) fn gif_path(&self, tag_index: Option<usize>) -> PathBuf {
+        let path = Path::new(&self.file_path);
+        let stem = path.file_stem().map(|x| x.to_string_lossy()).unwrap_or_else(|| "sprite".into());
+
+        // tag names can have anything in them, including slashes
+        let tag = tag_index.and_then(|x| self.tag_name(x))
+            .map(|x| x.chars().map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' }).collect::<String>());
+
+        match tag {
+            Some(tag) => path.with_file_name(format!("{stem}_{tag}.gif")),
+            None      => path.with_file_name(format!("{stem}.gif")),
+        }
+    }
+
+    /// Composites the frames of a tag, or every frame if there's no tag, and
+    /// saves them as an animated GIF at `path` with each frame's own duration.
+    /// Tags play in their direction and as many times as they say to.
+    ///
+    /// Indexed sprites keep their palette, everything else gets one picked by
+    /// median cut across all the frames.
+    pub(crate) fn export_gif(&self, path: &Path, tag_index: Option<usize>) -> io::Result<()> {
+        self.gif_export(path, tag_index).save(|_, _| ())
+    }
+
+    /// Gets everything `export_gif` needs to save the GIF, so the slow part can
+    /// be done on another thread.
+    pub(crate) fn gif_export(&self, path: &Path, tag_index: Option<usize>) -> GifExport {
+        let (sequence, looping) = match tag_index.and_then(|x| self.loaded_tags.get(x)) {
+            Some(tag) => (tag_frame_sequence(tag, tag.repeat_count), tag.repeat_count == 0),
+            None      => ((0..self.frame_count).collect(), true),
+        };
+
+        let palette = match self.colour_depth {
+            8 if !self.palette.is_empty() => Some(GifPalette::from_palette(&self.palette, self.transparent_index)),
+            _ => None,
+        };
+
+        GifExport {
+            path: path.to_owned(),
+            stacks: self.frame_stacks(),
+            sequence: sequence.into_iter().map(|x| (x, frame_duration(&self.frame_durations, x))).collect(),
+            looping, palette,
+        }
+    }
+
+    /// Flattens a frame like `composite_frame`, with the frames around it laid
+    /// underneath in their tint, fading out the further away they are.
+    pub(crate) fn composite_onion_skin(&self, frame_index: usize, onion: &OnionSkin) -> Image {
+        let (width, height) = (self.image_width as i32, self.image_height as i32);
+        let mut canvas = vec![0u8; width as usize * height as usize * 4];
+
+        let past = (1..=onion.before)
+            .filter_map(|d| frame_index.checked_sub(d).map(|f| (f, d, onion.past_tint)));
+        let future = (1..=onion.after)
+            .map(|d| (frame_index + d, d, onion.future_tint))
+            .filter(|(f, _, _)| *f < self.frame_count);
+
+        // the furthest frames go down first so the nearer ones end up over them
+        let mut around: Vec<(usize, usize, Color)> = past.chain(future).collect();
+        around.sort_by_key(|(_, d, _)| std::cmp::Reverse(*d));
+
+        // halfway to the tint keeps the frame's shading visible
+        let mix = |c: u8, t: u8| ((c as u16 + t as u16) / 2) as u8;
+
+        for (frame, distance, tint) in around {
+            let opacity = OnionSkin::opacity(distance);
+            if opacity == 0 {
+                continue;
+            }
+
+            let flat = self.flatten_frame(frame);
+            for (dst, src) in canvas.chunks_exact_mut(4).zip(flat.chunks_exact(4)) {
+                let tinted = [mix(src[0], tint.r), mix(src[1], tint.g), mix(src[2], tint.b), src[3]];
+
+                let blended = blend_pixel(AsepriteBlendMode::Normal, [dst[0], dst[1], dst[2], dst[3]], tinted, opacity);
+                dst.copy_from_slice(&blended);
+            }
+        }
+
+        let current = self.flatten_frame(frame_index);
+        for (dst, src) in canvas.chunks_exact_mut(4).zip(current.chunks_exact(4)) {
+            let blended = blend_pixel(AsepriteBlendMode::Normal, [dst[0], dst[1], dst[2], dst[3]], [src[0], src[1], src[2], src[3]], 255);
+            dst.copy_from_slice(&blended);
+        }
+
+        image_from_rgba(width, height, &canvas)
+    }
+
+    /// Flattens a frame into RGBA bytes, see `composite_frame`.
+    fn flatten_frame(&self, frame_index: usize) -> Vec<u8> {
+        let stack = FrameStacks {
+            width:  self.image_width,
+            height: self.image_height,
+            frames: vec![self.frame_stack(frame_index)],
+        };
+
+        stack.flatten(0)
+    }
+
+    /// Gets the cels of a frame that show when it's flattened, in the order
+    /// they're drawn with the layer and cel opacity and blend mode to draw them
+    /// with. Reference layers are left out.
+    fn frame_stack(&self, frame_index: usize) -> Vec<StackedCel> {
+        let mut stack = vec![];
+
+        for cel in self.loaded_cels.iter().filter(|x| x.frame_index == frame_index) {
+            let Some(layer_position) = self.layer_of(cel.layer_index) else { continue };
+            let layer = &self.loaded_layers[layer_position];
+
+            if layer.is_reference || !self.is_layer_visible(layer_position) {
+                continue;
+            }
+
+            let Some(source) = self.cel_source(cel) else { continue };
+            stack.extend(stacked_cel(source, layer.blend_mode, combined_opacity(layer.opacity, source.opacity)));
+        }
+
+        stack
+    }
+
+    /// Gets the cels of every frame, see `frame_stack`.
+    fn frame_stacks(&self) -> FrameStacks {
+        FrameStacks {
+            width:  self.image_width,
+            height: self.image_height,
+            frames: (0..self.frame_count).map(|x| self.frame_stack(x)).collect(),
+        }
+    }
+
+    /// Gets the cel that a cel's image comes from, which is itself unless it's
+    /// linked. Linked cels use everything from the cel they're linked to.
+    fn cel_source<'a>(&'a self, cel: &'a PreparedCel) -> Option<&'a PreparedCel> {
+        match cel.linked_to_frame {
+            Some(link) => self.loaded_cels.iter().find(|x| x.frame_index == link as usize && x.layer_index == cel.layer_index),
+            None       => Some(cel),
+        }
+    }
+
+    /// Gets the index of the cel whose image a cel shows, see `cel_source`.
+    fn cel_source_index(&self, cel_index: usize) -> Option<usize> {
+        let cel = self.loaded_cels.get(cel_index)?;
+        match cel.linked_to_frame {
+            Some(link) => self.loaded_cels.iter().position(|x| x.frame_index == link as usize && x.layer_index == cel.layer_index),
+            None       => Some(cel_index),
+        }
+    }
+
+    /// Saves each visible layer's cel in a frame as its own PNG, the size of the
+    /// sprite with the cel where it sits on the canvas. They go in `dir` named
+    /// after the layers, with group and reference layers left out.
+    ///
+    /// With `bake_opacity` the layer and cel opacity are applied to the pixels,
+    /// otherwise they're written out as drawn. Returns how many were saved.
+    pub(crate) fn export_layers(&self, dir: &Path, frame_index: usize, bake_opacity: bool) -> io::Result<usize> {
+        fs::create_dir_all(dir)?;
+
+        let mut used_names = HashSet::new();
+        let mut saved = 0;
+
+        for cel in self.loaded_cels.iter().filter(|x| x.frame_index == frame_index) {
+            let Some(layer_position) = self.layer_of(cel.layer_index) else { continue };
+            let layer = &self.loaded_layers[layer_position];
+
+            if matches!(layer.layer_type, AsepriteLayerType::Group) || layer.is_reference || !self.is_layer_visible(layer_position) {
+                continue;
+            }
+
+            let Some(source) = self.cel_source(cel) else { continue };
+            let opacity = if bake_opacity { combined_opacity(layer.opacity, source.opacity) } else { 255 };
+
+            let mut canvas = vec![0u8; self.image_width as usize * self.image_height as usize * 4];
+            if let Some(stacked) = stacked_cel(source, AsepriteBlendMode::Normal, opacity) {
+                blend_cel_onto(&mut canvas, self.image_width as i32, self.image_height as i32, &stacked);
+            }
+
+            // layer names can have anything in them, and don't have to be unique
+            let full_name = layer.full_name.clone().unwrap_or_else(|| layer.name.clone());
+            let mut name: String = full_name.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '.' { c } else { '_' }).collect();
+            if !used_names.insert(name.clone()) {
+                name = format!("{name}_{layer_position}");
+                used_names.insert(name.clone());
+            }
+
+            let png = image_from_rgba(self.image_width as i32, self.image_height as i32, &canvas)
+                .export_image_to_memory(".png")
+                .map_err(|e| io::Error::other(e.to_string()))?
+                .to_vec();
+
+            fs::write(dir.join(format!("{name}.png")), png)?;
+            saved += 1;
+        }
+
+        Ok(saved)
+    }
+
+    /// Gets where a frame's layers are saved to by default, which is a folder
+    /// next to the sprite.
+    pub(crate) fn layers_dir(&self, frame_index: usize) -> PathBuf {
+        let path = Path::new(&self.file_path);
+        let stem = path.file_stem().map(|x| x.to_string_lossy()).unwrap_or_else(|| "sprite".into());
+
+        path.with_file_name(format!("{stem}_{frame_index}_layers"))
+    }
+
+    /// Gets the name of the file the sprite was loaded from, without the folders
+    /// it's in.
+    pub fn file_name(&self) -> String {
+        Path::new(&self.file_path).file_name()
+            .map_or_else(|| self.file_path.clone(), |x| x.to_string_lossy().into_owned())
+    }
+
+    /// Gets a title and a short description of a cel, for showing in a details
+    /// panel. Returns `None` if there's no cel at `cel_index`.
+    pub fn cel_details(&self, cel_index: usize) -> Option<(String, String)> {
+        let cel = self.loaded_cels.get(cel_index)?;
+        let layer = self.layer_of(cel.layer_index).map(|x| &self.loaded_layers[x]);
+
+        let layer_name = layer.and_then(|x| x.full_name.clone())
+            .unwrap_or_else(|| format!("layer {}", cel.layer_index));
+
+        let title = format!("{} @ {}", layer_name, cel.frame_index);
+        let body = match cel.linked_to_frame {
+            Some(link) => format!("Linked to frame {link}"),
+            None => format!(
+                "Position: {}, {}\nSize: {} x {}\nOpacity: {}",
+                cel.position.x, cel.position.y,
+                cel.size.x, cel.size.y,
+                combined_opacity(layer.map_or(255, |x| x.opacity), cel.opacity)
+            ),
+        };
+
+        Some((title, body))
+    }
+
+    /// Finds every layer `full_name` that is used by more than one layer, along
+    /// with how many layers share it. The result is sorted by name.
+    pub fn layer_name_clashes(&self) -> Vec<(String, usize)> {
+        let mut seen: HashMap<String, usize> = HashMap::new();
+
+        for layer in &self.loaded_layers {
+            let name = layer.full_name.clone().unwrap_or_else(|| layer.name.clone());
+            *seen.entry(name).or_insert(0) += 1;
+        }
+
+        let mut clashes: Vec<(String, usize)> = seen.into_iter().filter(|(_, count)| *count > 1).collect();
+        clashes.sort();
+        clashes
+    }
+
+    /// Gets how big the sprite is and how much is in it.
+    pub(crate) fn summary(&self) -> SpriteSummary {
+        SpriteSummary {
+            frames: self.frame_count,
+            layers: self.loaded_layers.len(),
+            tags:   self.loaded_tags.len(),
+            width:  self.image_width,
+            height: self.image_height,
+        }
+    }
+
+    /// Gets what Aseprite calls the sprite's colour mode.
+    pub fn colour_mode_name(&self) -> &'static str {
+        match self.colour_depth {
+             8 => "Indexed",
+            16 => "Gray",
+            _  => "RGBA",
+        }
+    }
+
+    /// Checks if a palette entry is the transparent one, which only means
+    /// anything in indexed sprites.
+    pub fn is_transparent_index(&self, index: usize) -> bool {
+        self.colour_depth == 8 && index == self.transparent_index as usize
+    }
+
+    /// Gets the text shown when hovering over each palette entry, with its index,
+    /// hex RGBA and name. Made the first time it's asked for.
+    pub fn generate_palette_labels(&mut self) -> &[String] {
+        if self.cached_palette_labels.is_none() {
+            self.cached_palette_labels = Some(self.palette.iter().enumerate()
+            .map(|(i, [r, g, b, a])| {
+                format!("{i}: #{r:02x}{g:02x}{b:02x}{a:02x}{}{}",
+                    self.palette_names.get(i).and_then(|x| x.as_ref()).map(|x| format!(" {x}")).unwrap_or_default(),
+                    if self.is_transparent_index(i) { " (transparent)" } else { "" }
+                )
+            }).collect());
+        }
+
+        self.cached_palette_labels.as_ref().unwrap()
+    }
+
     pub fn invalidate_layer_list(&mut self) {
-        self.cached_list = None
+        self.cached_list = None;
+        self.cached_visibility = None;
+    }
+
+    /// Gets what the layer list is being filtered by.
+    pub fn layer_filter(&self) -> &str {
+        &self.layer_filter
+    }
+
+    /// Only lists layers with `filter` somewhere in their full name, ignoring
+    /// case. Returns if the filter changed.
+    pub fn set_layer_filter(&mut self, filter: &str) -> bool {
+        if self.layer_filter == filter {
+            return false;
+        }
+
+        self.layer_filter = filter.to_owned();
+        self.invalidate_layer_list();
+        true
+    }
+
+    /// Gets which layer a row of the layer list is, going by the last list made.
+    pub fn layer_list_row(&self, row: i32) -> Option<usize> {
+        usize::try_from(row).ok().and_then(|x| self.cached_list_rows.get(x)).copied()
+    }
+
+    /// Folds a group in the layer list, or unfolds it if it already was. Does
+    /// nothing for layers that aren't groups.
+    pub fn toggle_collapsed(&mut self, layer_index: usize) {
+        let Some(layer) = self.loaded_layers.get_mut(layer_index) else { return };
+        if !matches!(layer.layer_type, AsepriteLayerType::Group) {
+            return;
+        }
+
+        layer.collapsed ^= true;
+        self.invalidate_layer_list();
+    }
+
+    /// Gets the layers in the order Aseprite's timeline lists them, top to bottom
+    /// with each group's layers right under it. Layers in collapsed groups are
+    /// left out unless `unfold` is set.
+    fn layer_tree_order(&self, unfold: bool) -> Vec<usize> {
+        let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (index, layer) in self.loaded_layers.iter().enumerate() {
+            children.entry(layer.parent_index).or_default().push(index);
+        }
+
+        // layers are stored bottom up, so the top one comes off the stack first
+        let mut order = Vec::with_capacity(self.loaded_layers.len());
+        let mut stack = children.remove(&NO_PARENT).unwrap_or_default();
+        while let Some(index) = stack.pop() {
+            order.push(index);
+
+            if unfold || !self.loaded_layers[index].collapsed {
+                stack.extend(children.remove(&index).unwrap_or_default());
+            }
+        }
+
+        order
     }
 
     pub fn generate_layer_list(&mut self) -> &str {
         if self.cached_list.is_none() {
-            self.cached_list = Some(self.loaded_layers.iter().rev()
-            .map(|i| {
-                format!("{} {}",
-                    if i.visible {
+            // searching looks inside collapsed groups too
+            let filter = self.layer_filter.to_lowercase();
+            self.cached_list_rows = self.layer_tree_order(!filter.is_empty()).into_iter()
+            .filter(|x| self.loaded_layers[*x].full_name.as_deref().unwrap_or_default().to_lowercase().contains(&filter))
+            .collect();
+
+            self.cached_list = Some(self.cached_list_rows.iter()
+            .map(|&index| {
+                let i = &self.loaded_layers[index];
+                format!("{} {}{}",
+                    if self.soloed_layer == Some(index) {
+                        "#48#"
+                    } else if i.visible {
                         match i.layer_type {
                             AsepriteLayerType::Normal  => if i.is_reference { "#15#" } else { "#44#" },
-                            AsepriteLayerType::Group   => "#217#",
+                            AsepriteLayerType::Group   => if i.collapsed { "#217#" } else { "#3#" },
                             AsepriteLayerType::Tilemap => "#97#",
                         }
                     } else { "#45#" },
-                    i.full_name.as_ref().unwrap()
+                    "  ".repeat(i.child_level as usize),
+                    i.name
                 )
             }).collect::<Vec<String>>().join(";"));//).ok().unwrap()));
         }
 
         self.cached_list.as_ref().unwrap()
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ase::fixtures::{file, frame, layer, palette, raw_cel};
+
+    fn tag(from: usize, to: usize, direction: AsepriteTagDirection) -> PreparedTag {
+        PreparedTag {
+            from, to, direction,
+            name:         "tag".to_owned(),
+            colour:       Color::WHITE,
+            repeat_count: 0,
+        }
+    }
+
+    #[test]
+    fn loop_starts_line_up_with_the_sequence() {
+        let forward = tag(1, 4, AsepriteTagDirection::Forward);
+        let sequence = tag_frame_sequence(&forward, 3);
+        assert_eq!(tag_loop_starts(&forward, 3), vec![0, 4, 8]);
+        assert_eq!(sequence.len(), 12);
+
+        let ping_pong = tag(1, 4, AsepriteTagDirection::PingPong);
+        let sequence = tag_frame_sequence(&ping_pong, 3);
+        let starts = tag_loop_starts(&ping_pong, 3);
+        assert_eq!(starts, vec![0, 4, 7]);
+        // each loop after the first starts just past the frame it turned on
+        assert_eq!(sequence[starts[1] - 1], 4);
+        assert_eq!(sequence[starts[2] - 1], 1);
+
+        for direction in [AsepriteTagDirection::PingPong, AsepriteTagDirection::PingPongReverse, AsepriteTagDirection::Forward] {
+            let single = tag(2, 2, direction);
+            let sequence = tag_frame_sequence(&single, 3);
+            let starts = tag_loop_starts(&single, 3);
+            assert_eq!(starts.len(), 3);
+            assert!(starts.last().is_some_and(|x| *x < sequence.len()));
+        }
+    }
+
+    #[test]
+    fn cels_find_layers_wherever_their_chunks_are() {
+        // the cel on frame 0 is on a layer whose chunk only comes in frame 1
+        let data = file(32, &[
+            frame(&[layer("bg", 0), raw_cel(1, 0, 0, 2, 2, &[1, 2, 3, 4].repeat(4))]),
+            frame(&[raw_cel(0, 0, 0, 2, 2, &[5, 6, 7, 8].repeat(4)), layer("fg", 0), raw_cel(7, 0, 0, 1, 1, &[0; 4])]),
+        ]);
+        let sprite = LoadedSprite::from_aseprite("test.aseprite", aseprite::read_from_slice(&data).unwrap());
+
+        let names: Vec<&str> = sprite.loaded_layers.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["bg", "fg"]);
+        assert_eq!(sprite.layer_of(1), Some(1));
+        assert_eq!(sprite.layer_of(7), None);
+
+        // the cel on a layer that doesn't exist is left out with a warning
+        assert_eq!(sprite.loaded_cels.len(), 2);
+        assert!(sprite.warnings.iter().any(|x| x.contains("layer 7")));
+
+        let cel = sprite.loaded_cels.iter().find(|x| x.frame_index == 0).unwrap();
+        assert_eq!(cel.collision_bounds.y, -sprite.offset.y);
+
+        let (pixel, colour, _) = sprite.sample_pixel(Vector2 { x: 0.5, y: -sprite.offset.y + 0.5 }).unwrap();
+        assert_eq!((pixel.frame_index, pixel.layer_index), (0, 1));
+        assert_eq!(colour, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn cels_off_the_top_left_are_clipped() {
+        // a 6x6 cel at (-5, -5) only has its bottom right pixel on the 2x2 canvas
+        let pixels: Vec<u8> = (0..36u8).flat_map(|i| [i, i, i, 255]).collect();
+        let data = file(32, &[frame(&[layer("bg", 0), raw_cel(0, -5, -5, 6, 6, &pixels)])]);
+        let sprite = LoadedSprite::from_aseprite("test.aseprite", aseprite::read_from_slice(&data).unwrap());
+
+        let flat = sprite.flatten_frame(0);
+        assert_eq!(flat.len(), 2 * 2 * 4);
+        assert_eq!(flat[0..4], [35, 35, 35, 255]);
+        assert!(flat[4..].iter().all(|x| *x == 0));
+    }
+
+    #[test]
+    fn indexed_cels_go_through_the_palette() {
+        let colours = [[9, 9, 9, 255], [255, 0, 0, 255], [0, 255, 0, 128], [0, 0, 255, 255]];
+        let data = file(8, &[frame(&[palette(&colours), layer("bg", 0), raw_cel(0, 0, 0, 2, 2, &[0, 1, 2, 3])])]);
+        let sprite = LoadedSprite::from_aseprite("test.aseprite", aseprite::read_from_slice(&data).unwrap());
+
+        // index 0 is the header's transparent index, so its colour isn't used
+        assert_eq!(sprite.flatten_frame(0), [0, 0, 0, 0, 255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 255]);
+
+        let err = [ERR_COLOR.r, ERR_COLOR.g, ERR_COLOR.b, ERR_COLOR.a];
+        assert_eq!(indexed_to_rgba(&[3, 4], &colours, 0), [[0, 0, 255, 255], err].concat());
+    }
+
+    #[test]
+    fn boundaries_are_the_nearest_tag_start_either_way() {
+        let starts = [0, 4, 4, 9];
+        assert_eq!(next_boundary(&starts, 0, true), Some(4));
+        assert_eq!(next_boundary(&starts, 4, true), Some(9));
+        assert_eq!(next_boundary(&starts, 9, true), None);
+
+        // partway into a tag goes back to where it starts
+        assert_eq!(next_boundary(&starts, 6, false), Some(4));
+        assert_eq!(next_boundary(&starts, 4, false), Some(0));
+        assert_eq!(next_boundary(&starts, 0, false), None);
+        assert_eq!(next_boundary(&[], 3, true), None);
+    }
+
+    #[test]
+    fn reloads_say_what_changed() {
+        let old = SpriteSummary { frames: 12, layers: 3, tags: 2, width: 32, height: 32 };
+        assert_eq!(old.changes_from(&old), "nothing changed");
+
+        let new = SpriteSummary { frames: 16, layers: 4, ..old };
+        assert_eq!(new.changes_from(&old), "+1 layer, frames 12→16");
+
+        let new = SpriteSummary { layers: 1, tags: 1, width: 64, ..old };
+        assert_eq!(new.changes_from(&old), "-2 layers, -1 tag, canvas 32x32→64x32");
+    }
+
+    #[test]
+    fn zero_repeats_loop_forever_and_others_play_that_many_times() {
+        let forever = tag(1, 4, AsepriteTagDirection::Forward);
+        assert_eq!(tag_frame_sequence(&forever, forever.repeat_count), [1, 2, 3, 4]);
+        assert_eq!(forever.repeat_label(), ui_main::TAG_REPEAT_FOREVER);
+
+        let three = PreparedTag { repeat_count: 3, ..tag(1, 4, AsepriteTagDirection::Forward) };
+        assert_eq!(tag_frame_sequence(&three, three.repeat_count), [1, 2, 3, 4, 1, 2, 3, 4, 1, 2, 3, 4]);
+        assert_eq!(three.repeat_label(), "x3");
+    }
+
+    #[test]
+    fn tag_sequences_follow_their_direction() {
+        let forward = tag(1, 4, AsepriteTagDirection::Forward);
+        assert_eq!(tag_frame_sequence(&forward, 1), [1, 2, 3, 4]);
+        assert_eq!(tag_frame_sequence(&forward, 2), [1, 2, 3, 4, 1, 2, 3, 4]);
+
+        let reverse = tag(1, 4, AsepriteTagDirection::Reverse);
+        assert_eq!(tag_frame_sequence(&reverse, 1), [4, 3, 2, 1]);
+        assert_eq!(tag_frame_sequence(&reverse, 2), [4, 3, 2, 1, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn ping_pong_sequences_dont_play_the_turning_frames_twice() {
+        let ping_pong = tag(1, 4, AsepriteTagDirection::PingPong);
+        assert_eq!(tag_frame_sequence(&ping_pong, 0), [1, 2, 3, 4, 3, 2]);
+        assert_eq!(tag_frame_sequence(&ping_pong, 1), [1, 2, 3, 4]);
+        assert_eq!(tag_frame_sequence(&ping_pong, 2), [1, 2, 3, 4, 3, 2, 1]);
+        assert_eq!(tag_frame_sequence(&ping_pong, 3), [1, 2, 3, 4, 3, 2, 1, 2, 3, 4]);
+
+        let ping_pong_reverse = tag(1, 4, AsepriteTagDirection::PingPongReverse);
+        assert_eq!(tag_frame_sequence(&ping_pong_reverse, 0), [4, 3, 2, 1, 2, 3]);
+        assert_eq!(tag_frame_sequence(&ping_pong_reverse, 2), [4, 3, 2, 1, 2, 3, 4]);
+
+        // a single frame has nothing to turn around on, so it plays like a forward tag
+        let single = tag(2, 2, AsepriteTagDirection::PingPong);
+        assert_eq!(tag_frame_sequence(&single, 0), [2]);
+        assert_eq!(tag_frame_sequence(&single, 3), [2, 2, 2]);
+    }
 }
\ No newline at end of file
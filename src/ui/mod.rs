@@ -1,4 +1,12 @@
 mod loaded_aseprite;
+mod blend;
+mod gif;
 mod ui_traits;
 mod toast;
+mod config;
+mod layout;
+mod recent;
+mod playback;
+mod palette_file;
+pub mod headless;
 pub mod ui_main;
\ No newline at end of file
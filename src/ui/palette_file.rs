@@ -0,0 +1,77 @@
+/// Text formats a palette can be saved in for other tools to read.
+#[derive(Clone, Copy)]
+pub(crate) enum PaletteFormat {
+    /// GIMP's `.gpl`, which has room for each entry's name.
+    Gimp,
+    /// JASC's `.pal`, as read by Paint Shop Pro and a lot of pixel art tools.
+    /// It only has the colours.
+    Jasc,
+}
+
+impl PaletteFormat {
+    /// Gets the extension files in the format end with, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gimp => "gpl",
+            Self::Jasc => "pal",
+        }
+    }
+
+    /// Writes out a palette called `name`. Neither format has alpha, so it's
+    /// left off, and entries without a name of their own are left untitled.
+    pub fn write(self, name: &str, palette: &[[u8; 4]], names: &[Option<String>]) -> String {
+        match self {
+            Self::Gimp => {
+                let mut result = format!("GIMP Palette\nName: {name}\nColumns: 16\n#\n");
+
+                for (i, [r, g, b, _]) in palette.iter().enumerate() {
+                    // names run to the end of the line, so they can't have any
+                    // line breaks of their own
+                    let entry_name = names.get(i).and_then(|x| x.as_deref())
+                        .map(|x| x.replace(['\r', '\n'], " "))
+                        .unwrap_or_else(|| "Untitled".to_owned());
+
+                    result.push_str(&format!("{r:3} {g:3} {b:3}\t{entry_name}\n"));
+                }
+
+                result
+            },
+            Self::Jasc => {
+                let mut result = format!("JASC-PAL\r\n0100\r\n{}\r\n", palette.len());
+
+                for [r, g, b, _] in palette {
+                    result.push_str(&format!("{r} {g} {b}\r\n"));
+                }
+
+                result
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gimp_palettes_keep_names() {
+        let palette = [[255, 0, 0, 255], [0, 128, 7, 0]];
+        let names = [Some("red\nish".to_owned())];
+
+        assert_eq!(
+            PaletteFormat::Gimp.write("sprite", &palette, &names),
+            "GIMP Palette\nName: sprite\nColumns: 16\n#\n255   0   0\tred ish\n  0 128   7\tUntitled\n"
+        );
+    }
+
+    #[test]
+    fn jasc_palettes_are_just_the_colours() {
+        let palette = [[255, 0, 0, 255], [0, 128, 7, 0]];
+        let names = [Some("red".to_owned()), None];
+
+        assert_eq!(
+            PaletteFormat::Jasc.write("sprite", &palette, &names),
+            "JASC-PAL\r\n0100\r\n2\r\n255 0 0\r\n0 128 7\r\n"
+        );
+    }
+}
\ No newline at end of file
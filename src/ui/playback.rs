@@ -0,0 +1,272 @@
+use raylib::color::Color;
+
+/// Used for frames that say they last 0ms. Aseprite gives new frames 100ms.
+const FALLBACK_FRAME_DURATION: u16 = 100;
+/// How long the frame border flashes for when a loop of a tag ends, in milliseconds.
+const LOOP_FLASH_DURATION:     f32 = 250.0;
+
+/// Gets how long a frame is shown for in milliseconds, never 0.
+pub(crate) fn frame_duration(durations: &[u16], frame: usize) -> u16 {
+    match durations.get(frame).copied().unwrap_or(0) {
+        0 => FALLBACK_FRAME_DURATION,
+        n => n,
+    }
+}
+
+/// The opacity of the frames right next to the current one when onion skinning,
+/// the same as Aseprite's default.
+pub(crate) const ONION_SKIN_OPACITY:      u8 = 68;
+/// How much less opaque each frame further out than that is.
+pub(crate) const ONION_SKIN_OPACITY_STEP: u8 = 28;
+
+/// Settings for showing the frames around the current one under it in the
+/// preview. These stay the same between loaded sprites.
+#[derive(Clone, Copy)]
+pub(crate) struct OnionSkin {
+    pub enabled: bool,
+
+    /// How many frames before the current one are shown.
+    pub before: usize,
+    /// How many frames after the current one are shown.
+    pub after:  usize,
+
+    /// What the frames before the current one are tinted.
+    pub past_tint:   Color,
+    /// What the frames after the current one are tinted.
+    pub future_tint: Color,
+}
+
+impl Default for OnionSkin {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            before:  1,
+            after:   1,
+            past_tint:   Color::BLUE,
+            future_tint: Color::RED,
+        }
+    }
+}
+
+impl OnionSkin {
+    /// Gets how opaque a frame `distance` frames away from the current one is
+    /// drawn, fading out the further away it is.
+    pub fn opacity(distance: usize) -> u8 {
+        let fade = distance.saturating_sub(1).saturating_mul(ONION_SKIN_OPACITY_STEP as usize);
+        (ONION_SKIN_OPACITY as usize).saturating_sub(fade) as u8
+    }
+}
+
+/// Steps through a sprite's frames in time with how long each one lasts.
+#[derive(Default)]
+pub struct Playback {
+    pub playing: bool,
+    /// The tag being played, if only a tag's frames are.
+    pub tag:     Option<usize>,
+
+    /// The frames played in order.
+    sequence: Vec<usize>,
+    /// Goes back to the start of `sequence` after the last frame, otherwise
+    /// playback stops and holds on it.
+    looping:  bool,
+    /// Where in `sequence` playback is.
+    position: usize,
+    /// How long the current frame has been shown for, in milliseconds.
+    elapsed:  f32,
+
+    /// Where in `sequence` each loop of a tag with a set repeat count starts.
+    /// Empty when the frames are played over and over.
+    loop_starts: Vec<usize>,
+    /// How much longer the frame border flashes for after a loop ends, in
+    /// milliseconds.
+    flash:       f32,
+}
+
+impl Playback {
+    /// Sets up playback of every frame in order, paused on the first one.
+    pub fn all_frames(frame_count: usize) -> Self {
+        Self {
+            sequence: (0..frame_count).collect(),
+            looping:  true,
+            ..Default::default()
+        }
+    }
+
+    /// Sets up playback of a set sequence of frames, playing straight away.
+    pub fn sequence(sequence: Vec<usize>, looping: bool) -> Self {
+        Self {
+            playing: true,
+            sequence, looping,
+            ..Default::default()
+        }
+    }
+
+    /// Sets up playback of a tag's frames that stops at the end of its last loop,
+    /// playing straight away. `loop_starts` is where in `sequence` each loop
+    /// begins.
+    pub fn looped(sequence: Vec<usize>, loop_starts: Vec<usize>) -> Self {
+        Self {
+            loop_starts,
+            ..Self::sequence(sequence, false)
+        }
+    }
+
+    /// Gets which loop of a tag playback is on, counting from 1, and how many
+    /// it has. `None` if the frames are played over and over.
+    pub fn current_loop(&self) -> Option<(usize, usize)> {
+        if self.loop_starts.is_empty() {
+            return None;
+        }
+
+        let current = self.loop_starts.iter().filter(|x| **x <= self.position).count();
+        Some((current.max(1), self.loop_starts.len()))
+    }
+
+    /// Checks if the frame border should be flashing for the end of a loop.
+    pub fn is_flashing(&self) -> bool {
+        self.flash > 0.0
+    }
+
+    /// Checks if playback has stopped for good on the last frame.
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.position + 1 >= self.sequence.len()
+    }
+
+    /// Plays or pauses, starting over if playback had already finished.
+    pub fn toggle(&mut self) {
+        if !self.playing && self.is_finished() {
+            self.position = 0;
+            self.elapsed = 0.0;
+        }
+
+        self.playing ^= true;
+    }
+
+    /// Jumps to a frame and pauses on it. Returns false, without changing
+    /// anything, if the frame isn't one being played.
+    pub fn seek(&mut self, frame: usize) -> bool {
+        let Some(position) = self.sequence.iter().position(|x| *x == frame) else { return false };
+
+        self.position = position;
+        self.elapsed = 0.0;
+        self.playing = false;
+        true
+    }
+
+    /// Steps one frame forwards or backwards through the frames being played and
+    /// pauses on it, going round to the other end past either end.
+    pub fn step_frame(&mut self, forward: bool) {
+        let len = self.sequence.len();
+        if len == 0 {
+            return;
+        }
+
+        self.position = match forward {
+            true  => (self.position + 1) % len,
+            false => (self.position + len - 1) % len,
+        };
+        self.elapsed = 0.0;
+        self.playing = false;
+    }
+
+    /// Gets the frame being shown.
+    pub fn frame(&self) -> usize {
+        self.sequence.get(self.position).copied().unwrap_or(0)
+    }
+
+    /// Moves playback on by `delta` seconds, going past as many frames as that
+    /// covers. Returns if it ended up on a different frame.
+    pub fn step(&mut self, delta: f32, durations: &[u16]) -> bool {
+        // keeps fading after the last loop, once playback has stopped
+        self.flash = (self.flash - delta * 1000.0).max(0.0);
+
+        if !self.playing || self.sequence.is_empty() {
+            return false;
+        }
+
+        let before = self.frame();
+        self.elapsed += delta * 1000.0;
+
+        loop {
+            let duration = frame_duration(durations, self.frame()) as f32;
+            if self.elapsed < duration {
+                break;
+            }
+
+            if self.is_finished() {
+                self.playing = false;
+                self.elapsed = 0.0;
+                if !self.loop_starts.is_empty() {
+                    self.flash = LOOP_FLASH_DURATION;
+                }
+                break;
+            }
+
+            self.elapsed -= duration;
+            self.position = (self.position + 1) % self.sequence.len();
+
+            if self.loop_starts.contains(&self.position) {
+                self.flash = LOOP_FLASH_DURATION;
+            }
+        }
+
+        self.frame() != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_loops_and_stops_after_the_last() {
+        // two frames, three loops, with the second frame lasting longer
+        let durations = [100, 400];
+        let mut playback = Playback::looped(vec![0, 1, 0, 1, 0, 1], vec![0, 2, 4]);
+        assert_eq!(playback.current_loop(), Some((1, 3)));
+
+        playback.step(0.5, &durations);
+        assert_eq!(playback.current_loop(), Some((2, 3)));
+        assert!(playback.is_flashing());
+
+        playback.step(0.1, &durations);
+        playback.step(0.3, &durations);
+        assert_eq!(playback.current_loop(), Some((2, 3)));
+        assert!(!playback.is_flashing());
+
+        playback.step(0.1, &durations);
+        assert_eq!(playback.current_loop(), Some((3, 3)));
+
+        playback.step(0.5, &durations);
+        assert!(playback.is_finished());
+        assert!(!playback.playing);
+        assert!(playback.is_flashing());
+        assert_eq!(playback.frame(), 1);
+    }
+
+    #[test]
+    fn stepping_frames_wraps_around_and_pauses() {
+        let mut playback = Playback::sequence(vec![3, 4, 5], true);
+        playback.step_frame(false);
+        assert_eq!(playback.frame(), 5);
+        assert!(!playback.playing);
+
+        playback.step_frame(true);
+        playback.step_frame(true);
+        assert_eq!(playback.frame(), 4);
+
+        let mut empty = Playback::all_frames(0);
+        empty.step_frame(true);
+        assert_eq!(empty.frame(), 0);
+    }
+
+    #[test]
+    fn endless_playback_has_no_loop_count() {
+        let mut playback = Playback::sequence(vec![0, 1, 2], true);
+        playback.step(1.0, &[100, 100, 100]);
+
+        assert_eq!(playback.current_loop(), None);
+        assert!(!playback.is_flashing());
+        assert!(playback.playing);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,68 @@
+use std::{fs, io, path::{self, PathBuf}};
+
+use super::config::Config;
+
+/// The name of the file the recently opened paths are kept in, next to the
+/// config file.
+const RECENT_FILE_NAME: &str = "recent.txt";
+
+/// How many of the most recently opened paths are remembered.
+const MAX_RECENT_FILES: usize = 10;
+
+/// The paths of the files opened most recently, newest first. They're kept one
+/// to a line in a file in the config directory so they're there next time.
+#[derive(Default)]
+pub(crate) struct RecentFiles {
+    pub paths: Vec<String>,
+}
+
+impl RecentFiles {
+    /// Gets where the list is kept, if there's anywhere sensible for it.
+    fn path() -> Option<PathBuf> {
+        Config::dir().map(|x| x.join(RECENT_FILE_NAME))
+    }
+
+    /// Reads the list back, which is empty if it's never been saved.
+    pub fn load() -> Self {
+        let text = Self::path().and_then(|x| fs::read_to_string(x).ok()).unwrap_or_default();
+
+        Self {
+            paths: text.lines()
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty())
+                .take(MAX_RECENT_FILES)
+                .map(|x| x.to_owned())
+                .collect()
+        }
+    }
+
+    /// Moves a path to the front of the list, adding it if it isn't there yet,
+    /// and saves the list.
+    pub fn push(&mut self, fname: &str) -> io::Result<()> {
+        // kept absolute so they still open wherever the viewer's started from
+        let full = path::absolute(fname).map_or(fname.to_owned(), |x| x.to_string_lossy().into_owned());
+
+        self.paths.retain(|x| *x != full);
+        self.paths.insert(0, full);
+        self.paths.truncate(MAX_RECENT_FILES);
+
+        self.save()
+    }
+
+    /// Takes a path off the list and saves it, for files that aren't there
+    /// anymore.
+    pub fn remove(&mut self, fname: &str) -> io::Result<()> {
+        self.paths.retain(|x| x != fname);
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::path() else { return Ok(()) };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, self.paths.join("\n"))
+    }
+}
\ No newline at end of file
@@ -1,13 +1,131 @@
-use raylib::{color::Color, ffi::MouseButton, math::Rectangle, prelude::{RaylibDraw, RaylibDrawHandle}, RaylibHandle};
+use raylib::{color::Color, ffi::{self, MouseButton}, math::{Rectangle, Vector2}, prelude::{RaylibDraw, RaylibDrawHandle}, RaylibHandle};
 
-use super::{ui_main::FONT_SIZE_REG, ui_traits::ExpirableElement};
+use std::{sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc}};
+
+use super::{ui_main::FONT_SIZE_REG, ui_traits::{ExpirableElement, Overlay, OverlayCorner}};
+
+/// The widest a toast's text gets before it wraps onto another line.
+const TOAST_MAX_TEXT_WIDTH: i32 = 360;
+/// The space between the wrapped lines of a toast.
+const TOAST_LINE_SPACING:   i32 = 2;
+
+/// How serious a toast is, which picks its colour and icon.
+#[derive(Clone, Copy)]
+pub(crate) enum ToastKind {
+    Info,
+    Warning,
+    Error,
+}
+
+impl ToastKind {
+    fn background(self) -> Color {
+        match self {
+            Self::Info    => Color::BLACK,
+            Self::Warning => Color::BROWN,
+            Self::Error   => Color::MAROON,
+        }
+    }
+
+    /// The raygui icon shown before the text, like `#191#` in gui text.
+    fn icon(self) -> i32 {
+        match self {
+            Self::Info    => 191,
+            Self::Warning => 205,
+            Self::Error   => 113,
+        }
+    }
+}
+
+/// Breaks text into lines no wider than `max_width` as drawn, between words
+/// where it can. Words too wide for a line of their own are broken up wherever
+/// they have to be.
+fn wrap_text(d: &RaylibDrawHandle, text: &str, max_width: i32) -> Vec<String> {
+    let fits = |x: &str| d.measure_text(x, FONT_SIZE_REG) <= max_width;
+    let mut lines = vec![];
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+
+        for word in paragraph.split(' ') {
+            let joined = if line.is_empty() { word.to_owned() } else { format!("{line} {word}") };
+            if line.is_empty() || fits(&joined) {
+                line = joined;
+            } else {
+                lines.push(std::mem::replace(&mut line, word.to_owned()));
+            }
+
+            while !fits(&line) {
+                // always at least a character, so a single wide one can't get stuck
+                let first = line.char_indices().nth(1).map_or(line.len(), |(i, _)| i);
+                let split = line.char_indices().map(|(i, _)| i).rev()
+                    .find(|i| *i >= first && fits(&line[..*i]))
+                    .unwrap_or(first);
+
+                if split >= line.len() {
+                    break;
+                }
+
+                lines.push(line[..split].to_owned());
+                line = line[split..].to_owned();
+            }
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// How far a job running on another thread has got, shared between it and the
+/// toast showing it.
+#[derive(Clone, Default)]
+pub(crate) struct Progress {
+    done:     Arc<AtomicUsize>,
+    total:    Arc<AtomicUsize>,
+    finished: Arc<AtomicBool>,
+}
+
+impl Progress {
+    /// Says how many steps of the job are done out of how many there are.
+    pub fn set(&self, done: usize, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+        self.done.store(done, Ordering::Relaxed);
+    }
+
+    /// Gets how much of the job is done, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        let total = self.total.load(Ordering::Relaxed);
+        match total {
+            0 => 0.,
+            _ => (self.done.load(Ordering::Relaxed) as f32 / total as f32).min(1.),
+        }
+    }
+
+    /// Says the job is over, one way or another, which gets rid of its toast.
+    pub fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+}
 
 pub struct Toast {
     text:        String,
+    /// What `lines` were wrapped from, which is `text` with the progress after
+    /// it for progress toasts.
+    wrapped:     String,
+    /// `text` wrapped to fit, along with the width it was wrapped to.
+    lines:       Vec<String>,
+    wrapped_at:  i32,
     timer:       i32,
     start_timer: i32,
 
-    background: Color,
+    kind: ToastKind,
+    /// Shown as a bar filling up instead of the timer running down, with the
+    /// toast staying until the job's finished.
+    progress: Option<Progress>,
 
     bounds:   Rectangle,
     immortal: bool
@@ -15,44 +133,121 @@ pub struct Toast {
 
 impl ExpirableElement for Toast {
     fn is_alive(&self) -> bool {
-        self.immortal || self.timer > 0
+        (self.immortal || self.timer > 0) && !self.progress.as_ref().is_some_and(|x| x.is_finished())
     }
 }
 
 impl Toast {
-    pub fn new(text: &str, timer: i32) -> Self {
+    pub fn new(text: &str, timer: i32, kind: ToastKind) -> Self {
         Self {
             text: String::from(text),
+            wrapped: String::new(),
+            lines: vec![],
+            wrapped_at: 0,
             start_timer: timer,
             timer,
             bounds: Rectangle { ..Default::default() },
-            background: Color{a: 192, ..Color::BLACK},
+            kind,
+            progress: None,
             immortal: false
         }
     }
 
-    pub fn new_ex(text: &str, timer: i32, background: Color) -> Self {
-        Self { background: Color{a: 192, ..background}, ..Self::new(text, timer) }
+    /// Makes an info toast that shows how far a job has got, and goes away once
+    /// it's finished.
+    pub fn progress(text: &str, progress: Progress) -> Self {
+        Self { progress: Some(progress), ..Self::new(text, 1, ToastKind::Info) }
+    }
+
+    pub fn info(text: &str, timer: i32) -> Self {
+        Self::new(text, timer, ToastKind::Info)
+    }
+
+    pub fn warn(text: &str, timer: i32) -> Self {
+        Self::new(text, timer, ToastKind::Warning)
     }
 
-    pub fn draw(&mut self, y_offset: f32, d: &mut RaylibDrawHandle, window_w: i32) {
-        let w = d.measure_text(&self.text, FONT_SIZE_REG) as f32;
+    pub fn error(text: &str, timer: i32) -> Self {
+        Self::new(text, timer, ToastKind::Error)
+    }
+
+    /// Gets rid of the toast, pinned or not.
+    fn close(&mut self) {
+        self.immortal = false;
+        self.timer = 0;
+    }
+
+    /// Gets where the `x` that closes the toast is, in the padding on its right.
+    fn close_rect(&self) -> Rectangle {
+        Rectangle{ x: self.bounds.x + self.bounds.width - 12., width: 12., ..self.bounds }
+    }
+}
+
+impl Overlay for Toast {
+    /// Draws the toast `offset` pixels along from `corner` of `area`, which is
+    /// the part of the window toasts can go in. Text too wide for it is wrapped
+    /// onto more lines.
+    fn draw(&mut self, offset: f32, d: &mut RaylibDrawHandle, area: Rectangle, corner: OverlayCorner) {
         let padding = 6.;
+        let icon_size = 16.;
+
+        let max_width = TOAST_MAX_TEXT_WIDTH.min((area.width - icon_size - padding * 4.) as i32).max(FONT_SIZE_REG);
+        let text = match &self.progress {
+            Some(progress) => format!("{} {}%", self.text, (progress.fraction() * 100.) as i32),
+            None           => self.text.clone(),
+        };
+
+        if self.wrapped_at != max_width || self.wrapped != text {
+            self.lines = wrap_text(d, &text, max_width);
+            self.wrapped_at = max_width;
+            self.wrapped = text;
+        }
+
+        let line_height = FONT_SIZE_REG + TOAST_LINE_SPACING;
+        let w = self.lines.iter().map(|x| d.measure_text(x, FONT_SIZE_REG)).max().unwrap_or(0) as f32;
+        let h = (line_height * self.lines.len() as i32 - TOAST_LINE_SPACING).max(FONT_SIZE_REG) as f32;
+        let (width, height) = (w + icon_size + padding * 4., h + padding * 2.);
+
         self.bounds = Rectangle{
-            x: window_w as f32 - (padding * 4.) - w - 1.,
-            y: y_offset + 1.,
-            width: w + padding * 4.,
-            height: 10. + padding * 2.
+            x: if corner.is_left() { area.x + 1. } else { area.x + area.width - width - 1. },
+            y: if corner.is_top() { area.y + offset + 1. } else { area.y + area.height - offset - height - 1. },
+            width, height
         };
 
-        d.draw_rectangle_rec(self.bounds, self.background);
-        d.draw_text(&self.text, (self.bounds.x + padding * 2.) as i32, (self.bounds.y + padding) as i32, FONT_SIZE_REG, Color::WHITE);
+        d.draw_rectangle_rec(self.bounds, Color{a: 192, ..self.kind.background()});
+        for (i, line) in self.lines.iter().enumerate() {
+            let y = self.bounds.y + padding + (line_height * i as i32) as f32;
+            d.draw_text(line, (self.bounds.x + icon_size + padding * 2.) as i32, y as i32, FONT_SIZE_REG, Color::WHITE);
+        }
+
+        // lined up with the first line
+        unsafe {
+            ffi::GuiDrawIcon(
+                self.kind.icon(),
+                (self.bounds.x + padding) as i32, (self.bounds.y + (FONT_SIZE_REG as f32 + padding * 2. - icon_size) / 2.) as i32,
+                1,
+                Color::WHITE.into()
+            );
+        }
+
+        if self.contains(d.get_mouse_position()) {
+            let close = self.close_rect();
+            let colour = if close.check_collision_point_rec(d.get_mouse_position()) { Color::WHITE } else { Color::GRAY };
+            d.draw_text("x", (close.x + 3.) as i32, (self.bounds.y + padding) as i32, FONT_SIZE_REG, colour);
+        }
+
+        if self.immortal {
+            d.draw_rectangle_lines_ex(self.bounds, 1., Color::WHITESMOKE);
+        } else {
+            let filled = match &self.progress {
+                Some(progress) => progress.fraction(),
+                None           => self.timer as f32 / self.start_timer as f32,
+            };
 
-        if !self.immortal {
             d.draw_rectangle_rec(Rectangle{
                 x: self.bounds.x + 1.,
                 y: self.bounds.y + self.bounds.height - 3.,
-                width: self.bounds.width * (self.timer as f32 / self.start_timer as f32) - 2.,
+                width: (self.bounds.width * filled - 2.).max(0.),
                 height: 2.
             }, Color::WHITESMOKE);
 
@@ -65,19 +260,37 @@ impl Toast {
         }
     }
 
-    pub fn step(&mut self, rl: &RaylibHandle) {
-        self.timer -= 1;
+    /// Counts the toast down, holding it while it's hovered. Clicking it pins
+    /// it so it stays until it's closed, or unpins it, and right clicking it or
+    /// clicking the `x` closes it.
+    fn step(&mut self, rl: &RaylibHandle) {
+        // progress toasts wait for their job instead
+        if self.progress.is_none() {
+            self.timer -= 1;
+        }
         
-        if self.bounds.check_collision_point_rec(rl.get_mouse_position()) {
+        let mouse = rl.get_mouse_position();
+        if self.contains(mouse) {
             self.timer = self.start_timer;
-            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
-                self.immortal = false;
-                self.timer = 0;
+
+            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
+                self.close();
+            } else if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+                if self.close_rect().check_collision_point_rec(mouse) {
+                    self.close();
+                } else {
+                    self.immortal ^= true;
+                }
             }
         }
     }
 
-    pub fn height(&self) -> f32 {
+    /// Checks if a point on screen is over the toast, as of when it was last drawn.
+    fn contains(&self, point: Vector2) -> bool {
+        self.bounds.check_collision_point_rec(point)
+    }
+
+    fn height(&self) -> f32 {
         self.bounds.height
     }
 }
\ No newline at end of file
@@ -1,20 +1,43 @@
-use std::io::{stderr, Write};
+use std::ffi::CString;
+use std::io::{self, stderr, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread::{self, JoinHandle};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 
 use raylib::prelude::*;
 use raylib::{color::Color, math::Vector2};
 
-use crate::ase::aseprite::AsepriteError;
+use crate::ase::aseprite::{AsepriteBlendMode, AsepriteError};
 
-use super::loaded_aseprite::{LoadedSprite, GAP};
-use super::toast::Toast;
-use super::ui_traits::ExpirableElement;
+use super::config::Config;
+use super::layout::{Layout, MIN_WINDOW_H, MIN_WINDOW_W};
+use super::palette_file::PaletteFormat;
+use super::loaded_aseprite::{draw_label, DrawOptions, LoadedSprite, PreparedTileset, SheetLayout, GAP, TILESET_COLUMNS};
+use super::playback::{frame_duration, OnionSkin, Playback};
+use super::recent::RecentFiles;
+use super::toast::{Progress, Toast};
+use super::ui_traits::{ExpirableElement, Overlay, OverlayCorner};
 
 const MAX_ZOOM_OUT:    f32 = 20.00;
 const MAX_ZOOM_IN:     f32 =  0.10;
 const ZOOM_LERP_SPEED: f32 =  0.4;
+/// How close the zoom has to get to where it's going to count as there.
+const ZOOM_SETTLED:    f32 =  0.001;
+/// How much of the window the grid takes up when zoomed to fit.
+const FIT_ZOOM_MARGIN: f32 =  0.9;
 
 const SCROLL_SENSITIVITY: f32 = 10.0;
 
+/// How far the arrow keys and WASD pan the view a second, in screen pixels.
+const KEY_PAN_SPEED:  f32 = 600.0;
+/// How much each press of + and - zooms in or out by.
+const KEY_ZOOM_STEP:  f32 = 1.25;
+
+/// Enables actions only meant for tracking down bugs, like copying cels out.
+const DEBUG_ACTIONS: bool = cfg!(debug_assertions);
+
 pub(crate) const FONT_SIZE_REG: i32 = 10;
 pub(crate) const FONT_SIZE_BIG: i32 = FONT_SIZE_REG * 2;
 
@@ -26,7 +49,66 @@ const LAYER_RESIZE_ICON_SPREAD: f32   = 8.0;
 /// The colour of the resizing indicator and arrows for the layer list.
 const LAYER_RESIZE_COLOUR:      Color = Color::ORANGERED;
 
-const TOAST_COLOR_ERROR: Color = Color::MAROON;
+/// The backing colour of the tooltips shown over the bottom bar.
+const TOOLTIP_BACKGROUND: Color = Color{r: 0, g: 0, b: 0, a: 224};
+/// How far the hovered cel's details are drawn from the mouse.
+const CEL_TOOLTIP_OFFSET: i32   = 16;
+
+/// The size of the panels showing a pinned cel's details.
+const PINNED_PANEL_W: f32 = 160.0;
+const PINNED_PANEL_H: f32 = 90.0;
+
+/// How tall the strip of open sprites along the top of the window is.
+const TAB_STRIP_HEIGHT: f32 = 24.0;
+
+/// The most bytes the layer filter can hold, including the null at the end.
+const LAYER_FILTER_LEN:    usize = 64;
+/// How tall the layer filter above the layer list is.
+const LAYER_FILTER_HEIGHT: f32   = 24.0;
+
+/// How many swatches go in each row of the palette panel, like Aseprite's.
+const PALETTE_COLUMNS:     usize = 16;
+/// The size of each swatch in the palette panel.
+const PALETTE_SWATCH_SIZE: f32 = 10.0;
+/// Marks the palette entry that's transparent in indexed sprites.
+const PALETTE_TRANSPARENT_COLOR: Color = Color::RED;
+/// How tall the buttons under the palette for saving it are.
+const PALETTE_BUTTON_HEIGHT:     f32   = 20.0;
+
+/// The most room each tile takes up in the tileset panel, they're shrunk down
+/// from this to fit tall tilesets on screen.
+const TILESET_CELL_SIZE:      f32 = 32.0;
+/// The smallest tiles in the tileset panel get before they're too small to
+/// have their number written on them.
+const TILESET_LABEL_MIN_CELL: f32 = 20.0;
+
+/// How wide the list of recently opened files is.
+const RECENT_MENU_W: f32 = 320.0;
+
+/// The size of the panel the composited frame is played back in.
+const PREVIEW_PANEL_W: f32 = 160.0;
+const PREVIEW_PANEL_H: f32 = 184.0;
+/// How far the shadow under the composited frame is dropped for each time it's
+/// scaled up, kept between a couple of pixels and the most below.
+const PREVIEW_SHADOW_STEP:   f32   = 0.5;
+const PREVIEW_SHADOW_MAX:    f32   = 8.0;
+/// How many times the shadow is drawn, fading out, to soften its edges.
+const PREVIEW_SHADOW_LAYERS: u8    = 3;
+/// Drawn around the composited frame.
+const PREVIEW_BORDER_COLOR:  Color = Color{r: 255, g: 255, b: 255, a: 64};
+/// How many composited frames the preview keeps uploaded, the one shown
+/// longest ago is let go first.
+const PREVIEW_CACHE_FRAMES:  usize = 32;
+
+/// Where the timeline scrubber starts in the bottom bar, past the pixel colour.
+const SCRUBBER_X:         f32 = 824.0;
+/// The scrubber isn't shown when the window leaves it narrower than this.
+const SCRUBBER_MIN_WIDTH: f32 = 60.0;
+/// The least space between the scrubber's ticks, frames are skipped to keep it.
+const SCRUBBER_TICK_GAP:  f32 = 4.0;
+
+/// How many toasts are shown at once by default, the rest wait for them to go.
+const MAX_VISIBLE_TOASTS:  usize = 5;
 
 // struct Part {
 //     pos: Vector2,
@@ -37,19 +119,73 @@ pub(crate) const TAG_DIRECTION_FORWARD:      &str = "->";
 pub(crate) const TAG_DIRECTION_REVERSE:      &str = "<-";
 pub(crate) const TAG_DIRECTION_FORWARD_PONG: &str = "->, <-";
 pub(crate) const TAG_DIRECTION_REVERSE_PONG: &str = "<-, ->";
+/// Shown in place of a repeat count for tags that loop forever. The default font
+/// has no infinity sign.
+pub(crate) const TAG_REPEAT_FOREVER:         &str = "inf";
 
 #[derive(Default)]
 pub struct UIState {
-    loaded_sprite: Option<LoadedSprite>,
-    toasts:        Vec<Toast>,
+    /// Every open sprite, each in its own tab.
+    sprites:       Vec<LoadedSprite>,
+    /// Which of `sprites` is being shown.
+    active_sprite: usize,
+    /// Toasts and anything else drawn over the window for a while.
+    overlays:      Vec<Box<dyn Overlay>>,
+    /// How many of `overlays` are shown at once. The ones after them are queued,
+    /// with their timers held until they come up.
+    max_toasts:    usize,
+    /// Which corner of the window toasts stack up from.
+    toast_corner:  OverlayCorner,
+    /// What the window was last titled, so it's only set when it changes.
+    window_title:  String,
 
     desired_zoom: f32,
     fit_zoom:     f32,
+    /// The point on screen that zooming with the wheel is centred on, until the
+    /// zoom settles.
+    zoom_anchor:  Option<Vector2>,
+    /// How zoomed in the view was when the viewer was last closed, used for
+    /// the first sprite opened instead of fitting it to the window.
+    restored_zoom: Option<f32>,
 
     desired_position: Vector2,
     default_position: Vector2,
 
     show_zoom_reset: bool,
+    /// Shows the choice of layouts for exporting a sprite sheet.
+    show_sheet_menu: bool,
+
+    recent:           RecentFiles,
+    show_recent_menu: bool,
+    /// A file picked from the recent files, opened at the start of the next frame.
+    recent_picked:    Option<String>,
+
+    draw_options: DrawOptions,
+
+    /// Shows the bottom bar and panels, turned off for a clean view of the sprite.
+    chrome_visible: bool,
+
+    /// Cels picked out by clicking on them, which keep their details shown.
+    pinned_cels: Vec<usize>,
+
+    show_palette: bool,
+    /// Only shown for sprites that have a tileset, see `tileset_panel_shown`.
+    show_tilesets: bool,
+    /// Shows the colour of the pixel under the mouse in the bottom bar.
+    eyedropper:   bool,
+
+    playback:     Playback,
+    /// Dragging the playhead along the scrubber, which keeps going off it until
+    /// the mouse is let go.
+    scrubbing:    bool,
+    show_preview: bool,
+    /// The composited frames shown in the preview, along with which frame each
+    /// is, with the one shown last at the end.
+    preview:      Vec<(usize, PreviewFrame)>,
+    /// Uploading a preview frame failed, so they're all drawn from their pixels
+    /// from then on instead of trying again each frame.
+    preview_software: bool,
+    onion_skin:   OnionSkin,
 
     pub window_w: i32,
     pub window_h: i32,
@@ -61,28 +197,96 @@ pub struct UIState {
     layer_list_resizing:    bool,
     layer_list_scroll:      i32,
     layer_list_active:      i32,
+    /// The text typed into the layer filter, kept null terminated for raygui.
+    layer_filter:           Vec<u8>,
+    layer_filter_editing:   bool,
+    /// The blend mode dropdown in the layer properties is open.
+    blend_mode_editing:     bool,
+
+    /// The export running on another thread, if there is one.
+    export: Option<ExportJob>,
+
+    /// Opens sprites straight into the preview and plays them, see
+    /// `Config::auto_play`.
+    auto_play: bool,
+}
+
+/// A composited frame kept for the preview.
+enum PreviewFrame {
+    Texture(Texture2D),
+    /// Its pixels, a row at a time, for when it couldn't be uploaded.
+    Pixels(Vec<Color>),
+}
+
+/// An export running on another thread, with a toast showing how far it's got.
+struct ExportJob {
+    handle:   JoinHandle<io::Result<()>>,
+    progress: Progress,
+    /// Where it's being saved to.
+    path:     PathBuf,
+    /// What the toast says once it's saved.
+    done:     String,
 }
 
-const ACCEPTED_TYPES: [&str; 2] = [".ase", ".aseprite"];
+/// The default extensions loaded as Aseprite files, see `Config::sprite_extensions`.
+pub(crate) const ACCEPTED_TYPES: [&str; 2] = [".ase", ".aseprite"];
+/// The default plain images that can be shown as a single frame sprite when
+/// dropped, see `Config::image_extensions`.
+pub(crate) const IMAGE_TYPES:    [&str; 4] = [".png", ".bmp", ".jpg", ".qoi"];
+
+/// What the window is titled before anything's loaded, and what it starts with
+/// after.
+const WINDOW_TITLE: &str = "viewer";
 
 pub(crate) const WINDOW_W: i32 = 1200;
 pub(crate) const WINDOW_H: i32 = 800;
 
-pub fn ui() {
-    let (mut rl, thread) = raylib::init()
-        .size(WINDOW_W, WINDOW_H)
-        .title("ui")
-        // .vsync()
-        .build();
+/// The narrowest the layer list can be dragged.
+pub(crate) const LAYER_LIST_MIN_WIDTH:  f32 = 90.0;
+/// How much of the window is always left past the layer list when dragging it.
+pub(crate) const LAYER_LIST_WINDOW_GAP: f32 = 128.0;
+
+/// Runs the viewer, opening `open` straight away if it's given.
+pub fn ui(open: Option<&str>) {
+    let config = Config::load();
+    let layout = Layout::load();
+
+    let (mut rl, thread) = {
+        let mut builder = raylib::init();
+        builder
+            .size(layout.window_w, layout.window_h)
+            .title(WINDOW_TITLE)
+            .resizable();
+
+        // vsync can only be asked for when the window is made
+        if config.vsync {
+            builder.vsync();
+        }
+
+        builder.build()
+    };
+
+    rl.set_window_min_size(MIN_WINDOW_W, MIN_WINDOW_H);
 
     let mut state = UIState{
         desired_zoom: 1.,
         desired_position: Vector2{x: 0., y: 0.},
-        window_w: WINDOW_W,
-        window_h: WINDOW_H,
+        window_w: layout.window_w,
+        window_h: layout.window_h,
+
+        window_title: WINDOW_TITLE.to_owned(),
+        max_toasts: MAX_VISIBLE_TOASTS,
+        // a corner picked in the config wins over the one they were in last time
+        toast_corner: config.toast_corner.unwrap_or(layout.toast_corner),
+        restored_zoom: layout.zoom.map(|x| x.clamp(MAX_ZOOM_IN, MAX_ZOOM_OUT)),
+        recent: RecentFiles::load(),
+        auto_play: config.auto_play,
 
         layer_list_active: -1,
-        layer_list_width: 120.0,
+        layer_list_visible: layout.layer_list_visible,
+        layer_list_width: layout.layer_list_width,
+        chrome_visible: true,
+        draw_options: DrawOptions{ pixel_grid: true, ..Default::default() },
         ..Default::default()
     };
 
@@ -101,7 +305,7 @@ pub fn ui() {
     //     );
     // }
 
-    rl.set_target_fps(60);
+    rl.set_target_fps(config.target_fps);
 
     let mut cam = Camera2D {
         zoom: 1.0,
@@ -109,85 +313,74 @@ pub fn ui() {
         ..Default::default()
     };
 
+    if let Some(fname) = open {
+        if !open_file(&mut rl, &mut state, &config, fname) {
+            state.overlays.push(Box::new(Toast::error(
+                format!("can't open {fname}, it isn't an aseprite file or an image").as_str(),
+                300
+            )));
+        }
+    }
+
     while !rl.window_should_close() {
         // update
         {
+            if rl.is_window_resized() {
+                state.window_w = rl.get_screen_width();
+                state.window_h = rl.get_screen_height();
+                cam.offset = Vector2{x: (state.window_w/2) as f32, y: (state.window_h/2) as f32};
+            }
+
             if rl.is_file_dropped() {
                 let list = rl.load_dropped_files();
 
-                'path: for fname in list.paths() {
-                    for ext in ACCEPTED_TYPES {
-                        if rl.is_file_extension(fname, ext) {
-
-                            match LoadedSprite::load(fname, &mut rl, &thread) {
-                                Ok(new) => {
-                                    state.layer_list_visible = state.loaded_sprite.is_none() || state.layer_list_visible;
-                                    
-                                    state.default_position = Vector2{
-                                        x: (new.frame_count + GAP as usize) as f32 * new.pixel_width as f32 * new.image_width as f32,
-                                        y: (new.loaded_layers.len() + GAP as usize) as f32 * new.pixel_height as f32 * new.image_height as f32,
-                                    };
-                                    
-                                    state.default_position *= 0.5;
-                                    state.default_position.y *= -1.0;
-                                    
-                                    state.desired_position = state.default_position;
-                                    
-                                    state.toasts.push(
-                                        Toast::new(
-                                            {
-                                                format!(
-                                                    "file loaded successfully; {} cels, {} frames, {} layers",
-                                                    new.loaded_cels.len(),
-                                                    new.loaded_layers.len(),
-                                                    new.frame_count,
-                                                ).as_str()
-                                            },
-                                            180
-                                        )
-                                    );
-
-                                    state.loaded_sprite = Some(new);
-        
-                                    break 'path
-                                },
-                                Err(e) => {
-                                    match e {
-                                        AsepriteError::RanOutAtHeader => {
-                                            state.toasts.push(Toast::new_ex(
-                                                "file error! too small to have header",
-                                                210,
-                                                TOAST_COLOR_ERROR
-                                            ));
-                                        },
-                                        AsepriteError::HeaderMagicMismatch | AsepriteError::FrameMagicMismatch => {
-                                            state.toasts.push(Toast::new_ex(
-                                                "file error! corrupted data!",
-                                                210,
-                                                TOAST_COLOR_ERROR
-                                            ));
-                                        },
-                                        AsepriteError::Other(error) => {
-                                            state.toasts.push(Toast::new_ex(
-                                                "unknown error, check error output for details",
-                                                240,
-                                                TOAST_COLOR_ERROR
-                                            ));
-
-                                            let _ = stderr().write_all(error.to_string().as_bytes());
-                                        },
-                                    }
-                                },
-                            };
-                        }
+                for fname in list.paths() {
+                    open_file(&mut rl, &mut state, &config, fname);
+                }
+            }
+
+            if let Some(fname) = state.recent_picked.take() {
+                if !Path::new(&fname).exists() {
+                    if let Err(e) = state.recent.remove(&fname) {
+                        state.overlays.push(Box::new(Toast::error(
+                            format!("couldn't save the recent files: {e}").as_str(),
+                            300
+                        )));
                     }
+
+                    state.overlays.push(Box::new(Toast::warn(
+                        format!("{fname} isn't there anymore, so it's been taken off the recent files").as_str(),
+                        300
+                    )));
+                } else if !open_file(&mut rl, &mut state, &config, &fname) {
+                    state.overlays.push(Box::new(Toast::error(
+                        format!("can't open {fname}, it isn't an aseprite file or an image").as_str(),
+                        300
+                    )));
                 }
             }
 
-            state.desired_zoom += rl.get_mouse_wheel_move() / SCROLL_SENSITIVITY;
+            let wheel = rl.get_mouse_wheel_move();
+            if wheel != 0.0 {
+                state.zoom_anchor = Some(rl.get_mouse_position());
+            }
+
+            state.desired_zoom += wheel / SCROLL_SENSITIVITY;
             state.desired_zoom = state.desired_zoom.clamp(MAX_ZOOM_IN, MAX_ZOOM_OUT);
             
+            let anchor = state.zoom_anchor.map(|x| (x, rl.get_screen_to_world2D(x, cam)));
             cam.zoom += (state.desired_zoom - cam.zoom) * ZOOM_LERP_SPEED;
+
+            // the world point under the mouse stays put on screen the whole way
+            // through the zoom
+            if let Some((screen, world)) = anchor {
+                cam.target = world - (screen - cam.offset) / cam.zoom;
+                state.desired_position = cam.target;
+
+                if (state.desired_zoom - cam.zoom).abs() < ZOOM_SETTLED {
+                    state.zoom_anchor = None;
+                }
+            }
             
             if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_RIGHT) {
                 state.desired_position -= rl.get_mouse_delta() / cam.zoom;
@@ -199,15 +392,59 @@ pub fn ui() {
                 
             cam.target += (state.desired_position - cam.target) * 0.8;
             
-            for toast in &mut state.toasts {
-                toast.step(&rl);
+            for overlay in state.overlays.iter_mut().take(state.max_toasts) {
+                overlay.step(&rl);
             }
 
-            if let Some(loaded) = &mut state.loaded_sprite {
+            if let Some(loaded) = state.sprites.get_mut(state.active_sprite) {
                 loaded.step(&mut rl, &cam);
             }
+
+            if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && !mouse_over_chrome(&state, rl.get_mouse_position()) {
+                let world = rl.get_screen_to_world2D(rl.get_mouse_position(), cam);
+
+                if let Some((loaded, tag)) = state.sprites.get(state.active_sprite).and_then(|x| x.tag_at(world).map(|tag| (x, tag))) {
+                    // clicking the tag that's playing goes back to playing everything
+                    state.playback = match state.playback.tag {
+                        Some(playing) if playing == tag => Playback { playing: true, ..Playback::all_frames(loaded.frame_count) },
+                        _ => loaded.tag_playback(tag).unwrap_or_default(),
+                    };
+                    state.show_preview = true;
+                } else if let Some(cel) = state.sprites.get(state.active_sprite).and_then(|x| x.hovered_cel()) {
+                    match state.pinned_cels.iter().position(|x| *x == cel) {
+                        Some(i) => { state.pinned_cels.remove(i); },
+                        None    => state.pinned_cels.push(cel),
+                    }
+                }
+            }
+
+            // typing into the layer filter shouldn't set anything off
+            if !state.layer_filter_editing {
+                keyboard_shortcuts(&mut rl, &mut state);
+
+                // the config says which reader the sprite was opened with
+                if rl.is_key_pressed(KeyboardKey::KEY_F5) {
+                    reload_sprite(&rl, &mut state, &config);
+                }
+            }
+
+            if let Some(loaded) = state.sprites.get(state.active_sprite) {
+                state.playback.step(rl.get_frame_time(), &loaded.frame_durations);
+            }
+
+            finish_export(&mut state);
+
+            if state.show_preview {
+                refresh_preview(&mut rl, &thread, &mut state);
+            }
             
-            state.toasts.retain(|i| i.is_alive());
+            state.overlays.retain(|i| i.is_alive());
+
+            let title = window_title(&state);
+            if title != state.window_title {
+                rl.set_window_title(&thread, &title);
+                state.window_title = title;
+            }
         }
 
         let top_left_cam = rl.get_screen_to_world2D(Vector2{x: 0.0, y: 0.0}, cam);
@@ -220,6 +457,25 @@ pub fn ui() {
             height: bot_rig_cam.y - top_left_cam.y,
         };
 
+        // cels are only uploaded once they come on screen, and tilesets once
+        // their panel is open
+        let show_tilesets = tileset_panel_shown(&state);
+        if let Some(spr) = state.sprites.get_mut(state.active_sprite) {
+            let failed_before = spr.failed_uploads;
+            spr.stream_textures(&mut rl, &thread, &visible_area);
+
+            if show_tilesets {
+                spr.upload_tilesets(&mut rl, &thread);
+            }
+
+            if spr.failed_uploads > failed_before {
+                state.overlays.push(Box::new(Toast::error(
+                    format!("{} cels couldn't be uploaded to the GPU and are left out", spr.failed_uploads).as_str(),
+                    300
+                )));
+            }
+        }
+
         // draw
         {
             let mut d = rl.begin_drawing(&thread);
@@ -247,8 +503,8 @@ pub fn ui() {
                 //     d.draw_circle(px as i32, py as i32, part.spd / cam.zoom, Color{ a: 40, ..Color::WHITE });
                 // }
                 
-                if let Some(ref mut spr) = state.loaded_sprite {
-                    spr.draw(&mut d, &cam, &visible_area);
+                if let Some(spr) = state.sprites.get_mut(state.active_sprite) {
+                    spr.draw(&mut d, &cam, &visible_area, &state.draw_options, &state.pinned_cels);
                 }
 
                 // d.draw_rectangle_lines_ex(visible_area, 4.0, Color::MAGENTA);
@@ -256,8 +512,24 @@ pub fn ui() {
 
             // draw screenspace
             {
-                match state.loaded_sprite {
-                    Some(_) => { layer_list(&mut d, &mut state); },
+                match state.sprites.get(state.active_sprite) {
+                    Some(_) => if state.chrome_visible {
+                        tab_strip(&mut d, &mut state);
+                        layer_list(&mut d, &mut state);
+                        pinned_panels(&mut d, &mut state);
+
+                        if state.show_preview {
+                            preview_panel(&mut d, &mut state);
+                        }
+
+                        if state.show_palette {
+                            palette_panel(&mut d, &mut state);
+                        }
+
+                        if tileset_panel_shown(&state) {
+                            tileset_panel(&mut d, &mut state);
+                        }
+                    },
                     None => {
                         let tx = "drag and drop an aseprite file..";
                         let tx_w = d.measure_text(tx,FONT_SIZE_BIG);
@@ -265,16 +537,52 @@ pub fn ui() {
                     },
                 };
 
+                if !state.sprites.is_empty() {
+                    cel_tooltip(&mut d, &state);
+                }
+
+                let area = toast_area(&state);
+                let corner = state.toast_corner;
+
                 let mut toast_y = 0.0;
-                for toast in &mut state.toasts {
-                    toast.draw( toast_y, &mut d, state.window_w);
-                    toast_y += toast.height() + 4.
+                for overlay in state.overlays.iter_mut().take(state.max_toasts) {
+                    overlay.draw( toast_y, &mut d, area, corner);
+                    toast_y += overlay.height() + 4.
                 }
 
-                bottom_bar(&mut d, &mut state, &cam);
+                let queued = state.overlays.len().saturating_sub(state.max_toasts);
+                if queued > 0 {
+                    let tx = format!("+{queued} more");
+                    let tx_w = d.measure_text(&tx, FONT_SIZE_REG);
+
+                    let x = if corner.is_left() { area.x + 8. } else { area.x + area.width - tx_w as f32 - 8. };
+                    let y = if corner.is_top() { area.y + toast_y + 2. } else { area.y + area.height - toast_y - FONT_SIZE_REG as f32 - 2. };
+                    d.draw_text(&tx, x as i32, y as i32, FONT_SIZE_REG, Color::GRAY);
+                }
+
+                if state.chrome_visible {
+                    bottom_bar(&mut d, &mut state, &cam);
+                }
             }
         }
     }
+
+    let layout = Layout {
+        window_w: state.window_w,
+        window_h: state.window_h,
+
+        layer_list_visible: state.layer_list_visible,
+        layer_list_width:   state.layer_list_width,
+
+        toast_corner: state.toast_corner,
+        // with nothing open the zoom is whatever it started at, so the next
+        // sprite may as well be fit to the window
+        zoom:         (!state.sprites.is_empty()).then_some(state.desired_zoom),
+    };
+
+    if let Err(e) = layout.save() {
+        eprintln!("couldn't save the layout: {e}");
+    }
 }
 
 // fn wrap(x: f32, lo: f32, hi: f32) -> f32 {
@@ -298,168 +606,1766 @@ pub fn ui() {
 //     (lo1 + (x - lo1) % size) - subby
 // }
 
-fn label_wrapper(d: &mut RaylibDrawHandle, bounds: impl Into<ffi::Rectangle>, text: &str, is_btn: bool) -> bool {
-    // let lbl_str = CString::new(text).unwrap();
-    // let lbl_str = lbl_str.as_c_str();
-    if is_btn {
-        d.gui_label_button(bounds, text)
-    } else {
-        d.gui_label(bounds, text)
-    }
-}
+/// Handles the single key shortcuts, see the README for what they all do.
+fn keyboard_shortcuts(rl: &mut RaylibHandle, state: &mut UIState) {
+    let ctrl = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+    let shift = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+    let any_down = |rl: &RaylibHandle, keys: &[KeyboardKey]| keys.iter().any(|x| rl.is_key_down(*x));
+    let any_pressed = |rl: &RaylibHandle, keys: &[KeyboardKey]| keys.iter().any(|x| rl.is_key_pressed(*x));
 
-fn layer_list(d: &mut RaylibDrawHandle, state: &mut UIState) {
-    if let Some(ref mut loaded) = state.loaded_sprite {
-        if state.layer_list_visible {
-            let dd_str = loaded.generate_layer_list();
+    // going by the zoom it's heading to so panning moves the same speed on
+    // screen however far in it is
+    let pan = KEY_PAN_SPEED * rl.get_frame_time() / state.desired_zoom;
+    let mut nudge = Vector2{x: 0., y: 0.};
 
-            let layer_list_rec = Rectangle{
-                x: 0.0,
-                y: 0.0,
-                width: state.layer_list_width,
-                height: WINDOW_H as f32,
-            };
+    if any_down(rl, &[KeyboardKey::KEY_LEFT, KeyboardKey::KEY_A]) {
+        nudge.x -= pan;
+    }
+    if any_down(rl, &[KeyboardKey::KEY_RIGHT, KeyboardKey::KEY_D]) {
+        nudge.x += pan;
+    }
+    if any_down(rl, &[KeyboardKey::KEY_UP, KeyboardKey::KEY_W]) {
+        nudge.y -= pan;
+    }
+    if !ctrl && any_down(rl, &[KeyboardKey::KEY_DOWN, KeyboardKey::KEY_S]) {
+        nudge.y += pan;
+    }
 
-            let _ = d.gui_list_view(
-                layer_list_rec, dd_str, &mut state.layer_list_scroll, &mut state.layer_list_active
-            );
+    if nudge.x != 0. || nudge.y != 0. {
+        state.zoom_anchor = None;
+        state.desired_position += nudge;
+    }
 
-            let resize_area = Rectangle{
-                x: layer_list_rec.width - 8.0,
-                width: 16.0,
-                ..layer_list_rec
-            };
+    if any_pressed(rl, &[KeyboardKey::KEY_EQUAL, KeyboardKey::KEY_KP_ADD]) {
+        state.zoom_anchor = None;
+        state.desired_zoom = (state.desired_zoom * KEY_ZOOM_STEP).clamp(MAX_ZOOM_IN, MAX_ZOOM_OUT);
+    }
 
-            let lo_resize_bound: f32 = 90.0;
-            let hi_resize_bound: f32 = d.get_screen_width() as f32 - 128.0;
+    if any_pressed(rl, &[KeyboardKey::KEY_MINUS, KeyboardKey::KEY_KP_SUBTRACT]) {
+        state.zoom_anchor = None;
+        state.desired_zoom = (state.desired_zoom / KEY_ZOOM_STEP).clamp(MAX_ZOOM_IN, MAX_ZOOM_OUT);
+    }
 
-            let m = d.get_mouse_position();
-            if resize_area.check_collision_point_rec(m) || state.layer_list_resizing {
-                d.draw_line_ex(Vector2{
-                    x: resize_area.x + resize_area.width / 2.,
-                    y: resize_area.y
-                }, Vector2{
-                    x: resize_area.x + resize_area.width / 2.,
-                    y: resize_area.y + resize_area.height
-                }, resize_area.width / 4., LAYER_RESIZE_COLOUR);
+    if any_pressed(rl, &[KeyboardKey::KEY_ZERO, KeyboardKey::KEY_KP_0]) {
+        state.zoom_anchor = None;
+        state.desired_zoom = 1.;
+    }
 
-                unsafe {
-                    let arrows_height = (resize_area.height / 2.) as i32;
+    if rl.is_key_pressed(KeyboardKey::KEY_F) && !state.sprites.is_empty() {
+        state.zoom_anchor = None;
+        state.desired_zoom = state.fit_zoom;
+    }
 
-                    if state.layer_list_width > lo_resize_bound {
-                        ffi::GuiDrawIcon(
-                            118,
-                            (resize_area.x - LAYER_RESIZE_ICON_SPREAD) as i32, arrows_height,
-                            1,
-                            LAYER_RESIZE_COLOUR.into()
-                        );
-                    }
-                    if state.layer_list_width < hi_resize_bound {
-                        ffi::GuiDrawIcon(
-                            119,
-                            (resize_area.x + LAYER_RESIZE_ICON_SPREAD) as i32, arrows_height,
-                            1,
-                            LAYER_RESIZE_COLOUR.into()
-                        );
-                    }
-                };
+    if rl.is_key_pressed(KeyboardKey::KEY_H) {
+        state.chrome_visible ^= true;
+    }
 
-                state.layer_list_resizing = d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT);
+    if rl.is_key_pressed(KeyboardKey::KEY_SPACE) && !state.sprites.is_empty() {
+        state.playback.toggle();
+        state.show_preview = true;
+    }
 
-                if state.layer_list_resizing {
-                    state.layer_list_width = m.x.clamp(lo_resize_bound, hi_resize_bound)
-                }
-            }
+    if rl.is_key_pressed(KeyboardKey::KEY_O) && !state.sprites.is_empty() {
+        state.onion_skin.enabled ^= true;
+        state.show_preview = true;
+        state.preview.clear();
+    }
 
-            if state.layer_list_active >= 0 && (state.layer_list_active as usize) < loaded.loaded_layers.len() {
-                let effective_layer_active = (loaded.loaded_layers.len() - 1) - (state.layer_list_active as usize);
-                let prop_bounds = Rectangle{
-                    x: state.layer_list_width + 8.,
-                    y: 8.0,
-                    width: 120.0,
-                    height: 130.0,
-                };
+    if rl.is_key_pressed(KeyboardKey::KEY_I) && !state.sprites.is_empty() {
+        state.eyedropper ^= true;
+    }
 
-                let layer_name = loaded.loaded_layers[effective_layer_active].name.as_str();
-                // let layer_name = layer_name.as_c_str();
+    if rl.is_key_pressed(KeyboardKey::KEY_G) {
+        state.draw_options.pixel_grid ^= true;
+    }
 
-                if d.gui_window_box(prop_bounds, layer_name) {
-                    state.layer_list_active = -1;
-                }
-                
-                let layer = &loaded.loaded_layers[effective_layer_active];
-                let properties_contents = format!(
-                    "Blend mode: {}\nOpacity: {}{}{}",
-                    layer.blend_mode, 
-                    layer.opacity, 
-                    if layer.background {"\nIs a background"} else {"\n"},
-                    if layer.is_reference {"\nIs a reference"} else {"\n"},
-                );
-                
-                d.gui_label(Rectangle{
-                    x: prop_bounds.x + 4.0,
-                    y: prop_bounds.y + 24.0,
-                    width: prop_bounds.width,
-                    height: 72.0
-                }, properties_contents.as_ref());
+    // S on its own pans
+    if ctrl && rl.is_key_pressed(KeyboardKey::KEY_S) {
+        save_frame_png(state);
+    }
 
-                if d.gui_check_box(Rectangle{
-                    x: prop_bounds.x + 8.0,
-                    y: prop_bounds.y + prop_bounds.height - 28.0,
-                    width: 24.0,
-                    height: 24.0,
-                }, "Visible", &mut loaded.loaded_layers[effective_layer_active].visible) {
-                    loaded.invalidate_layer_list();
+    for (key, forward) in [(KeyboardKey::KEY_COMMA, false), (KeyboardKey::KEY_PERIOD, true)] {
+        if !rl.is_key_pressed(key) {
+            continue;
+        }
+        let Some(loaded) = state.sprites.get(state.active_sprite) else { break };
+
+        if shift {
+            // tags outside the one being played go back to playing everything
+            if let Some(frame) = loaded.tag_boundary(state.playback.frame(), forward) {
+                if !state.playback.seek(frame) {
+                    state.playback = Playback::all_frames(loaded.frame_count);
+                    state.playback.seek(frame);
                 }
             }
+        } else {
+            state.playback.step_frame(forward);
         }
+
+        state.show_preview = true;
+    }
+
+    if ctrl && rl.is_key_pressed(KeyboardKey::KEY_TAB) && !state.sprites.is_empty() {
+        let count = state.sprites.len();
+        let next = match shift {
+            true  => (state.active_sprite + count - 1) % count,
+            false => (state.active_sprite + 1) % count,
+        };
+
+        switch_sprite(state, next);
+    }
+
+    if ctrl && rl.is_key_pressed(KeyboardKey::KEY_C) {
+        copy_frame(rl, state);
+    }
+
+    if DEBUG_ACTIONS && !ctrl && rl.is_key_pressed(KeyboardKey::KEY_C) {
+        copy_cel_data_uri(rl, state);
     }
 }
 
-fn bottom_bar(d: &mut RaylibDrawHandle, state: &mut UIState, cam: &Camera2D) {
-    d.gui_panel(Rectangle{x: 0., y: (state.window_h - 24) as f32, width: state.window_w as f32, height: 24.}, "");
+/// Opens a file in a new tab if it has one of the extensions sprites or images
+/// are opened from, letting the user know if it couldn't be loaded. Returns if
+/// it had one of them.
+fn open_file(rl: &mut RaylibHandle, state: &mut UIState, config: &Config, fname: &str) -> bool {
+    if config.sprite_extensions.iter().any(|ext| rl.is_file_extension(fname, ext)) {
+        match LoadedSprite::read(fname) {
+            Ok(new) => sprite_loaded(state, new),
+            Err(e)  => load_failed(state, e),
+        };
 
-    {
-        let bounds = Rectangle{x: 0., y: (state.window_h - 24) as f32, width: 24., height: 24.};
-        match state.loaded_sprite {
-            Some(_) => {
-                if label_wrapper(d, bounds, if state.layer_list_visible { "#197#" } else { "#196#" }, true) {
-                    state.layer_list_visible ^= true;
-                }
+        return true;
+    }
+
+    if config.image_extensions.iter().any(|ext| rl.is_file_extension(fname, ext)) {
+        match LoadedSprite::read_image(fname) {
+            Ok(new) => {
+                state.overlays.push(Box::new(Toast::warn(
+                    "not an aseprite file, showing it as a single image",
+                    240
+                )));
+
+                sprite_loaded(state, new);
             },
-            None => { label_wrapper(d, bounds,  "#196#", false); },
+            Err(e) => load_failed(state, e),
         };
+
+        return true;
+    }
+
+    false
+}
+
+/// Gets what the window should be titled, naming the sprite being shown and its
+/// size so windows can be told apart.
+fn window_title(state: &UIState) -> String {
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return WINDOW_TITLE.to_owned() };
+
+    format!("{WINDOW_TITLE} — {} ({}×{}, {} frame{})",
+        loaded.file_name(),
+        loaded.image_width, loaded.image_height,
+        loaded.frame_count, if loaded.frame_count == 1 { "" } else { "s" }
+    )
+}
+
+/// Opens a freshly loaded sprite in a new tab and shows it, framing the view
+/// around it and letting the user know about anything odd in it.
+fn sprite_loaded(state: &mut UIState, new: LoadedSprite) {
+    if let Err(e) = state.recent.push(&new.file_path) {
+        state.overlays.push(Box::new(Toast::error(
+            format!("couldn't save the recent files: {e}").as_str(),
+            300
+        )));
     }
 
-    if label_wrapper(d, Rectangle{x: 28., y: (state.window_h - 24) as f32, width: 90., height: 24.},
-                     format!("#43# {0:.2}%", cam.zoom * 100.).as_str(), true) {
-        state.show_zoom_reset ^= true;
+    state.overlays.push(Box::new(
+        Toast::info(
+            {
+                format!(
+                    "file loaded successfully; {} cels, {} frames, {} layers",
+                    new.loaded_cels.len(),
+                    new.loaded_layers.len(),
+                    new.frame_count,
+                ).as_str()
+            },
+            180
+        )
+    ));
+
+    let clashes = new.layer_name_clashes();
+    if !clashes.is_empty() {
+        state.overlays.push(Box::new(Toast::warn(
+            format!(
+                "duplicate layer names: {}",
+                clashes.iter()
+                    .map(|(name, count)| format!("{name} (x{count})"))
+                    .collect::<Vec<String>>().join(", ")
+            ).as_str(),
+            300
+        )));
     }
 
-    if state.show_zoom_reset {
-        let rect = Rectangle{x: 28., y: (state.window_h - 72) as f32, width: 65., height: 24.};
+    for warning in &new.warnings {
+        state.overlays.push(Box::new(Toast::warn(warning.as_str(), 300)));
+    }
 
-        if d.gui_button(rect, "#43# fit") {
-            state.desired_zoom = state.fit_zoom;
-            state.show_zoom_reset = false
-        }
-        if d.gui_button(Rectangle{y: rect.y + rect.height, ..rect}, "#42# 100%") {
-            state.desired_zoom = 1.;
-            state.show_zoom_reset = false
-        }
+    if new.has_layer_cycle() {
+        state.overlays.push(Box::new(Toast::warn(
+            "layer hierarchy loops back on itself, some names may be incomplete",
+            300
+        )));
     }
 
-    {
-        let recenter = Rectangle{x: 112., y: (state.window_h - 24) as f32, width: 90., height: 24.};
-        let t = format!("#48# {0:.0}, {1:.0}", cam.target.x, cam.target.y);
-        let recenter_tx = if recenter.check_collision_point_rec(d.get_mouse_position()) {
-            "#48# recenter?"
-        } else {
-            t.as_str() 
-        };
-        
-        if label_wrapper(d, recenter, recenter_tx, true) {
-            state.desired_position = state.default_position;
+    state.sprites.push(new);
+    switch_sprite(state, state.sprites.len() - 1);
+
+    state.desired_position = state.default_position;
+    // the first sprite goes back to how zoomed in the view was last time
+    state.desired_zoom = state.restored_zoom.take().unwrap_or(state.fit_zoom);
+
+    if state.auto_play {
+        let loaded = &state.sprites[state.active_sprite];
+        state.playback = loaded.tag_playback(0)
+            .unwrap_or_else(|| Playback { playing: true, ..Playback::all_frames(loaded.frame_count) });
+        state.show_preview = true;
+    }
+}
+
+/// Shows the sprite in another tab. The view stays where it is so the same spot
+/// can be compared between sprites, but anything picked out in the last one is
+/// let go of.
+fn switch_sprite(state: &mut UIState, index: usize) {
+    // only the sprite being shown keeps anything on the GPU
+    if index != state.active_sprite {
+        if let Some(old) = state.sprites.get_mut(state.active_sprite) {
+            old.release_textures();
         }
     }
+
+    state.zoom_anchor = None;
+    state.pinned_cels.clear();
+    state.preview.clear();
+    state.layer_list_active = -1;
+    state.layer_list_scroll = 0;
+
+    let Some(new) = state.sprites.get(index) else { return };
+    state.active_sprite = index;
+    state.playback = Playback::all_frames(new.frame_count);
+
+    state.default_position = Vector2{
+        x: (new.frame_count + GAP as usize) as f32 * new.pixel_width as f32 * new.image_width as f32,
+        y: (new.loaded_layers.len() + GAP as usize) as f32 * new.pixel_height as f32 * new.image_height as f32,
+    };
+    
+    state.default_position *= 0.5;
+    state.default_position.y *= -1.0;
+
+    // the grid is framed between the top of the window and the bottom bar, a
+    // sprite without any size just ends up as zoomed in as it goes
+    let (grid_w, grid_h) = (state.default_position.x * 2.0, -state.default_position.y * 2.0);
+    let fit = (state.window_w as f32 / grid_w).min((state.window_h - 24) as f32 / grid_h) * FIT_ZOOM_MARGIN;
+    state.fit_zoom = fit.clamp(MAX_ZOOM_IN, MAX_ZOOM_OUT);
+}
+
+/// Reads the sprite being shown from its file again, keeping the view where it
+/// is and saying what changed.
+fn reload_sprite(rl: &RaylibHandle, state: &mut UIState, config: &Config) {
+    let Some(old) = state.sprites.get(state.active_sprite) else { return };
+    let (fname, before) = (old.file_path.clone(), old.summary());
+
+    let reloaded = match config.sprite_extensions.iter().any(|ext| rl.is_file_extension(&fname, ext)) {
+        true  => LoadedSprite::read(&fname),
+        false => LoadedSprite::read_image(&fname),
+    };
+
+    let new = match reloaded {
+        Ok(new) => new,
+        Err(e)  => {
+            load_failed(state, e);
+            return;
+        },
+    };
+
+    let changes = new.summary().changes_from(&before);
+    for warning in &new.warnings {
+        state.overlays.push(Box::new(Toast::warn(warning.as_str(), 300)));
+    }
+
+    // the old sprite's textures go with it
+    state.sprites[state.active_sprite] = new;
+    switch_sprite(state, state.active_sprite);
+
+    state.overlays.push(Box::new(Toast::info(format!("reloaded {fname}: {changes}").as_str(), 180)));
+}
+
+/// Closes a sprite's tab, showing the one before it if it was being shown.
+fn close_sprite(state: &mut UIState, index: usize) {
+    if index >= state.sprites.len() {
+        return;
+    }
+
+    state.sprites.remove(index);
+
+    match index.cmp(&state.active_sprite) {
+        std::cmp::Ordering::Less    => state.active_sprite -= 1,
+        std::cmp::Ordering::Equal   => switch_sprite(state, index.saturating_sub(1)),
+        std::cmp::Ordering::Greater => (),
+    }
+}
+
+/// Shows the toast matching an error from loading a file.
+fn load_failed(state: &mut UIState, e: AsepriteError) {
+    match e {
+        AsepriteError::RanOutAtHeader => {
+            state.overlays.push(Box::new(Toast::error(
+                "file error! too small to have header",
+                210
+            )));
+        },
+        AsepriteError::HeaderMagicMismatch | AsepriteError::FrameMagicMismatch => {
+            state.overlays.push(Box::new(Toast::error(
+                "file error! corrupted data!",
+                210
+            )));
+        },
+        AsepriteError::Other(error) => {
+            state.overlays.push(Box::new(Toast::error(
+                "unknown error, check error output for details",
+                240
+            )));
+
+            let _ = stderr().write_all(error.to_string().as_bytes());
+        },
+    }
+}
+
+/// Copies the hovered cel (or the last pinned one) to the clipboard as a base64
+/// PNG data URI, so it can be pasted straight into a bug report.
+fn copy_cel_data_uri(rl: &mut RaylibHandle, state: &mut UIState) {
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return };
+    let Some(cel) = loaded.hovered_cel().or(state.pinned_cels.last().copied()) else { return };
+
+    let toast = match loaded.cel_png(cel) {
+        Some(png) => {
+            let uri = format!("data:image/png;base64,{}", BASE64.encode(png));
+            match rl.set_clipboard_text(&uri) {
+                Ok(()) => Toast::info("copied cel as a data URI", 180),
+                Err(e) => Toast::error(format!("couldn't copy cel: {e}").as_str(), 240),
+            }
+        },
+        None => Toast::warn("cel has no image of its own to copy", 240),
+    };
+
+    state.overlays.push(Box::new(toast));
+}
+
+/// Copies the frame playback is on to the clipboard as an image. It's written
+/// to a PNG in the temp folder first, and if the image can't be put on the
+/// clipboard that PNG's path is copied instead.
+fn copy_frame(rl: &mut RaylibHandle, state: &mut UIState) {
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return };
+    let frame = state.playback.frame();
+
+    let name = loaded.frame_png_path(frame).file_name().map(|x| x.to_owned()).unwrap_or_else(|| "frame.png".into());
+    let path = std::env::temp_dir().join(name);
+
+    let toast = match loaded.frame_png(frame).map(|png| std::fs::write(&path, png)) {
+        Some(Ok(())) => match copy_png_to_clipboard(&path) {
+            Ok(()) => Toast::info(format!("copied frame {frame}").as_str(), 180),
+            Err(e) => match rl.set_clipboard_text(&path.to_string_lossy()) {
+                Ok(()) => Toast::warn(format!("couldn't copy the image ({e}), copied the path to it instead").as_str(), 300),
+                Err(e) => Toast::error(format!("couldn't copy frame {frame}: {e}").as_str(), 240),
+            },
+        },
+        Some(Err(e)) => Toast::error(format!("couldn't save {}: {e}", path.display()).as_str(), 240),
+        None         => Toast::error(format!("couldn't encode frame {frame}").as_str(), 240),
+    };
+
+    state.overlays.push(Box::new(toast));
+}
+
+/// Puts a PNG on the system clipboard as an image, through whatever the platform
+/// has for it since raylib only does text. On Linux that's `wl-copy` under
+/// Wayland and `xclip` otherwise, which have to be installed.
+fn copy_png_to_clipboard(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut c = Command::new("powershell");
+        c.args(["-NoProfile", "-STA", "-Command"]).arg(format!(
+            "Add-Type -AssemblyName System.Windows.Forms; [System.Windows.Forms.Clipboard]::SetImage([System.Drawing.Image]::FromFile('{}'))",
+            path.display().to_string().replace('\'', "''")
+        ));
+        c
+    } else if cfg!(target_os = "macos") {
+        let mut c = Command::new("osascript");
+        c.arg("-e").arg(format!(
+            "set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)",
+            path.display().to_string().replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+        c
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        let mut c = Command::new("wl-copy");
+        c.args(["--type", "image/png"]).stdin(std::fs::File::open(path)?);
+        c
+    } else {
+        let mut c = Command::new("xclip");
+        c.args(["-selection", "clipboard", "-target", "image/png", "-in"]).arg(path);
+        c
+    };
+
+    let status = command.status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{} exited with {status}", command.get_program().to_string_lossy())));
+    }
+
+    Ok(())
+}
+
+/// Saves the frame playback is on as a PNG next to the sprite.
+fn save_frame_png(state: &mut UIState) {
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return };
+    let frame = state.playback.frame();
+    let path = loaded.frame_png_path(frame);
+
+    // `Image::export_image` doesn't say if it worked, so it's encoded and written
+    // out here instead
+    let toast = match loaded.frame_png(frame).map(|png| std::fs::write(&path, png)) {
+        Some(Ok(())) => Toast::info(format!("saved frame {frame} to {}", path.display()).as_str(), 180),
+        Some(Err(e)) => Toast::error(format!("couldn't save {}: {e}", path.display()).as_str(), 240),
+        None         => Toast::error(format!("couldn't encode frame {frame}").as_str(), 240),
+    };
+
+    state.overlays.push(Box::new(toast));
+}
+
+/// Saves every frame packed into a sprite sheet next to the sprite, along with
+/// its JSON.
+fn save_sheet(state: &mut UIState, layout: SheetLayout) {
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return };
+    let path = loaded.sheet_path();
+    let export = loaded.sheet_export(&path, layout);
+
+    let done = format!("saved sprite sheet to {}", path.display());
+    start_export(state, "saving sprite sheet", done, path, move |progress| {
+        export.save(|step, steps| progress.set(step, steps))
+    });
+}
+
+/// Saves the tag being played as a GIF next to the sprite, or every frame if
+/// there's no tag playing.
+fn save_gif(state: &mut UIState) {
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return };
+    let tag = state.playback.tag;
+    let path = loaded.gif_path(tag);
+    let export = loaded.gif_export(&path, tag);
+
+    let done = format!("saved GIF to {}", path.display());
+    start_export(state, "saving GIF", done, path, move |progress| {
+        export.save(|step, steps| progress.set(step, steps))
+    });
+}
+
+/// Runs an export on another thread with a toast showing how far it's got, as
+/// long as there isn't one running already. `done` is what the toast says once
+/// it's saved to `path`.
+fn start_export(state: &mut UIState, label: &str, done: String, path: PathBuf, save: impl FnOnce(&Progress) -> io::Result<()> + Send + 'static) {
+    if state.export.is_some() {
+        state.overlays.push(Box::new(Toast::warn("wait for the export that's already going to finish first", 180)));
+        return;
+    }
+
+    let progress = Progress::default();
+    state.overlays.push(Box::new(Toast::progress(label, progress.clone())));
+
+    let shared = progress.clone();
+    let handle = thread::spawn(move || save(&shared));
+    state.export = Some(ExportJob { handle, progress, path, done });
+}
+
+/// Swaps the progress toast for one saying how it went, once the export running
+/// on another thread is done.
+fn finish_export(state: &mut UIState) {
+    if !state.export.as_ref().is_some_and(|x| x.handle.is_finished()) {
+        return;
+    }
+    let Some(job) = state.export.take() else { return };
+
+    job.progress.finish();
+    let toast = match job.handle.join() {
+        Ok(Ok(())) => Toast::info(job.done.as_str(), 180),
+        Ok(Err(e)) => Toast::error(format!("couldn't save {}: {e}", job.path.display()).as_str(), 240),
+        Err(_)     => Toast::error(format!("couldn't save {}, something went wrong partway through", job.path.display()).as_str(), 240),
+    };
+
+    state.overlays.push(Box::new(toast));
+}
+
+/// Saves each layer of the frame playback is on as its own PNG, in a folder next
+/// to the sprite.
+fn save_layer_pngs(state: &mut UIState, bake_opacity: bool) {
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return };
+    let frame = state.playback.frame();
+    let dir = loaded.layers_dir(frame);
+
+    let toast = match loaded.export_layers(&dir, frame, bake_opacity) {
+        Ok(count) => Toast::info(format!("saved {count} layers of frame {frame} to {}", dir.display()).as_str(), 180),
+        Err(e)    => Toast::error(format!("couldn't save to {}: {e}", dir.display()).as_str(), 240),
+    };
+
+    state.overlays.push(Box::new(toast));
+}
+
+/// Opens the system file manager at `dir`.
+fn open_in_file_manager(dir: &Path) -> io::Result<()> {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+
+    Command::new(program).arg(dir).spawn().map(|_| ())
+}
+
+/// Draws a small floating label just above `bounds` while the mouse is resting
+/// over it.
+fn tooltip(d: &mut RaylibDrawHandle, bounds: Rectangle, text: &str) {
+    let m = d.get_mouse_position();
+    if !bounds.check_collision_point_rec(m) || d.get_mouse_delta().length() > 0.0 {
+        return;
+    }
+
+    let padding = 4;
+    let w = d.measure_text(text, FONT_SIZE_REG) + padding * 2;
+    let h = FONT_SIZE_REG + padding * 2;
+
+    let x = (m.x as i32).min(d.get_screen_width() - w - 1).max(0);
+    let y = bounds.y as i32 - h - padding;
+
+    d.draw_rectangle(x, y, w, h, TOOLTIP_BACKGROUND);
+    d.draw_text(text, x + padding, y + padding, FONT_SIZE_REG, Color::WHITE);
+}
+
+/// Draws the details of the cel under the mouse in a box next to it.
+fn cel_tooltip(d: &mut RaylibDrawHandle, state: &UIState) {
+    let m = d.get_mouse_position();
+    if mouse_over_chrome(state, m) || d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_RIGHT) {
+        return;
+    }
+
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return };
+    let Some((title, details)) = loaded.hovered_cel().and_then(|x| loaded.cel_details(x)) else { return };
+
+    let lines: Vec<&str> = std::iter::once(title.as_str()).chain(details.lines()).collect();
+
+    let padding = 4;
+    let line_h = FONT_SIZE_REG + 2;
+    let w = lines.iter().map(|x| d.measure_text(x, FONT_SIZE_REG)).max().unwrap_or(0) + padding * 2;
+    let h = lines.len() as i32 * line_h + padding * 2;
+
+    // goes to the other side of the mouse rather than off the window
+    let mut x = m.x as i32 + CEL_TOOLTIP_OFFSET;
+    if x + w > d.get_screen_width() {
+        x = m.x as i32 - CEL_TOOLTIP_OFFSET - w;
+    }
+
+    let mut y = m.y as i32 + CEL_TOOLTIP_OFFSET;
+    if y + h > d.get_screen_height() {
+        y = m.y as i32 - CEL_TOOLTIP_OFFSET - h;
+    }
+
+    d.draw_rectangle(x, y, w, h, TOOLTIP_BACKGROUND);
+    for (i, line) in lines.iter().enumerate() {
+        d.draw_text(line, x + padding, y + padding + i as i32 * line_h, FONT_SIZE_REG, Color::WHITE);
+    }
+}
+
+fn label_wrapper(d: &mut RaylibDrawHandle, bounds: impl Into<ffi::Rectangle>, text: &str, is_btn: bool) -> bool {
+    // let lbl_str = CString::new(text).unwrap();
+    // let lbl_str = lbl_str.as_c_str();
+    if is_btn {
+        d.gui_label_button(bounds, text)
+    } else {
+        d.gui_label(bounds, text)
+    }
+}
+
+/// Where the properties of the selected layer are shown, next to the layer list.
+fn layer_properties_rect(layer_list_width: f32) -> Rectangle {
+    Rectangle{
+        x: layer_list_width + 8.,
+        y: TAB_STRIP_HEIGHT + 8.0,
+        width: 120.0,
+        height: 170.0,
+    }
+}
+
+/// Where the blend mode dropdown is in the layer properties, closed.
+fn blend_mode_dropdown_rect(layer_list_width: f32) -> Rectangle {
+    let prop_bounds = layer_properties_rect(layer_list_width);
+
+    Rectangle{
+        x: prop_bounds.x + 4.0,
+        y: prop_bounds.y + 28.0,
+        width: prop_bounds.width - 8.0,
+        height: 20.0,
+    }
+}
+
+/// Where the details of the `i`th pinned cel are shown. They stack upwards from
+/// the bottom right corner.
+fn pinned_panel_rect(state: &UIState, i: usize) -> Rectangle {
+    Rectangle{
+        x: state.window_w as f32 - PINNED_PANEL_W - 8.,
+        y: (state.window_h - 24) as f32 - (i + 1) as f32 * (PINNED_PANEL_H + 4.) - 4.,
+        width: PINNED_PANEL_W,
+        height: PINNED_PANEL_H,
+    }
+}
+
+/// Checks if a point on screen is over any of the UI drawn on top of the
+/// workspace, so clicks there aren't taken as clicks on the sprite.
+fn mouse_over_chrome(state: &UIState, m: Vector2) -> bool {
+    // toasts show even with the rest of the chrome hidden
+    if state.overlays.iter().take(state.max_toasts).any(|x| x.contains(m)) {
+        return true;
+    }
+
+    if !state.chrome_visible {
+        return false;
+    }
+
+    if m.y >= (state.window_h - 24) as f32 {
+        return true;
+    }
+
+    if state.layer_list_visible {
+        if m.x <= state.layer_list_width + 8. {
+            return true;
+        }
+
+        if state.layer_list_active >= 0 && layer_properties_rect(state.layer_list_width).check_collision_point_rec(m) {
+            return true;
+        }
+
+        // the open list hangs down past the properties, with a row for every mode
+        // under the dropdown itself
+        if state.layer_list_active >= 0 && state.blend_mode_editing {
+            let dropdown = blend_mode_dropdown_rect(state.layer_list_width);
+            let open = Rectangle{ height: (dropdown.height + 2.0) * (AsepriteBlendMode::COUNT + 1) as f32, ..dropdown };
+            if open.check_collision_point_rec(m) {
+                return true;
+            }
+        }
+    }
+
+    if !state.sprites.is_empty() && tab_strip_rect(state).check_collision_point_rec(m) {
+        return true;
+    }
+
+    if state.show_sheet_menu && sheet_menu_rect(state).check_collision_point_rec(m) {
+        return true;
+    }
+
+    if state.show_recent_menu && recent_menu_rect(state).check_collision_point_rec(m) {
+        return true;
+    }
+
+    if state.show_palette && palette_panel_rect(state).check_collision_point_rec(m) {
+        return true;
+    }
+
+    if tileset_panel_shown(state) && tileset_panel_layout(state).0.check_collision_point_rec(m) {
+        return true;
+    }
+
+    if state.show_preview && preview_panel_rect(state).check_collision_point_rec(m) {
+        return true;
+    }
+
+    (0..state.pinned_cels.len()).any(|i| pinned_panel_rect(state, i).check_collision_point_rec(m))
+}
+
+/// Gets the part of the window toasts stack up in, which leaves out the layer
+/// list and bottom bar while they're shown.
+fn toast_area(state: &UIState) -> Rectangle {
+    let left = if state.chrome_visible && state.layer_list_visible && !state.sprites.is_empty() { state.layer_list_width + 8. } else { 0. };
+    let bottom = if state.chrome_visible { 24. } else { 0. };
+
+    Rectangle{x: left, y: 0., width: state.window_w as f32 - left, height: state.window_h as f32 - bottom}
+}
+
+/// Where the tabs of the open sprites are, along the top of the window right of
+/// the layer list.
+fn tab_strip_rect(state: &UIState) -> Rectangle {
+    let left = if state.layer_list_visible { state.layer_list_width } else { 0. };
+
+    Rectangle{x: left, y: 0., width: state.window_w as f32 - left, height: TAB_STRIP_HEIGHT}
+}
+
+/// Draws a tab for each open sprite, switching to whichever is clicked and
+/// closing any that get closed.
+fn tab_strip(d: &mut RaylibDrawHandle, state: &mut UIState) {
+    let names: Vec<CString> = state.sprites.iter()
+        .map(|x| CString::new(x.file_name()).unwrap_or_default())
+        .collect();
+    let mut text: Vec<*const std::ffi::c_char> = names.iter().map(|x| x.as_ptr()).collect();
+
+    let mut active = state.active_sprite as i32;
+    let close = unsafe {
+        ffi::GuiTabBar(tab_strip_rect(state).into(), text.as_mut_ptr(), text.len() as i32, &mut active)
+    };
+
+    if let Ok(close) = usize::try_from(close) {
+        close_sprite(state, close);
+    } else if active as usize != state.active_sprite {
+        switch_sprite(state, active as usize);
+    }
+}
+
+/// Where the sprite sheet layouts are listed, just above their button.
+fn sheet_menu_rect(state: &UIState) -> Rectangle {
+    Rectangle{x: 346., y: (state.window_h - 96) as f32, width: 90., height: 72.}
+}
+
+/// Where the recently opened files are listed, going up from the bottom bar
+/// with a row for each one.
+fn recent_menu_rect(state: &UIState) -> Rectangle {
+    let height = state.recent.paths.len() as f32 * 24.;
+    Rectangle{x: 570., y: (state.window_h - 24) as f32 - height, width: RECENT_MENU_W, height}
+}
+
+/// Cuts the start off a path until it fits in `width`, so the end with the
+/// file's name stays readable.
+fn fit_path(d: &RaylibDrawHandle, path: &str, width: i32) -> String {
+    if d.measure_text(path, FONT_SIZE_REG) <= width {
+        return path.to_owned();
+    }
+
+    path.char_indices()
+        .map(|(i, _)| format!("...{}", &path[i..]))
+        .find(|x| d.measure_text(x, FONT_SIZE_REG) <= width)
+        .unwrap_or_default()
+}
+
+/// Where the preview of the playing animation is shown, in the bottom left
+/// corner just past the layer list.
+fn preview_panel_rect(state: &UIState) -> Rectangle {
+    let left = if state.layer_list_visible { state.layer_list_width } else { 0. };
+
+    Rectangle{
+        x: left + 8.,
+        y: (state.window_h - 24) as f32 - PREVIEW_PANEL_H - 8.,
+        width: PREVIEW_PANEL_W,
+        height: PREVIEW_PANEL_H,
+    }
+}
+
+/// Where the palette swatches are shown, in the top right corner and tall enough
+/// for every row of them.
+fn palette_panel_rect(state: &UIState) -> Rectangle {
+    let entries = state.sprites.get(state.active_sprite).map_or(0, |x| x.palette.len());
+    let rows = entries.div_ceil(PALETTE_COLUMNS).max(1);
+    let width = PALETTE_COLUMNS as f32 * PALETTE_SWATCH_SIZE + 8.;
+    // the export buttons only go under an actual palette
+    let buttons = if entries > 0 { PALETTE_BUTTON_HEIGHT + 4. } else { 0. };
+
+    Rectangle{
+        x: state.window_w as f32 - width - 8.,
+        y: TAB_STRIP_HEIGHT + 8.,
+        width,
+        height: 32. + rows as f32 * PALETTE_SWATCH_SIZE + buttons,
+    }
+}
+
+/// Draws every palette entry as a swatch, with the transparent one crossed out,
+/// and buttons under them to save the palette for other tools. Hovering over a
+/// swatch says what its index, colour and name are.
+fn palette_panel(d: &mut RaylibDrawHandle, state: &mut UIState) {
+    let bounds = palette_panel_rect(state);
+    let Some(loaded) = state.sprites.get_mut(state.active_sprite) else { return };
+
+    let title = format!("#26# palette ({})", loaded.palette.len());
+    if d.gui_window_box(bounds, title.as_str()) {
+        state.show_palette = false;
+        return;
+    }
+
+    if loaded.palette.is_empty() {
+        d.gui_label(Rectangle{
+            x: bounds.x + 4.0,
+            y: bounds.y + 24.0,
+            width: bounds.width - 8.0,
+            height: bounds.height - 28.0
+        }, "no palette");
+        return;
+    }
+
+    let m = d.get_mouse_position();
+    let mut hovered = None;
+
+    for (i, [r, g, b, a]) in loaded.palette.iter().copied().enumerate() {
+        let swatch = Rectangle{
+            x: bounds.x + 4. + (i % PALETTE_COLUMNS) as f32 * PALETTE_SWATCH_SIZE,
+            y: bounds.y + 28. + (i / PALETTE_COLUMNS) as f32 * PALETTE_SWATCH_SIZE,
+            width: PALETTE_SWATCH_SIZE,
+            height: PALETTE_SWATCH_SIZE,
+        };
+
+        d.draw_rectangle_rec(swatch, Color::new(r, g, b, a));
+
+        if loaded.is_transparent_index(i) {
+            d.draw_line_ex(
+                Vector2{x: swatch.x, y: swatch.y + swatch.height},
+                Vector2{x: swatch.x + swatch.width, y: swatch.y},
+                1., PALETTE_TRANSPARENT_COLOR
+            );
+            d.draw_rectangle_lines_ex(swatch, 1., PALETTE_TRANSPARENT_COLOR);
+        }
+
+        if swatch.check_collision_point_rec(m) {
+            hovered = Some((i, swatch));
+        }
+    }
+
+    if let Some((i, swatch)) = hovered {
+        d.draw_rectangle_lines_ex(swatch, 1., Color::WHITE);
+
+        if let Some(label) = loaded.generate_palette_labels().get(i) {
+            tooltip(d, swatch, label);
+        }
+    }
+
+    let mut export = None;
+    let button_w = (bounds.width - 12.) / 2.;
+    for (i, format) in [PaletteFormat::Gimp, PaletteFormat::Jasc].into_iter().enumerate() {
+        let button = Rectangle{
+            x: bounds.x + 4. + i as f32 * (button_w + 4.),
+            y: bounds.y + bounds.height - PALETTE_BUTTON_HEIGHT - 4.,
+            width: button_w,
+            height: PALETTE_BUTTON_HEIGHT,
+        };
+
+        if d.gui_button(button, format!("#7# .{}", format.extension()).as_str()) {
+            export = Some(format);
+        }
+        tooltip(d, button, format!("save the palette as a .{} file", format.extension()).as_str());
+    }
+
+    if let Some(format) = export {
+        let path = loaded.palette_path(format);
+        let toast = match loaded.export_palette(&path, format) {
+            Ok(()) => Toast::info(format!("saved palette to {}", path.display()).as_str(), 180),
+            Err(e) => Toast::error(format!("couldn't save {}: {e}", path.display()).as_str(), 240),
+        };
+
+        state.overlays.push(Box::new(toast));
+    }
+}
+
+/// Checks if the tileset panel is open on a sprite that has any tilesets.
+fn tileset_panel_shown(state: &UIState) -> bool {
+    state.show_tilesets && state.sprites.get(state.active_sprite).is_some_and(|x| !x.loaded_tilesets.is_empty())
+}
+
+/// Where the tilesets are shown, in the top right corner under the palette if
+/// that's open, along with how much the tiles are scaled by. Tall tilesets are
+/// shrunk so the panel stays above the bottom bar.
+fn tileset_panel_layout(state: &UIState) -> (Rectangle, f32) {
+    let tilesets = state.sprites.get(state.active_sprite).map_or(&[][..], |x| x.loaded_tilesets.as_slice());
+
+    let y = match state.show_palette {
+        true  => {
+            let palette = palette_panel_rect(state);
+            palette.y + palette.height + 8.
+        },
+        false => TAB_STRIP_HEIGHT + 8.,
+    };
+
+    // the title bar and each tileset's name line stay the same size
+    let fixed = 28. + tilesets.len() as f32 * (FONT_SIZE_REG + 8) as f32;
+    let tiles_height: f32 = tilesets.iter().map(|x| x.rows() as f32 * tile_cell_size(x).y).sum();
+    let available = state.window_h as f32 - 24. - 8. - y;
+
+    let scale = if tiles_height > 0. { ((available - fixed) / tiles_height).clamp(0.25, 1.) } else { 1. };
+
+    let width = (TILESET_COLUMNS as f32 * TILESET_CELL_SIZE * scale).max(PALETTE_COLUMNS as f32 * PALETTE_SWATCH_SIZE) + 8.;
+
+    (Rectangle{
+        x: state.window_w as f32 - width - 8.,
+        y,
+        width,
+        height: fixed + tiles_height * scale + 4.,
+    }, scale)
+}
+
+/// Gets the size a tileset's tiles are shown at in the tileset panel before
+/// it's scaled, which keeps the tiles' shape.
+fn tile_cell_size(tileset: &PreparedTileset) -> Vector2 {
+    let longest = tileset.tile_width.max(tileset.tile_height).max(1) as f32;
+
+    Vector2{
+        x: TILESET_CELL_SIZE * tileset.tile_width as f32 / longest,
+        y: TILESET_CELL_SIZE * tileset.tile_height as f32 / longest,
+    }
+}
+
+/// Draws every tileset's tiles in a grid with their numbers on them, the way
+/// Aseprite numbers them. Hovering over one says what its number and size are.
+fn tileset_panel(d: &mut RaylibDrawHandle, state: &mut UIState) {
+    let (bounds, scale) = tileset_panel_layout(state);
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return };
+
+    let title = format!("#111# tilesets ({})", loaded.loaded_tilesets.len());
+    if d.gui_window_box(bounds, title.as_str()) {
+        state.show_tilesets = false;
+        return;
+    }
+
+    let m = d.get_mouse_position();
+    let mut hovered = None;
+    let mut y = bounds.y + 28.;
+
+    for tileset in &loaded.loaded_tilesets {
+        let name = format!("{} ({} tiles, {}x{})", tileset.name, tileset.tile_count, tileset.tile_width, tileset.tile_height);
+        d.gui_label(Rectangle{ x: bounds.x + 4., y, width: bounds.width - 8., height: FONT_SIZE_REG as f32 }, name.as_str());
+        y += (FONT_SIZE_REG + 8) as f32;
+
+        let cell = tile_cell_size(tileset) * scale;
+        let grid = Rectangle{
+            x:      bounds.x + 4.,
+            y,
+            width:  TILESET_COLUMNS as f32 * cell.x,
+            height: tileset.rows() as f32 * cell.y,
+        };
+        y += grid.height;
+
+        if tileset.external {
+            d.gui_label(grid, "from another file");
+            continue;
+        }
+
+        if let Some(txtr) = tileset.texture() {
+            d.draw_texture_pro(txtr,
+                Rectangle{ x: 0., y: 0., width: txtr.width() as f32, height: txtr.height() as f32 },
+                grid, Vector2::zero(), 0., Color::WHITE
+            );
+        }
+
+        for i in 0..tileset.tile_count {
+            let tile = Rectangle{
+                x:      grid.x + (i % TILESET_COLUMNS) as f32 * cell.x,
+                y:      grid.y + (i / TILESET_COLUMNS) as f32 * cell.y,
+                width:  cell.x,
+                height: cell.y,
+            };
+
+            d.draw_rectangle_lines_ex(tile, 1., Color{a: 64, ..Color::BLACK});
+
+            if let Some(number) = tileset.shown_index(i).filter(|_| cell.x.min(cell.y) >= TILESET_LABEL_MIN_CELL) {
+                draw_label(d, number.to_string().as_str(), tile.x as i32 + 2, tile.y as i32 + 2, FONT_SIZE_REG, Color::WHITE);
+            }
+
+            if tile.check_collision_point_rec(m) {
+                hovered = Some((tileset, i, tile));
+            }
+        }
+    }
+
+    if let Some((tileset, i, tile)) = hovered {
+        d.draw_rectangle_lines_ex(tile, 1., Color::WHITE);
+
+        let label = match tileset.shown_index(i) {
+            Some(number) if tileset.base_index != 1 => format!("tile {number} ({i} in the file): {}x{}", tileset.tile_width, tileset.tile_height),
+            Some(number) => format!("tile {number}: {}x{}", tileset.tile_width, tileset.tile_height),
+            None         => format!("empty tile: {}x{}", tileset.tile_width, tileset.tile_height),
+        };
+        tooltip(d, tile, label.as_str());
+    }
+}
+
+/// Composites the frame playback is on for the preview, if it isn't already.
+fn refresh_preview(rl: &mut RaylibHandle, thread: &RaylibThread, state: &mut UIState) {
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return };
+    let frame = state.playback.frame();
+
+    if let Some(i) = state.preview.iter().position(|(shown, _)| *shown == frame) {
+        let cached = state.preview.remove(i);
+        state.preview.push(cached);
+        return;
+    }
+
+    let img = match state.onion_skin.enabled {
+        true  => loaded.composite_onion_skin(frame, &state.onion_skin),
+        false => loaded.composite_frame(frame),
+    };
+    let shown = match state.preview_software {
+        true  => PreviewFrame::Pixels(img.get_image_data().to_vec()),
+        false => match rl.load_texture_from_image(thread, &img) {
+            Ok(texture) => PreviewFrame::Texture(texture),
+            Err(e) => {
+                state.preview_software = true;
+                state.overlays.push(Box::new(Toast::error(
+                    format!("can't upload the preview ({e}), it'll be drawn without the GPU").as_str(),
+                    300
+                )));
+                PreviewFrame::Pixels(img.get_image_data().to_vec())
+            },
+        },
+    };
+
+    if state.preview.len() >= PREVIEW_CACHE_FRAMES {
+        state.preview.remove(0);
+    }
+    state.preview.push((frame, shown));
+}
+
+/// Draws a preview frame that couldn't be uploaded scaled into `shown`, with a
+/// rectangle for each run of the same colour along a row.
+fn draw_preview_pixels(d: &mut RaylibDrawHandle, pixels: &[Color], width: usize, shown: Rectangle) {
+    if width == 0 {
+        return;
+    }
+    let height = pixels.len() / width;
+    let (scale_x, scale_y) = (shown.width / width as f32, shown.height / height.max(1) as f32);
+
+    for (y, row) in pixels.chunks_exact(width).enumerate() {
+        let mut x = 0;
+        for run in row.chunk_by(|a, b| a == b) {
+            if run[0].a > 0 {
+                // rounding both edges keeps neighbouring runs from leaving gaps
+                let (left, right) = (shown.x + x as f32 * scale_x, shown.x + (x + run.len()) as f32 * scale_x);
+                let (top, bottom) = (shown.y + y as f32 * scale_y, shown.y + (y + 1) as f32 * scale_y);
+                d.draw_rectangle(
+                    left.round() as i32,
+                    top.round() as i32,
+                    (right.round() - left.round()) as i32,
+                    (bottom.round() - top.round()) as i32,
+                    run[0]
+                );
+            }
+            x += run.len();
+        }
+    }
+}
+
+/// Draws the composited frame playback is on, scaled to fit in the panel.
+fn preview_panel(d: &mut RaylibDrawHandle, state: &mut UIState) {
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return };
+    let bounds = preview_panel_rect(state);
+    let frame = state.playback.frame();
+
+    let mut title = match state.playback.tag.and_then(|x| loaded.tag_name(x)) {
+        Some(tag) => format!("#131# {tag}: frame {frame} ({}ms)", frame_duration(&loaded.frame_durations, frame)),
+        None      => format!("#131# frame {frame} ({}ms)", frame_duration(&loaded.frame_durations, frame)),
+    };
+    match state.playback.current_loop() {
+        Some(_) if state.playback.is_finished() && !state.playback.playing => title.push_str(", done."),
+        Some((current, total)) => title.push_str(&format!(", loop {current}/{total}")),
+        None => (),
+    }
+    if d.gui_window_box(bounds, title.as_str()) {
+        state.show_preview = false;
+        state.playback.playing = false;
+        return;
+    }
+
+    let Some((_, shown_frame)) = state.preview.iter().rfind(|(shown, _)| *shown == frame) else { return };
+
+    let area = Rectangle{
+        x: bounds.x + 4.,
+        y: bounds.y + 28.,
+        width: bounds.width - 8.,
+        height: bounds.height - 32.,
+    };
+
+    let (w, h) = (
+        loaded.image_width as f32 * loaded.pixel_width as f32,
+        loaded.image_height as f32 * loaded.pixel_height as f32
+    );
+
+    // whole number scales keep the pixels square when the sprite is small enough
+    let scale = (area.width / w).min(area.height / h);
+    let scale = if scale >= 1. { scale.floor() } else { scale };
+
+    let shown = Rectangle{
+        x: area.x + (area.width - w * scale) / 2.,
+        y: area.y + (area.height - h * scale) / 2.,
+        width: w * scale,
+        height: h * scale,
+    };
+
+    // a shadow dropped further the bigger the frame's drawn, and a border, keep
+    // sprites with dark or see-through edges apart from the panel behind them
+    let drop = (scale * PREVIEW_SHADOW_STEP).clamp(2., PREVIEW_SHADOW_MAX);
+    for layer in 0..PREVIEW_SHADOW_LAYERS {
+        let spread = layer as f32;
+        d.draw_rectangle_rec(Rectangle{
+            x: shown.x + drop - spread,
+            y: shown.y + drop - spread,
+            width: shown.width + spread * 2.,
+            height: shown.height + spread * 2.,
+        }, Color{r: 0, g: 0, b: 0, a: 96 / (layer + 1)});
+    }
+
+    match shown_frame {
+        PreviewFrame::Texture(texture) => d.draw_texture_pro(
+            texture,
+            Rectangle{x: 0., y: 0., width: texture.width() as f32, height: texture.height() as f32},
+            shown,
+            Vector2{x: 0., y: 0.},
+            0.,
+            Color::WHITE
+        ),
+        PreviewFrame::Pixels(pixels) => draw_preview_pixels(d, pixels, loaded.image_width as usize, shown),
+    }
+
+    d.draw_rectangle_lines_ex(Rectangle{
+        x: shown.x - 1.,
+        y: shown.y - 1.,
+        width: shown.width + 2.,
+        height: shown.height + 2.,
+    }, 1., PREVIEW_BORDER_COLOR);
+
+    // makes where each loop of a repeating tag ends easy to spot
+    if state.playback.is_flashing() {
+        d.draw_rectangle_lines_ex(shown, 2., Color::YELLOW);
+    }
+}
+
+/// Draws the details of every pinned cel, unpinning any that get closed.
+fn pinned_panels(d: &mut RaylibDrawHandle, state: &mut UIState) {
+    let Some(loaded) = state.sprites.get(state.active_sprite) else { return };
+    let mut unpin = None;
+
+    for (i, cel_index) in state.pinned_cels.iter().enumerate() {
+        let Some((title, details)) = loaded.cel_details(*cel_index) else { continue };
+        let bounds = pinned_panel_rect(state, i);
+
+        if d.gui_window_box(bounds, title.as_str()) {
+            unpin = Some(i);
+        }
+
+        d.gui_label(Rectangle{
+            x: bounds.x + 4.0,
+            y: bounds.y + 24.0,
+            width: bounds.width - 8.0,
+            height: bounds.height - 28.0
+        }, details.as_str());
+    }
+
+    if let Some(i) = unpin {
+        state.pinned_cels.remove(i);
+    }
+}
+
+fn layer_list(d: &mut RaylibDrawHandle, state: &mut UIState) {
+    if let Some(loaded) = state.sprites.get_mut(state.active_sprite) {
+        if state.layer_list_visible {
+            let filter_rec = Rectangle{
+                x: 0.0,
+                y: 0.0,
+                width: state.layer_list_width,
+                height: LAYER_FILTER_HEIGHT,
+            };
+
+            state.layer_filter.resize(LAYER_FILTER_LEN, 0);
+            // raygui edits the buffer in place, so it has to be handed over as is
+            let toggled = unsafe {
+                ffi::GuiTextBox(
+                    filter_rec.into(),
+                    state.layer_filter.as_mut_ptr() as *mut _,
+                    LAYER_FILTER_LEN as i32,
+                    state.layer_filter_editing
+                ) > 0
+            };
+            if toggled {
+                state.layer_filter_editing ^= true;
+            }
+
+            let end = state.layer_filter.iter().position(|x| *x == 0).unwrap_or(LAYER_FILTER_LEN);
+            let filter = String::from_utf8_lossy(&state.layer_filter[..end]);
+            if filter.is_empty() && !state.layer_filter_editing {
+                d.gui_label(Rectangle{ x: 4.0, ..filter_rec }, "#42#Filter layers");
+            }
+
+            // rows move around when the filter changes, so whatever was picked
+            // no longer lines up
+            if loaded.set_layer_filter(&filter) {
+                state.layer_list_active = -1;
+                state.layer_list_scroll = 0;
+            }
+
+            let dd_str = loaded.generate_layer_list();
+
+            let layer_list_rec = Rectangle{
+                x: 0.0,
+                y: LAYER_FILTER_HEIGHT,
+                width: state.layer_list_width,
+                height: state.window_h as f32 - LAYER_FILTER_HEIGHT,
+            };
+
+            // left aligned so the layers inside groups line up under them
+            let alignment = d.gui_get_style(GuiControl::LISTVIEW, GuiControlProperty::TEXT_ALIGNMENT);
+            d.gui_set_style(GuiControl::LISTVIEW, GuiControlProperty::TEXT_ALIGNMENT, GuiTextAlignment::TEXT_ALIGN_LEFT as i32);
+
+            let before = state.layer_list_active;
+            let _ = d.gui_list_view(
+                layer_list_rec, dd_str, &mut state.layer_list_scroll, &mut state.layer_list_active
+            );
+
+            d.gui_set_style(GuiControl::LISTVIEW, GuiControlProperty::TEXT_ALIGNMENT, alignment);
+
+            if state.layer_list_active != before {
+                // clicking the picked row again unpicks it
+                let clicked = if state.layer_list_active >= 0 { state.layer_list_active } else { before };
+                let clicked = loaded.layer_list_row(clicked);
+
+                // alt clicking solos the layer instead of opening its properties
+                let alt = d.is_key_down(KeyboardKey::KEY_LEFT_ALT) || d.is_key_down(KeyboardKey::KEY_RIGHT_ALT);
+                if alt {
+                    if let Some(layer) = clicked {
+                        loaded.toggle_solo(layer);
+                        state.preview.clear();
+                    }
+
+                    state.layer_list_active = before;
+                } else if let Some(layer) = clicked {
+                    // the rows above a group stay put, so it stays picked
+                    loaded.toggle_collapsed(layer);
+                }
+            }
+
+            let resize_area = Rectangle{
+                x: layer_list_rec.width - 8.0,
+                width: 16.0,
+                ..layer_list_rec
+            };
+
+            let lo_resize_bound: f32 = LAYER_LIST_MIN_WIDTH;
+            let hi_resize_bound: f32 = d.get_screen_width() as f32 - LAYER_LIST_WINDOW_GAP;
+
+            let m = d.get_mouse_position();
+            if resize_area.check_collision_point_rec(m) || state.layer_list_resizing {
+                d.draw_line_ex(Vector2{
+                    x: resize_area.x + resize_area.width / 2.,
+                    y: resize_area.y
+                }, Vector2{
+                    x: resize_area.x + resize_area.width / 2.,
+                    y: resize_area.y + resize_area.height
+                }, resize_area.width / 4., LAYER_RESIZE_COLOUR);
+
+                unsafe {
+                    let arrows_height = (resize_area.height / 2.) as i32;
+
+                    if state.layer_list_width > lo_resize_bound {
+                        ffi::GuiDrawIcon(
+                            118,
+                            (resize_area.x - LAYER_RESIZE_ICON_SPREAD) as i32, arrows_height,
+                            1,
+                            LAYER_RESIZE_COLOUR.into()
+                        );
+                    }
+                    if state.layer_list_width < hi_resize_bound {
+                        ffi::GuiDrawIcon(
+                            119,
+                            (resize_area.x + LAYER_RESIZE_ICON_SPREAD) as i32, arrows_height,
+                            1,
+                            LAYER_RESIZE_COLOUR.into()
+                        );
+                    }
+                };
+
+                state.layer_list_resizing = d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT);
+
+                if state.layer_list_resizing {
+                    state.layer_list_width = m.x.clamp(lo_resize_bound, hi_resize_bound)
+                }
+            }
+
+            if let Some(effective_layer_active) = loaded.layer_list_row(state.layer_list_active) {
+                let prop_bounds = layer_properties_rect(state.layer_list_width);
+
+                let layer_name = loaded.loaded_layers[effective_layer_active].name.as_str();
+                // let layer_name = layer_name.as_c_str();
+
+                if d.gui_window_box(prop_bounds, layer_name) {
+                    state.layer_list_active = -1;
+                    state.blend_mode_editing = false;
+                }
+
+                // nothing under the open blend mode list can be clicked through it
+                if state.blend_mode_editing {
+                    d.gui_lock();
+                }
+                
+                let layer = &loaded.loaded_layers[effective_layer_active];
+                let properties_contents = format!(
+                    "{}{}\n{}",
+                    if layer.background {"Is a background"} else {""},
+                    if layer.is_reference {"\nIs a reference"} else {"\n"},
+                    layer.note.as_deref().unwrap_or(""),
+                );
+                
+                d.gui_label(Rectangle{
+                    x: prop_bounds.x + 4.0,
+                    y: prop_bounds.y + 52.0,
+                    width: prop_bounds.width,
+                    height: 64.0
+                }, properties_contents.as_ref());
+
+                // only changes how the layer's shown here, nothing's saved back
+                let mut opacity = layer.opacity as f32;
+                let opacity_text = layer.opacity.to_string();
+                if d.gui_slider(Rectangle{
+                    x: prop_bounds.x + 48.0,
+                    y: prop_bounds.y + prop_bounds.height - 48.0,
+                    width: prop_bounds.width - 76.0,
+                    height: 16.0,
+                }, "Opacity", opacity_text.as_str(), &mut opacity, 0.0, 255.0) {
+                    loaded.loaded_layers[effective_layer_active].opacity = opacity.round() as u8;
+                    state.preview.clear();
+                }
+
+                if d.gui_check_box(Rectangle{
+                    x: prop_bounds.x + 8.0,
+                    y: prop_bounds.y + prop_bounds.height - 28.0,
+                    width: 24.0,
+                    height: 24.0,
+                }, "Visible", &mut loaded.loaded_layers[effective_layer_active].visible) {
+                    loaded.invalidate_layer_list();
+                    state.preview.clear();
+                }
+
+                let mut soloed = loaded.soloed_layer() == Some(effective_layer_active);
+                if d.gui_check_box(Rectangle{
+                    x: prop_bounds.x + 64.0,
+                    y: prop_bounds.y + prop_bounds.height - 28.0,
+                    width: 24.0,
+                    height: 24.0,
+                }, "Solo", &mut soloed) {
+                    loaded.toggle_solo(effective_layer_active);
+                    state.preview.clear();
+                }
+
+                // drawn last so the open list goes over everything else, and like the
+                // opacity only changes how the layer's shown here
+                d.gui_unlock();
+
+                let blend_modes = (0..AsepriteBlendMode::COUNT).map(|x| AsepriteBlendMode::from(x).to_string()).collect::<Vec<_>>().join(";");
+                let before = loaded.loaded_layers[effective_layer_active].blend_mode as i32;
+                let mut blend_mode = before;
+
+                if d.gui_dropdown_box(blend_mode_dropdown_rect(state.layer_list_width), blend_modes.as_str(), &mut blend_mode, state.blend_mode_editing) {
+                    state.blend_mode_editing ^= true;
+                }
+
+                if blend_mode != before {
+                    loaded.loaded_layers[effective_layer_active].blend_mode = AsepriteBlendMode::from(blend_mode as u16);
+                    state.preview.clear();
+                }
+            }
+        }
+    }
+}
+
+fn bottom_bar(d: &mut RaylibDrawHandle, state: &mut UIState, cam: &Camera2D) {
+    d.gui_panel(Rectangle{x: 0., y: (state.window_h - 24) as f32, width: state.window_w as f32, height: 24.}, "");
+
+    // drawn last so nothing in the bar covers them
+    let mut tips: Vec<(Rectangle, &str)> = vec![];
+
+    {
+        let bounds = Rectangle{x: 0., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, "toggle layer list"));
+        match state.sprites.get(state.active_sprite) {
+            Some(_) => {
+                if label_wrapper(d, bounds, if state.layer_list_visible { "#197#" } else { "#196#" }, true) {
+                    state.layer_list_visible ^= true;
+                }
+            },
+            None => { label_wrapper(d, bounds,  "#196#", false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 28., y: (state.window_h - 24) as f32, width: 90., height: 24.};
+        tips.push((bounds, "zoom, click for presets"));
+
+        if label_wrapper(d, bounds, format!("#43# {0:.2}%", cam.zoom * 100.).as_str(), true) {
+            state.show_zoom_reset ^= true;
+        }
+    }
+
+    if state.show_zoom_reset {
+        let rect = Rectangle{x: 28., y: (state.window_h - 96) as f32, width: 65., height: 24.};
+
+        if d.gui_button(rect, "#43# fit") {
+            state.zoom_anchor = None;
+            state.desired_zoom = state.fit_zoom;
+            state.show_zoom_reset = false
+        }
+        if d.gui_button(Rectangle{y: rect.y + rect.height, ..rect}, "#42# 100%") {
+            state.zoom_anchor = None;
+            state.desired_zoom = 1.;
+            state.show_zoom_reset = false
+        }
+        // frames everything at once, both lerp back on their own
+        if d.gui_button(Rectangle{y: rect.y + rect.height * 2., ..rect}, "#48# reset") {
+            state.zoom_anchor = None;
+            state.desired_zoom = state.fit_zoom;
+            state.desired_position = state.default_position;
+            state.show_zoom_reset = false
+        }
+    }
+
+    {
+        let recenter = Rectangle{x: 112., y: (state.window_h - 24) as f32, width: 90., height: 24.};
+        tips.push((recenter, "camera position, click to recenter"));
+        let t = format!("#48# {0:.0}, {1:.0}", cam.target.x, cam.target.y);
+        let recenter_tx = if recenter.check_collision_point_rec(d.get_mouse_position()) {
+            "#48# recenter?"
+        } else {
+            t.as_str() 
+        };
+        
+        if label_wrapper(d, recenter, recenter_tx, true) {
+            state.zoom_anchor = None;
+            state.desired_position = state.default_position;
+        }
+    }
+
+    {
+        let bounds = Rectangle{x: 206., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        let icon = if state.draw_options.keyframes_only { "#175#" } else { "#174#" };
+        tips.push((bounds, if state.draw_options.keyframes_only { "show linked cels" } else { "hide linked cels" }));
+        match state.sprites.get(state.active_sprite) {
+            Some(_) => {
+                if label_wrapper(d, bounds, icon, true) {
+                    state.draw_options.keyframes_only ^= true;
+                }
+            },
+            None => { label_wrapper(d, bounds, icon, false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 234., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, "open containing folder"));
+        match state.sprites.get(state.active_sprite) {
+            Some(loaded) => {
+                if label_wrapper(d, bounds, "#3#", true) {
+                    let folder = Path::new(&loaded.file_path).parent()
+                        .map(|p| if p.as_os_str().is_empty() { Path::new(".") } else { p });
+
+                    let toast = match folder {
+                        Some(folder) => open_in_file_manager(folder).err()
+                            .map(|e| format!("couldn't open {}: {e}", folder.display())),
+                        None => Some("couldn't tell which folder the sprite is in".to_owned()),
+                    };
+
+                    if let Some(text) = toast {
+                        state.overlays.push(Box::new(Toast::error(text.as_str(), 240)));
+                    }
+                }
+            },
+            None => { label_wrapper(d, bounds, "#3#", false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 262., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        let icon = if state.playback.playing { "#132#" } else { "#131#" };
+        tips.push((bounds, if state.playback.playing { "pause (space)" } else { "play (space)" }));
+        match state.sprites.get(state.active_sprite) {
+            Some(_) => {
+                if label_wrapper(d, bounds, icon, true) {
+                    state.playback.toggle();
+                    state.show_preview = true;
+                }
+            },
+            None => { label_wrapper(d, bounds, icon, false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 290., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        let icon = if state.onion_skin.enabled { "#45#" } else { "#44#" };
+        tips.push((bounds, if state.onion_skin.enabled { "hide onion skin (o)" } else { "show onion skin (o)" }));
+        match state.sprites.get(state.active_sprite) {
+            Some(_) => {
+                if label_wrapper(d, bounds, icon, true) {
+                    state.onion_skin.enabled ^= true;
+                    state.show_preview = true;
+                    state.preview.clear();
+                }
+            },
+            None => { label_wrapper(d, bounds, icon, false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 318., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, "save frame as PNG (ctrl+s)"));
+        match state.sprites.get(state.active_sprite) {
+            Some(_) => {
+                if label_wrapper(d, bounds, "#7#", true) {
+                    save_frame_png(state);
+                }
+            },
+            None => { label_wrapper(d, bounds, "#7#", false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 346., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, "export sprite sheet"));
+        match state.sprites.get(state.active_sprite) {
+            Some(_) => {
+                if label_wrapper(d, bounds, "#12#", true) {
+                    state.show_sheet_menu ^= true;
+                }
+            },
+            None => { label_wrapper(d, bounds, "#12#", false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 374., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, if state.playback.tag.is_some() { "export gif of the playing tag" } else { "export gif" }));
+        match state.sprites.get(state.active_sprite) {
+            Some(_) => {
+                if label_wrapper(d, bounds, "#14#", true) {
+                    save_gif(state);
+                }
+            },
+            None => { label_wrapper(d, bounds, "#14#", false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 402., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, "save each layer as a PNG, hold shift to bake in opacity"));
+        match state.sprites.get(state.active_sprite) {
+            Some(_) => {
+                if label_wrapper(d, bounds, "#4#", true) {
+                    let bake = d.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || d.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+                    save_layer_pngs(state, bake);
+                }
+            },
+            None => { label_wrapper(d, bounds, "#4#", false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 430., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, if state.show_palette { "hide palette" } else { "show palette" }));
+        match state.sprites.get(state.active_sprite) {
+            Some(_) => {
+                if label_wrapper(d, bounds, "#26#", true) {
+                    state.show_palette ^= true;
+                }
+            },
+            None => { label_wrapper(d, bounds, "#26#", false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 458., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, if state.eyedropper { "hide pixel colour (i)" } else { "show pixel colour under the mouse (i)" }));
+        match state.sprites.get(state.active_sprite) {
+            Some(_) => {
+                if label_wrapper(d, bounds, "#27#", true) {
+                    state.eyedropper ^= true;
+                }
+            },
+            None => { label_wrapper(d, bounds, "#27#", false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 486., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, if state.draw_options.pixel_grid { "hide pixel grid (g)" } else { "show pixel grid when zoomed in (g)" }));
+        match state.sprites.get(state.active_sprite) {
+            Some(_) => {
+                if label_wrapper(d, bounds, if state.draw_options.pixel_grid { "#97#" } else { "#96#" }, true) {
+                    state.draw_options.pixel_grid ^= true;
+                }
+            },
+            None => { label_wrapper(d, bounds, "#97#", false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 514., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, if state.draw_options.slices { "hide slices" } else { "show slices" }));
+        match state.sprites.get(state.active_sprite) {
+            Some(loaded) if !loaded.loaded_slices.is_empty() => {
+                if label_wrapper(d, bounds, "#36#", true) {
+                    state.draw_options.slices ^= true;
+                }
+            },
+            _ => { label_wrapper(d, bounds, "#36#", false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 542., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, if state.show_tilesets { "hide tilesets" } else { "show tilesets" }));
+        match state.sprites.get(state.active_sprite) {
+            Some(loaded) if !loaded.loaded_tilesets.is_empty() => {
+                if label_wrapper(d, bounds, "#111#", true) {
+                    state.show_tilesets ^= true;
+                }
+            },
+            _ => { label_wrapper(d, bounds, "#111#", false); },
+        };
+    }
+
+    {
+        let bounds = Rectangle{x: 570., y: (state.window_h - 24) as f32, width: 24., height: 24.};
+        tips.push((bounds, "recent files"));
+        if label_wrapper(d, bounds, "#5#", !state.recent.paths.is_empty()) {
+            state.show_recent_menu ^= true;
+        }
+    }
+
+    if state.eyedropper {
+        let m = d.get_mouse_position();
+        let sample = state.sprites.get(state.active_sprite)
+            .filter(|_| !mouse_over_chrome(state, m))
+            .and_then(|x| x.sample_pixel(d.get_screen_to_world2D(m, *cam)));
+
+        if let Some((pixel, [r, g, b, a], index)) = sample {
+            let swatch = Rectangle{x: 598., y: (state.window_h - 20) as f32, width: 16., height: 16.};
+            d.draw_rectangle_rec(swatch, Color::new(r, g, b, a));
+            d.draw_rectangle_lines_ex(swatch, 1., Color::WHITE);
+
+            let text = match index {
+                Some(i) => format!("{}, {}: #{r:02x}{g:02x}{b:02x}{a:02x} (index {i})", pixel.x, pixel.y),
+                None    => format!("{}, {}: #{r:02x}{g:02x}{b:02x}{a:02x}", pixel.x, pixel.y),
+            };
+            label_wrapper(d, Rectangle{x: 618., y: (state.window_h - 24) as f32, width: 200., height: 24.}, text.as_str(), false);
+        }
+    }
+
+    // what the sprite is goes against the right edge, the scrubber gets whatever
+    // room is left between
+    let summary = state.sprites.get(state.active_sprite).map(|x| format!(
+        "{}x{} {}, {} frame{}, {} layer{}{}",
+        x.image_width, x.image_height, x.colour_mode_name(),
+        x.frame_count, if x.frame_count == 1 { "" } else { "s" },
+        x.loaded_layers.len(), if x.loaded_layers.len() == 1 { "" } else { "s" },
+        match state.preview.len() {
+            0 => String::new(),
+            n => format!(", {n}/{PREVIEW_CACHE_FRAMES} previewed"),
+        },
+    ));
+
+    let summary_w = summary.as_ref().map_or(0., |x| (d.measure_text(x, FONT_SIZE_REG) + 16) as f32);
+    if let Some(summary) = &summary {
+        label_wrapper(d, Rectangle{x: state.window_w as f32 - summary_w, y: (state.window_h - 24) as f32, width: summary_w, height: 24.}, summary, false);
+    }
+
+    let track = Rectangle{
+        x:      SCRUBBER_X,
+        y:      (state.window_h - 20) as f32,
+        width:  state.window_w as f32 - SCRUBBER_X - 8. - summary_w,
+        height: 16.,
+    };
+
+    let timeline = state.sprites.get(state.active_sprite)
+        .filter(|x| x.frame_count > 0 && track.width >= SCRUBBER_MIN_WIDTH)
+        .map(|x| (x.frame_count, (0..x.loaded_tags.len()).filter_map(|i| x.tag_span(i)).collect::<Vec<_>>()));
+
+    if let Some((frame_count, spans)) = timeline {
+        tips.push((track, "drag to scrub through the frames"));
+        let frame_w = track.width / frame_count as f32;
+
+        for (from, to, colour) in spans {
+            d.draw_rectangle_rec(Rectangle{
+                x:     track.x + from as f32 * frame_w,
+                width: (to - from + 1) as f32 * frame_w,
+                ..track
+            }, Color{a: 96, ..colour});
+        }
+
+        d.draw_rectangle_lines_ex(track, 1., Color::GRAY);
+
+        let every = ((SCRUBBER_TICK_GAP / frame_w).ceil() as usize).max(1);
+        for i in (0..frame_count).step_by(every) {
+            let x = (track.x + i as f32 * frame_w) as i32;
+            d.draw_line(x, (track.y + track.height) as i32 - 4, x, (track.y + track.height) as i32, Color::GRAY);
+        }
+
+        let head = Rectangle{
+            x:     track.x + state.playback.frame() as f32 * frame_w,
+            width: frame_w.max(2.),
+            ..track
+        };
+        d.draw_rectangle_rec(head, Color{a: 128, ..Color::WHITE});
+        d.draw_rectangle_lines_ex(head, 1., Color::WHITE);
+
+        let m = d.get_mouse_position();
+        if d.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) && track.check_collision_point_rec(m) {
+            state.scrubbing = true;
+        }
+
+        if state.scrubbing {
+            let frame = ((m.x - track.x) / frame_w).clamp(0., (frame_count - 1) as f32) as usize;
+
+            // frames outside the tag being played go back to playing everything
+            if (state.playback.playing || frame != state.playback.frame()) && !state.playback.seek(frame) {
+                state.playback = Playback::all_frames(frame_count);
+                state.playback.seek(frame);
+            }
+
+            state.show_preview = true;
+        }
+    }
+
+    if !d.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+        state.scrubbing = false;
+    }
+
+    let frame_count = state.sprites.get(state.active_sprite).map(|x| x.frame_count);
+    if let (true, Some(frame_count)) = (state.show_sheet_menu, frame_count) {
+        let rect = Rectangle{height: 24., ..sheet_menu_rect(state)};
+
+        let layout = if d.gui_button(rect, "#12# horizontal") {
+            Some(SheetLayout::Horizontal)
+        } else if d.gui_button(Rectangle{y: rect.y + rect.height, ..rect}, "#12# vertical") {
+            Some(SheetLayout::Vertical)
+        } else if d.gui_button(Rectangle{y: rect.y + rect.height * 2., ..rect}, "#97# grid") {
+            Some(SheetLayout::square(frame_count))
+        } else {
+            None
+        };
+
+        if let Some(layout) = layout {
+            save_sheet(state, layout);
+            state.show_sheet_menu = false;
+        }
+    }
+
+    if state.show_recent_menu {
+        let rect = Rectangle{height: 24., ..recent_menu_rect(state)};
+        let mut picked = None;
+
+        for (i, path) in state.recent.paths.iter().enumerate() {
+            let text = fit_path(d, path, rect.width as i32 - 16);
+            if d.gui_button(Rectangle{y: rect.y + rect.height * i as f32, ..rect}, text.as_str()) {
+                picked = Some(path.clone());
+            }
+        }
+
+        if picked.is_some() {
+            state.recent_picked = picked;
+            state.show_recent_menu = false;
+        }
+    }
+
+    for (bounds, text) in tips {
+        tooltip(d, bounds, text);
+    }
 }
\ No newline at end of file
@@ -1,3 +1,75 @@
+use raylib::{math::{Rectangle, Vector2}, prelude::RaylibDrawHandle, RaylibHandle};
+
+use std::{fmt::Display, str::FromStr};
+
 pub(crate) trait ExpirableElement {
     fn is_alive(&self) -> bool;
+}
+
+/// Something drawn over the rest of the window for a while, like a toast. The
+/// ones being shown are stacked up from a corner of the window, one after
+/// another.
+pub(crate) trait Overlay: ExpirableElement {
+    /// Moves it on by a frame, letting it react to the mouse.
+    fn step(&mut self, rl: &RaylibHandle);
+
+    /// Draws it `offset` pixels along from `corner` of `area`, which is the part
+    /// of the window overlays can go in.
+    fn draw(&mut self, offset: f32, d: &mut RaylibDrawHandle, area: Rectangle, corner: OverlayCorner);
+
+    /// Gets how much of the stack it took up when it was last drawn.
+    fn height(&self) -> f32;
+
+    /// Checks if a point on screen is over it, as of when it was last drawn.
+    fn contains(&self, point: Vector2) -> bool;
+}
+
+/// Which corner of the window overlays stack up from.
+#[derive(Clone, Copy, Default)]
+pub(crate) enum OverlayCorner {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl OverlayCorner {
+    /// Checks if overlays stack down from the top of the window, otherwise they
+    /// stack up from the bottom.
+    pub fn is_top(self) -> bool {
+        matches!(self, Self::TopLeft | Self::TopRight)
+    }
+
+    /// Checks if overlays line up with the left of the window.
+    pub fn is_left(self) -> bool {
+        matches!(self, Self::TopLeft | Self::BottomLeft)
+    }
+}
+
+impl FromStr for OverlayCorner {
+    type Err = ();
+
+    /// Reads a corner written like `top-left` or `bottom right`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace([' ', '_'], "-").as_str() {
+            "top-left"     => Ok(Self::TopLeft),
+            "top-right"    => Ok(Self::TopRight),
+            "bottom-left"  => Ok(Self::BottomLeft),
+            "bottom-right" => Ok(Self::BottomRight),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for OverlayCorner {
+    /// Writes the corner the way `from_str` reads it.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TopLeft     => f.write_str("top-left"),
+            Self::TopRight    => f.write_str("top-right"),
+            Self::BottomLeft  => f.write_str("bottom-left"),
+            Self::BottomRight => f.write_str("bottom-right"),
+        }
+    }
 }
\ No newline at end of file